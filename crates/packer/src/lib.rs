@@ -13,6 +13,8 @@ pub struct Packer {
     max_height: u16,
     /// Skylines for the skyline packing algorithm.
     skylines: Vec<Skyline>,
+    /// Removed rectangles below the skylines that can be filled again.
+    free_rectangles: Vec<Rectangle>,
 }
 
 impl Packer {
@@ -34,10 +36,14 @@ impl Packer {
         };
         let skylines = vec![skyline];
 
+        // Nothing is removed yet
+        let free_rectangles = Vec::new();
+
         Self {
             max_width,
             max_height,
             skylines,
+            free_rectangles,
         }
     }
 
@@ -151,6 +157,11 @@ impl Packer {
     pub fn insert(&mut self, rectangle_size: impl Into<(u16, u16)>) -> Option<(u16, u16)> {
         let (rectangle_width, rectangle_height) = rectangle_size.into();
 
+        // Fill the space of removed rectangles first
+        if let Some(offset) = self.insert_in_free_rectangle(rectangle_width, rectangle_height) {
+            return Some(offset);
+        }
+
         // Find the rectangle with the skyline, keep the bottom and width as small as possible
         let mut bottom = u16::MAX;
         let mut width = u16::MAX;
@@ -182,6 +193,159 @@ impl Packer {
         Some((x, y))
     }
 
+    /// Remove a packed rectangle so its space can be filled again.
+    ///
+    /// When the rectangle is directly below the skylines they are lowered, otherwise the space is kept for rectangles that fit inside it.
+    ///
+    /// # Arguments
+    ///
+    /// * `(x, y, width, height)` - Tuple rectangle with the offset returned by [`Self::insert`] and the size passed to it.
+    #[inline]
+    pub fn remove(&mut self, rectangle: impl Into<(u16, u16, u16, u16)>) {
+        let (x, y, width, height) = rectangle.into();
+
+        // Nothing to free
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        self.free_rectangles.push(Rectangle {
+            x,
+            y,
+            width,
+            height,
+        });
+
+        // Combine neighboring free rectangles so bigger rectangles fit inside them
+        self.merge_free_rectangles();
+
+        // Give the free rectangles that are directly below the skylines back to them, which can free other rectangles above them
+        while let Some(index) = self
+            .free_rectangles
+            .iter()
+            .position(|free_rectangle| self.can_lower(*free_rectangle))
+        {
+            let free_rectangle = self.free_rectangles.swap_remove(index);
+            self.lower(free_rectangle);
+        }
+    }
+
+    /// Put a rectangle in the smallest free rectangle it fits in.
+    fn insert_in_free_rectangle(&mut self, width: u16, height: u16) -> Option<(u16, u16)> {
+        // Find the best fitting free rectangle
+        let (index, free_rectangle) = self
+            .free_rectangles
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, free_rectangle)| {
+                free_rectangle.width >= width && free_rectangle.height >= height
+            })
+            .min_by_key(|(_, free_rectangle)| free_rectangle.area())?;
+        self.free_rectangles.swap_remove(index);
+
+        // Keep the space to the right and below the rectangle free
+        let right = Rectangle {
+            x: free_rectangle.x + width,
+            y: free_rectangle.y,
+            width: free_rectangle.width - width,
+            height,
+        };
+        let below = Rectangle {
+            x: free_rectangle.x,
+            y: free_rectangle.y + height,
+            width: free_rectangle.width,
+            height: free_rectangle.height - height,
+        };
+        self.free_rectangles.extend(
+            [right, below]
+                .into_iter()
+                .filter(|rectangle| rectangle.area() > 0),
+        );
+
+        Some((free_rectangle.x, free_rectangle.y))
+    }
+
+    /// Merge free rectangles sharing a whole side until none can be merged anymore.
+    fn merge_free_rectangles(&mut self) {
+        'merge: loop {
+            for index in 0..self.free_rectangles.len() {
+                for other_index in (index + 1)..self.free_rectangles.len() {
+                    if let Some(merged) =
+                        self.free_rectangles[index].merge(self.free_rectangles[other_index])
+                    {
+                        self.free_rectangles[index] = merged;
+                        self.free_rectangles.swap_remove(other_index);
+
+                        // Merged rectangle can be merged with the ones already checked
+                        continue 'merge;
+                    }
+                }
+            }
+
+            break;
+        }
+    }
+
+    /// Whether all skylines above the free rectangle are at its bottom.
+    fn can_lower(&self, free_rectangle: Rectangle) -> bool {
+        let bottom = free_rectangle.y + free_rectangle.height;
+
+        self.skylines
+            .iter()
+            .filter(|skyline| {
+                skyline.left() < free_rectangle.x + free_rectangle.width
+                    && skyline.right() > free_rectangle.x
+            })
+            .all(|skyline| skyline.y == bottom)
+    }
+
+    /// Lower the skylines above the free rectangle to its top.
+    fn lower(&mut self, free_rectangle: Rectangle) {
+        let left = free_rectangle.x;
+        let right = free_rectangle.x + free_rectangle.width;
+
+        let mut skylines = Vec::with_capacity(self.skylines.len() + 2);
+        for skyline in &self.skylines {
+            if skyline.right() <= left || skyline.left() >= right {
+                // Skyline is not above the rectangle
+                skylines.push(*skyline);
+
+                continue;
+            }
+
+            // Keep the part to the left of the rectangle
+            if skyline.left() < left {
+                skylines.push(Skyline {
+                    x: skyline.x,
+                    y: skyline.y,
+                    width: left - skyline.left(),
+                });
+            }
+
+            // Lower the part above the rectangle
+            let lowered_left = skyline.left().max(left);
+            skylines.push(Skyline {
+                x: lowered_left,
+                y: free_rectangle.y,
+                width: skyline.right().min(right) - lowered_left,
+            });
+
+            // Keep the part to the right of the rectangle
+            if skyline.right() > right {
+                skylines.push(Skyline {
+                    x: right,
+                    y: skyline.y,
+                    width: skyline.right() - right,
+                });
+            }
+        }
+        self.skylines = skylines;
+
+        // Merge the skylines on the same height
+        self.merge();
+    }
+
     /// Return the rect fitting in a skyline if possible.
     fn can_put(&self, skyline_index: usize, width: u16, height: u16) -> Option<(u16, u16)> {
         // Right side of the rectangle, doesn't change because only the Y position will shift in the next loop
@@ -302,6 +466,55 @@ impl Skyline {
     }
 }
 
+/// Free space below the skylines.
+#[derive(Debug, Clone, Copy)]
+struct Rectangle {
+    /// X position on the rectangle.
+    x: u16,
+    /// Y position on the rectangle.
+    y: u16,
+    /// Horizontal size.
+    width: u16,
+    /// Vertical size.
+    height: u16,
+}
+
+impl Rectangle {
+    /// Amount of pixels.
+    #[inline(always)]
+    pub const fn area(self) -> u32 {
+        self.width as u32 * self.height as u32
+    }
+
+    /// Combine with another rectangle into a single rectangle if they share a whole side.
+    #[inline(always)]
+    pub const fn merge(self, other: Self) -> Option<Self> {
+        if self.x == other.x && self.width == other.width {
+            // Vertical neighbors
+            if self.y + self.height == other.y || other.y + other.height == self.y {
+                return Some(Self {
+                    x: self.x,
+                    y: if self.y < other.y { self.y } else { other.y },
+                    width: self.width,
+                    height: self.height + other.height,
+                });
+            }
+        } else if self.y == other.y && self.height == other.height {
+            // Horizontal neighbors
+            if self.x + self.width == other.x || other.x + other.width == self.x {
+                return Some(Self {
+                    x: if self.x < other.x { self.x } else { other.x },
+                    y: self.y,
+                    width: self.width + other.width,
+                    height: self.height,
+                });
+            }
+        }
+
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -357,4 +570,34 @@ mod tests {
         }
         assert!(packer.insert((4, 4)).is_none());
     }
+
+    #[test]
+    fn remove_rects() {
+        // Removing a single block from a full box should make space for exactly one block again
+        let mut packer = Packer::new((32, 32));
+        let offsets = (0..64)
+            .map(|_| packer.insert((4, 4)).unwrap())
+            .collect::<Vec<_>>();
+        let (x, y) = offsets[9];
+        packer.remove((x, y, 4, 4));
+        assert_eq!(packer.insert((4, 4)), Some((x, y)));
+        assert!(packer.insert((4, 4)).is_none());
+
+        // Removing 4 neighboring blocks from a full box should make space for a single bigger block
+        for (x, y) in [(8, 8), (12, 8), (8, 12), (12, 12)] {
+            packer.remove((x, y, 4, 4));
+        }
+        assert_eq!(packer.insert((8, 8)), Some((8, 8)));
+        assert!(packer.insert((4, 4)).is_none());
+
+        // Removing everything should make space for a block of the full size
+        let mut packer = Packer::new((32, 32));
+        let offsets = (0..64)
+            .map(|_| packer.insert((4, 4)).unwrap())
+            .collect::<Vec<_>>();
+        for (x, y) in offsets {
+            packer.remove((x, y, 4, 4));
+        }
+        assert_eq!(packer.insert((32, 32)), Some((0, 0)));
+    }
 }
@@ -0,0 +1,120 @@
+//! Show how a camera can follow a player through a world bigger than the screen.
+//!
+//! Move with the arrow keys, zoom with Z and X, and shake the camera by holding space.
+//! The sprite under the mouse is drawn in the world, the text is drawn on the screen.
+//!
+//! The `threeforms.png` sprite image for this example is:
+//! {{ img(src="/assets/threeforms.png" alt="Sprite") }}
+//! With the following `threeforms.ron` RON configuration file for positioning the center of the sprite:
+//! ```ron
+//! (offset: Middle)
+//! ```
+
+use chuot::{context::KeyCode, Config, Context, Game};
+
+/// Size of the world in pixels.
+const WORLD_SIZE: f32 = 480.0;
+
+/// Define a game state for our example.
+struct GameState {
+    /// Horizontal position of the player in the world.
+    x: f32,
+    /// Vertical position of the player in the world.
+    y: f32,
+    /// Zoom factor of the camera.
+    zoom: u32,
+}
+
+impl Game for GameState {
+    /// Update the game.
+    fn update(&mut self, ctx: Context) {
+        // Move the player with the arrow keys
+        let speed = 100.0 * ctx.delta_time();
+        if ctx.key_held(KeyCode::ArrowLeft) {
+            self.x -= speed;
+        }
+        if ctx.key_held(KeyCode::ArrowRight) {
+            self.x += speed;
+        }
+        if ctx.key_held(KeyCode::ArrowUp) {
+            self.y -= speed;
+        }
+        if ctx.key_held(KeyCode::ArrowDown) {
+            self.y += speed;
+        }
+
+        // Zoom with the Z and X keys
+        if ctx.key_pressed(KeyCode::KeyZ) {
+            self.zoom = (self.zoom + 1).min(4);
+        }
+        if ctx.key_pressed(KeyCode::KeyX) {
+            self.zoom = (self.zoom - 1).max(1);
+        }
+
+        // Shake the camera when holding space
+        let shake = if ctx.key_held(KeyCode::Space) {
+            (chuot::random(-2.0, 2.0), chuot::random(-2.0, 2.0))
+        } else {
+            (0.0, 0.0)
+        };
+
+        // Follow the player without showing anything outside of the world
+        ctx.camera()
+            .center((self.x, self.y))
+            .zoom(self.zoom)
+            .shake(shake)
+            .bounds((0.0, 0.0, WORLD_SIZE, WORLD_SIZE))
+            .apply();
+    }
+
+    /// Render the game.
+    fn render(&mut self, ctx: Context) {
+        // Draw a grid of sprites filling the world
+        ctx.sprite("threeforms")
+            .draw_multiple_translated((0..10).flat_map(|y| {
+                (0..10).map(move |x| {
+                    (
+                        (x as f32).mul_add(48.0, 24.0),
+                        (y as f32).mul_add(48.0, 24.0),
+                    )
+                })
+            }));
+
+        // Draw the player
+        ctx.sprite("threeforms")
+            .translate((self.x, self.y))
+            .tint(0xFF_FF_80_80)
+            .draw();
+
+        // Draw a sprite in the world below the mouse
+        if let Some(mouse) = ctx.mouse_world() {
+            ctx.sprite("threeforms").translate(mouse).alpha(0.5).draw();
+        }
+
+        // Draw a HUD that isn't moved by the camera
+        ctx.text("Beachball", &format!("ZOOM {}", self.zoom))
+            .in_screen_space()
+            .draw();
+    }
+}
+
+/// Open an empty window.
+fn main() {
+    // Game configuration
+    let config = Config {
+        buffer_width: 240.0,
+        buffer_height: 192.0,
+        // Apply a minimum of 3 times scaling for the buffer
+        // Will result in a minimum, and on web exact, window size of 720x576
+        scaling: 3.0,
+        ..Default::default()
+    };
+
+    // Spawn the window and run the 'game'
+    GameState {
+        x: WORLD_SIZE / 2.0,
+        y: WORLD_SIZE / 2.0,
+        zoom: 1,
+    }
+    .run(chuot::load_assets!(), config);
+}
@@ -9,7 +9,7 @@
 //! ```
 
 use chuot::{
-    assets::{loadable::Loadable, loader::Loader, Id},
+    assets::{loadable::Loadable, loader::Loader, AssetError, Id},
     context::ContextInner,
     Config, Context, Game,
 };
@@ -21,8 +21,8 @@ impl Loader<String> for TxtLoader {
     const EXTENSION: &'static str = "txt";
 
     /// Load the bytes into UTF-8, ignoring invalid characters.
-    fn load(bytes: &[u8], _id: &Id) -> String {
-        String::from_utf8_lossy(bytes).into_owned()
+    fn load(bytes: &[u8], _id: &Id) -> Result<String, AssetError> {
+        Ok(String::from_utf8_lossy(bytes).into_owned())
     }
 }
 
@@ -30,14 +30,14 @@ impl Loader<String> for TxtLoader {
 struct TxtString(pub String);
 
 impl Loadable for TxtString {
-    fn load_if_exists(id: &Id, ctx: &mut ContextInner) -> Option<Self>
+    fn load_if_exists(id: &Id, ctx: &mut ContextInner) -> Result<Option<Self>, AssetError>
     where
         Self: Sized,
     {
         // Use the created loader to load a txt asset
         let text = ctx.asset_source.load_if_exists::<TxtLoader, _>(id)?;
 
-        Some(Self(text))
+        Ok(text.map(Self))
     }
 }
 
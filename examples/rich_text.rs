@@ -0,0 +1,53 @@
+//! Show how text can be styled inline with markup.
+//!
+//! The `Beachball.png` font image for this example is:
+//! {{ img(src="/assets/Beachball.png" alt="Font") }}
+//! With the following `Beachball.ron` RON file:
+//! ```ron
+//! (glyph_width: 10,  glyph_height: 10)
+//! ```
+
+use chuot::{Config, Context, Game};
+
+/// Define a game state for our example.
+#[derive(Default)]
+struct GameState {
+    /// Time driving the text effects.
+    time: f32,
+}
+
+impl Game for GameState {
+    /// Update the game.
+    fn update(&mut self, ctx: Context) {
+        self.time += ctx.delta_time();
+    }
+
+    /// Render the game.
+    fn render(&mut self, ctx: Context) {
+        // Parse the markup and animate the effects
+        ctx.text(
+            "Beachball",
+            "[color=#FF0]GOLD[/color]\n[wave]WAVY[/wave]\n[shake]SCARY[/shake]\n[icon=crate] CRATE",
+        )
+        .translate((2.0, 2.0))
+        .markup()
+        .time(self.time)
+        .draw();
+    }
+}
+
+/// Open an empty window.
+fn main() {
+    // Game configuration
+    let config = Config {
+        buffer_width: 120.0,
+        buffer_height: 96.0,
+        // Apply a minimum of 6 times scaling for the buffer
+        // Will result in a minimum, and on web exact, window size of 720x576
+        scaling: 6.0,
+        ..Default::default()
+    };
+
+    // Spawn the window and run the 'game'
+    GameState::default().run(chuot::load_assets!(), config);
+}
@@ -0,0 +1,97 @@
+//! Show how the buffer can be split into two viewports, each following a different player.
+//!
+//! Move the first player with WASD and the second player with the arrow keys.
+//!
+//! The `threeforms.png` sprite image for this example is:
+//! {{ img(src="/assets/threeforms.png" alt="Sprite") }}
+//! With the following `threeforms.ron` RON configuration file for positioning the center of the sprite:
+//! ```ron
+//! (offset: Middle)
+//! ```
+
+use chuot::{context::KeyCode, Config, Context, Game};
+
+/// Define a game state for our example.
+struct GameState {
+    /// Positions of both players in the world.
+    players: [(f32, f32); 2],
+}
+
+impl Game for GameState {
+    /// Update the game.
+    fn update(&mut self, ctx: Context) {
+        let speed = 100.0 * ctx.delta_time();
+
+        // Move each player with its own keys
+        for ((x, y), [left, right, up, down]) in self.players.iter_mut().zip([
+            [KeyCode::KeyA, KeyCode::KeyD, KeyCode::KeyW, KeyCode::KeyS],
+            [
+                KeyCode::ArrowLeft,
+                KeyCode::ArrowRight,
+                KeyCode::ArrowUp,
+                KeyCode::ArrowDown,
+            ],
+        ]) {
+            if ctx.key_held(left) {
+                *x -= speed;
+            }
+            if ctx.key_held(right) {
+                *x += speed;
+            }
+            if ctx.key_held(up) {
+                *y -= speed;
+            }
+            if ctx.key_held(down) {
+                *y += speed;
+            }
+        }
+
+        // Split the buffer into a left and right half, existing cameras are kept so this can be called every update
+        let (width, height) = ctx.size();
+        ctx.set_viewports([
+            (0.0, 0.0, width / 2.0, height),
+            (width / 2.0, 0.0, width / 2.0, height),
+        ]);
+
+        // Let the camera of each viewport follow its player
+        for (viewport, player) in self.players.iter().enumerate() {
+            ctx.viewport_camera(viewport).center(*player).apply();
+        }
+    }
+
+    /// Render the game.
+    fn render(&mut self, ctx: Context) {
+        // Draw both players, they are visible in both viewports when close to each other
+        for (player, tint) in self.players.iter().zip([0xFF_FF_80_80, 0xFF_80_80_FF]) {
+            ctx.sprite("threeforms")
+                .translate(*player)
+                .tint(tint)
+                .draw();
+        }
+
+        // Draw a divider between the viewports
+        ctx.text("Beachball", "|")
+            .translate((ctx.width() / 2.0 - 2.0, ctx.height() / 2.0))
+            .in_screen_space()
+            .draw();
+    }
+}
+
+/// Open an empty window.
+fn main() {
+    // Game configuration
+    let config = Config {
+        buffer_width: 240.0,
+        buffer_height: 192.0,
+        // Apply a minimum of 3 times scaling for the buffer
+        // Will result in a minimum, and on web exact, window size of 720x576
+        scaling: 3.0,
+        ..Default::default()
+    };
+
+    // Spawn the window and run the 'game'
+    GameState {
+        players: [(60.0, 96.0), (180.0, 96.0)],
+    }
+    .run(chuot::load_assets!(), config);
+}
@@ -0,0 +1,74 @@
+//! Show how a sprite can be tinted, faded, flashed and blended.
+//!
+//! The `threeforms.png` sprite image for this example is:
+//! {{ img(src="/assets/threeforms.png" alt="Sprite") }}
+//! With the following `threeforms.ron` RON configuration file for positioning the center of the sprite:
+//! ```ron
+//! (offset: Middle)
+//! ```
+
+use chuot::{context::BlendMode, Config, Context, Game};
+
+/// Define a game state for our example.
+#[derive(Default)]
+struct GameState {
+    /// Time passed, used for animating the colors.
+    time: f32,
+}
+
+impl Game for GameState {
+    /// Update the game.
+    fn update(&mut self, ctx: Context) {
+        // Increment the time with with the timestep so it animates smoothly
+        self.time += ctx.delta_time();
+    }
+
+    /// Render the game.
+    fn render(&mut self, ctx: Context) {
+        let (width, height) = ctx.size();
+
+        // Draw the sprite with a red tint
+        ctx.sprite("threeforms")
+            .translate((width / 5.0, height / 2.0))
+            .tint(0xFFFF8080)
+            .draw();
+
+        // Draw the sprite fading in and out
+        ctx.sprite("threeforms")
+            .translate((width * 2.0 / 5.0, height / 2.0))
+            .alpha(self.time.sin().mul_add(0.5, 0.5))
+            .draw();
+
+        // Draw the sprite flashing white like it's being hit
+        ctx.sprite("threeforms")
+            .translate((width * 3.0 / 5.0, height / 2.0))
+            .flash(0xFFFFFFFF, (self.time * 4.0).sin().max(0.0))
+            .draw();
+
+        // Draw the sprite twice on top of each other with additive blending, making it glow
+        ctx.sprite("threeforms")
+            .translate((width * 4.0 / 5.0, height / 2.0))
+            .draw();
+        ctx.sprite("threeforms")
+            .translate((width * 4.0 / 5.0, height / 2.0))
+            .blend_mode(BlendMode::Additive)
+            .alpha(0.5)
+            .draw();
+    }
+}
+
+/// Open an empty window.
+fn main() {
+    // Game configuration
+    let config = Config {
+        buffer_width: 240.0,
+        buffer_height: 192.0,
+        // Apply a minimum of 3 times scaling for the buffer
+        // Will result in a minimum, and on web exact, window size of 720x576
+        scaling: 3.0,
+        ..Default::default()
+    };
+
+    // Spawn the window and run the 'game'
+    GameState::default().run(chuot::load_assets!(), config);
+}
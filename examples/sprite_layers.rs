@@ -0,0 +1,66 @@
+//! Show how sprites can be drawn on named layers and ordered with a z-index, regardless of the order of the draw calls.
+//!
+//! The `threeforms.png` sprite image for this example is:
+//! {{ img(src="/assets/threeforms.png" alt="Sprite") }}
+//! With the following `threeforms.ron` RON configuration file for positioning the center of the sprite:
+//! ```ron
+//! (offset: Middle)
+//! ```
+
+use chuot::{Config, Context, Game};
+
+/// Define a game state for our example.
+#[derive(Default)]
+struct GameState {
+    /// Time passed, used for moving the sprites.
+    time: f32,
+}
+
+impl Game for GameState {
+    /// Update the game.
+    fn update(&mut self, ctx: Context) {
+        // Increment the time with with the timestep so it animates smoothly
+        self.time += ctx.delta_time();
+    }
+
+    /// Render the game.
+    fn render(&mut self, ctx: Context) {
+        let (width, height) = ctx.size();
+
+        // Draw the foreground first, it will still be shown on top because of the layer
+        ctx.sprite("threeforms")
+            .translate((width / 2.0, height / 2.0))
+            .layer("foreground")
+            .tint(0xFF_FF_80_80)
+            .draw();
+
+        // Draw sprites moving around on the default first layer, sorted by their vertical position
+        for index in 0..3 {
+            let offset = (index as f32).mul_add(2.0, self.time);
+            let y = offset.sin().mul_add(height / 4.0, height / 2.0);
+
+            ctx.sprite("threeforms")
+                .translate((offset.cos().mul_add(width / 4.0, width / 2.0), y))
+                .z(y as i32)
+                .draw();
+        }
+    }
+}
+
+/// Open an empty window.
+fn main() {
+    // Game configuration
+    let config = Config {
+        buffer_width: 240.0,
+        buffer_height: 192.0,
+        // Apply a minimum of 3 times scaling for the buffer
+        // Will result in a minimum, and on web exact, window size of 720x576
+        scaling: 3.0,
+        ..Default::default()
+    }
+    // Define the layers, drawn from first to last
+    .with_layers(["entities", "foreground"]);
+
+    // Spawn the window and run the 'game'
+    GameState::default().run(chuot::load_assets!(), config);
+}
@@ -0,0 +1,65 @@
+//! Show how a sprite can be scaled, flipped and rotated around a custom pivot.
+//!
+//! The `threeforms.png` sprite image for this example is:
+//! {{ img(src="/assets/threeforms.png" alt="Sprite") }}
+//! With the following `threeforms.ron` RON configuration file for positioning the center of the sprite:
+//! ```ron
+//! (offset: Middle)
+//! ```
+
+use chuot::{Config, Context, Game};
+
+/// Define a game state for our example.
+#[derive(Default)]
+struct GameState {
+    /// Time passed, used for animating the transformations.
+    time: f32,
+}
+
+impl Game for GameState {
+    /// Update the game.
+    fn update(&mut self, ctx: Context) {
+        // Increment the time with with the timestep so it animates smoothly
+        self.time += ctx.delta_time();
+    }
+
+    /// Render the game.
+    fn render(&mut self, ctx: Context) {
+        let (width, height) = ctx.size();
+
+        // Draw the sprite flipped every second, like a character changing the direction it's facing
+        ctx.sprite("threeforms")
+            .translate((width / 4.0, height / 2.0))
+            .flip_x(self.time as u32 % 2 == 0)
+            .draw();
+
+        // Draw the sprite stretched with a non-uniform scale
+        ctx.sprite("threeforms")
+            .translate((width / 2.0, height / 2.0))
+            .scale((1.0, self.time.sin().mul_add(0.5, 1.5)))
+            .draw();
+
+        // Draw the sprite rotating around its left top corner instead of the middle
+        ctx.sprite("threeforms")
+            .translate((width * 3.0 / 4.0, height / 2.0))
+            .pivot((0.0, 0.0))
+            .rotate(self.time)
+            .draw();
+    }
+}
+
+/// Open an empty window.
+fn main() {
+    // Game configuration
+    let config = Config {
+        buffer_width: 240.0,
+        buffer_height: 192.0,
+        // Apply a minimum of 3 times scaling for the buffer
+        // Will result in a minimum, and on web exact, window size of 720x576
+        scaling: 3.0,
+        ..Default::default()
+    };
+
+    // Spawn the window and run the 'game'
+    GameState::default().run(chuot::load_assets!(), config);
+}
@@ -0,0 +1,62 @@
+//! Show how text can be measured, wrapped and aligned.
+//!
+//! The `Beachball.png` font image for this example is:
+//! {{ img(src="/assets/Beachball.png" alt="Font") }}
+//! With the following `Beachball.ron` RON file:
+//! ```ron
+//! (glyph_width: 10,  glyph_height: 10)
+//! ```
+
+use chuot::{
+    context::text::{TextAlign, VerticalAnchor},
+    Config, Context, Game,
+};
+
+/// Define a game state for our example.
+#[derive(Default)]
+struct GameState;
+
+impl Game for GameState {
+    /// Render the game.
+    fn render(&mut self, ctx: Context) {
+        // Draw a title centered at the top of the screen
+        ctx.text("Beachball", "TITLE")
+            .translate((ctx.width() / 2.0, 2.0))
+            .align(TextAlign::Center)
+            .draw();
+
+        // Wrap a longer text inside the width of the screen, centered in the middle
+        ctx.text("Beachball", "wrapped text in the middle")
+            .translate((ctx.width() / 2.0, ctx.height() / 2.0))
+            .max_width(ctx.width() - 4.0)
+            .align(TextAlign::Center)
+            .anchor(VerticalAnchor::Middle)
+            .line_height(12.0)
+            .draw();
+
+        // Measure the text to draw it exactly in the right bottom corner
+        let (width, height) = ctx.text_size("Beachball", "END");
+        ctx.text("Beachball", "END")
+            .translate((ctx.width() - width - 2.0, ctx.height() - height - 2.0))
+            .draw();
+    }
+
+    /// Do nothing during the update loop.
+    fn update(&mut self, _ctx: Context) {}
+}
+
+/// Open an empty window.
+fn main() {
+    // Game configuration
+    let config = Config {
+        buffer_width: 120.0,
+        buffer_height: 96.0,
+        // Apply a minimum of 6 times scaling for the buffer
+        // Will result in a minimum, and on web exact, window size of 720x576
+        scaling: 6.0,
+        ..Default::default()
+    };
+
+    // Spawn the window and run the 'game'
+    GameState.run(chuot::load_assets!(), config);
+}
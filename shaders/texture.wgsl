@@ -20,8 +20,10 @@ struct TextureInfo {
 
 struct ScreenInfo {
     @location(0) size: vec2<f32>,
-    // WASM needs the types to be aligned to 16 bytes
-    @location(1) _padding: vec2<f32>,
+    // Translation of the camera
+    @location(1) camera_translation: vec2<f32>,
+    // Rotation and zoom of the camera, as the columns of a 2x2 matrix
+    @location(2) camera_matrix: vec4<f32>,
 }
 
 @group(1) @binding(0)
@@ -45,13 +47,23 @@ struct InstanceInput {
     @location(4) sub_rectangle: vec4<f32>,
     // Which texture to render, dimensions are stored in the uniform buffer
     @location(5) tex_index: u32,
+    // Color multiplied with each pixel, packed as BGRA
+    @location(6) tint: vec4<f32>,
+    // Solid color mixed with each pixel, packed as BGRA with the alpha being the amount
+    @location(7) flash: vec4<f32>,
+    // Whether to ignore the camera, `1` when true
+    @location(8) screen_space: u32,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
-    // Whether any matrix operation besides simple translation and reflection is applied
-    @location(1) @interpolate(flat) only_translated_or_reflected: f32,
+    // Whether any matrix operation besides simple translation, reflection and integer scaling is applied
+    @location(1) @interpolate(flat) pixel_aligned: f32,
+    // Color multiplied with each pixel in linear color space
+    @location(2) @interpolate(flat) tint: vec4<f32>,
+    // Solid color mixed with each pixel in linear color space, alpha is the amount
+    @location(3) @interpolate(flat) flash: vec4<f32>,
 }
 
 @vertex
@@ -75,9 +87,19 @@ fn vs_main(
     // Translate, rotate and skew with the instance matrix
     let projected_position = instance_matrix * vec3<f32>(model_position, 1.0);
 
+    // Move the world with the camera, unless the instance is drawn in screen space
+    let is_screen_space = instance.screen_space != 0u;
+    let camera_matrix = mat2x2<f32>(screen_info.camera_matrix.xy, screen_info.camera_matrix.zw);
+    let camera_position = camera_matrix * projected_position.xy + screen_info.camera_translation;
+    let position = select(camera_position, projected_position.xy, is_screen_space);
+
+    // Combine the rotation, skewing and scaling of the camera and the instance
+    let combined_matrix = camera_matrix * mat2x2<f32>(instance.matrix.xy, instance.matrix.zw);
+    let matrix = select(vec4<f32>(combined_matrix[0], combined_matrix[1]), instance.matrix, is_screen_space);
+
     // Move from 0..width to -1..1
     let screen_size_half = screen_info.size / 2.0;
-    let screen_offset = 1.0 - projected_position.xy / screen_size_half;
+    let screen_offset = 1.0 - position / screen_size_half;
     // Move the 0..1 texture coordinates to relative coordinates within the 4096x4096 atlas texture for the specified texture
     // Also apply the sub rectangle offset from the instance
     let tex_coords = (offset + instance.sub_rectangle.xy + instance.sub_rectangle.zw * model.tex_coords) / ATLAS_TEXTURE_SIZE;
@@ -86,14 +108,19 @@ fn vs_main(
     out.tex_coords = tex_coords;
     out.clip_position = vec4<f32>(-screen_offset.x, screen_offset.y, model.position.z, 1.0);
 
-    // Check if we have any skewing, scaling or rotation
-    out.only_translated_or_reflected = f32( 
-        abs(instance.matrix.x) == 1.0 &&
-        instance.matrix.y == 0.0 &&
-        instance.matrix.z == 0.0 &&
-        abs(instance.matrix.w) == 1.0
+    // Check if we have any skewing, rotation or non-integer scaling
+    // Flipped and integer scaled sprites map every texel to whole pixels so they don't need to be upscaled
+    out.pixel_aligned = f32(
+        matrix.y == 0.0 &&
+        matrix.z == 0.0 &&
+        fract(matrix.x) == 0.0 &&
+        fract(matrix.w) == 0.0
     );
 
+    // Convert the sRGB colors to linear colors, the alpha is already linear
+    out.tint = vec4<f32>(pow(instance.tint.zyx, vec3<f32>(2.2)), instance.tint.w);
+    out.flash = vec4<f32>(pow(instance.flash.zyx, vec3<f32>(2.2)), instance.flash.w);
+
     return out;
 }
 
@@ -496,14 +523,24 @@ fn slice_dist(
     return vec4(-1.0);
 }
 
+// Apply the tint and flash colors, and premultiply the alpha for the blend modes.
+fn apply_style(color: vec4<f32>, in: VertexOutput) -> vec4<f32> {
+    // Multiply with the tint
+    let tinted = color * in.tint;
+
+    // Mix with the solid flash color
+    let rgb = mix(tinted.rgb, in.flash.rgb, in.flash.a);
+
+    return vec4<f32>(rgb * tinted.a, tinted.a);
+}
+
 // Scale3x
-@fragment
-fn fs_main_scale3x(in: VertexOutput) -> @location(0) vec4<f32> {
+fn sample_scale3x(in: VertexOutput) -> vec4<f32> {
     // Take the sample of the exact pixel
     let c = textureSample(t_diffuse, s_diffuse, in.tex_coords);
 
-    // Don't apply the algorithm when no rotations or skewing occurs
-    if in.only_translated_or_reflected == 1.0 {
+    // Don't apply the algorithm when no rotations, skewing or non-integer scaling occurs
+    if in.pixel_aligned == 1.0 {
         return c;
     }
 
@@ -525,13 +562,12 @@ fn fs_main_scale3x(in: VertexOutput) -> @location(0) vec4<f32> {
 }
 
 // Diag2x
-@fragment
-fn fs_main_diag2x(in: VertexOutput) -> @location(0) vec4<f32> {
+fn sample_diag2x(in: VertexOutput) -> vec4<f32> {
     // Take the sample of the exact pixel
     let c = textureSample(t_diffuse, s_diffuse, in.tex_coords);
 
-    // Don't apply the algorithm when no rotations or skewing occurs
-    if in.only_translated_or_reflected == 1.0 {
+    // Don't apply the algorithm when no rotations, skewing or non-integer scaling occurs
+    if in.pixel_aligned == 1.0 {
         return c;
     }
 
@@ -553,13 +589,12 @@ fn fs_main_diag2x(in: VertexOutput) -> @location(0) vec4<f32> {
 }
 
 // Scale2x
-@fragment
-fn fs_main_scale2x(in: VertexOutput) -> @location(0) vec4<f32> {
+fn sample_scale2x(in: VertexOutput) -> vec4<f32> {
     // Take the sample of the exact pixel
     let c = textureSample(t_diffuse, s_diffuse, in.tex_coords);
 
-    // Don't apply the algorithm when no rotations or skewing occurs
-    if in.only_translated_or_reflected == 1.0 {
+    // Don't apply the algorithm when no rotations, skewing or non-integer scaling occurs
+    if in.pixel_aligned == 1.0 {
         return c;
     }
 
@@ -577,20 +612,18 @@ fn fs_main_scale2x(in: VertexOutput) -> @location(0) vec4<f32> {
 }
 
 // Nearest neighbor
-@fragment
-fn fs_main_nearest_neighbor(in: VertexOutput) -> @location(0) vec4<f32> {
+fn sample_nearest_neighbor(in: VertexOutput) -> vec4<f32> {
     // Return the exact pixel
     return textureSample(t_diffuse, s_diffuse, in.tex_coords);
 }
 
 // Torcado's cleanEdge
-@fragment
-fn fs_main_clean_edge(in: VertexOutput) -> @location(0) vec4<f32> {
+fn sample_clean_edge(in: VertexOutput) -> vec4<f32> {
     // Take the sample of the exact pixel
     let c = textureSample(t_diffuse, s_diffuse, in.tex_coords);
 
-    // Don't apply the algorithm when no rotations or skewing occurs
-    if in.only_translated_or_reflected == 1.0 {
+    // Don't apply the algorithm when no rotations, skewing or non-integer scaling occurs
+    if in.pixel_aligned == 1.0 {
         return c;
     }
 
@@ -652,3 +685,27 @@ fn fs_main_clean_edge(in: VertexOutput) -> @location(0) vec4<f32> {
     );
 }
 
+@fragment
+fn fs_main_scale3x(in: VertexOutput) -> @location(0) vec4<f32> {
+    return apply_style(sample_scale3x(in), in);
+}
+
+@fragment
+fn fs_main_diag2x(in: VertexOutput) -> @location(0) vec4<f32> {
+    return apply_style(sample_diag2x(in), in);
+}
+
+@fragment
+fn fs_main_scale2x(in: VertexOutput) -> @location(0) vec4<f32> {
+    return apply_style(sample_scale2x(in), in);
+}
+
+@fragment
+fn fs_main_nearest_neighbor(in: VertexOutput) -> @location(0) vec4<f32> {
+    return apply_style(sample_nearest_neighbor(in), in);
+}
+
+@fragment
+fn fs_main_clean_edge(in: VertexOutput) -> @location(0) vec4<f32> {
+    return apply_style(sample_clean_edge(in), in);
+}
@@ -0,0 +1,170 @@
+//! Track which assets are used while loading other assets, so they can be reloaded together.
+
+use std::cell::RefCell;
+
+use hashbrown::{HashMap, HashSet};
+
+use super::Id;
+
+/// Assets that are read while loading other assets.
+///
+/// Every asset that's loaded is pushed on a stack while its loader runs, every ID read while it's on top is a dependency of it.
+#[derive(Debug, Default)]
+pub(crate) struct Dependencies {
+    /// IDs of the assets that are currently being loaded, the last one is loaded by the one before it.
+    loading: RefCell<Vec<Id>>,
+    /// For every ID the assets that read it while loading.
+    dependents: RefCell<HashMap<Id, HashSet<Id>>>,
+}
+
+impl Dependencies {
+    /// Register that an asset is read, making the asset that's currently being loaded depend on it.
+    pub(crate) fn read(&self, id: &Id) {
+        let loading = self.loading.borrow();
+        let Some(dependent) = loading.last() else {
+            return;
+        };
+
+        // Files with the same ID are part of the same asset
+        if dependent == id {
+            return;
+        }
+
+        self.dependents
+            .borrow_mut()
+            .entry(id.clone())
+            .or_default()
+            .insert(dependent.clone());
+    }
+
+    /// Mark an asset as being loaded until [`Self::finish`] is called.
+    pub(crate) fn start(&self, id: &Id) {
+        // The asset using this asset depends on it
+        self.read(id);
+
+        self.loading.borrow_mut().push(id.clone());
+    }
+
+    /// Mark the asset last passed to [`Self::start`] as loaded.
+    pub(crate) fn finish(&self) {
+        self.loading.borrow_mut().pop();
+    }
+
+    /// Forget what an asset depends on, because it's loaded again.
+    pub(crate) fn forget(&self, id: &Id) {
+        self.dependents.borrow_mut().retain(|_, dependents| {
+            dependents.remove(id);
+
+            !dependents.is_empty()
+        });
+    }
+
+    /// All assets that depend on an asset, directly or through other assets.
+    ///
+    /// Direct dependents come before the assets depending on them.
+    pub(crate) fn dependents(&self, id: &Id) -> Vec<Id> {
+        let all_dependents = self.dependents.borrow();
+
+        // Walk the dependents breadth first, an ID is only added once even when there's a cycle
+        let mut dependents = vec![id.clone()];
+        let mut visited = HashSet::new();
+        visited.insert(id.clone());
+        let mut index = 0;
+        while let Some(id) = dependents.get(index).cloned() {
+            index += 1;
+
+            if let Some(direct_dependents) = all_dependents.get(&id) {
+                dependents.extend(
+                    direct_dependents
+                        .iter()
+                        .filter(|dependent| visited.insert((*dependent).clone()))
+                        .cloned(),
+                );
+            }
+        }
+
+        // The asset itself is not a dependent
+        dependents.remove(0);
+
+        dependents
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Load an asset which reads the other assets while loading.
+    fn load(dependencies: &Dependencies, id: &str, reads: &[&str]) {
+        dependencies.start(&Id::new(id));
+        for read in reads {
+            dependencies.read(&Id::new(read));
+        }
+        dependencies.finish();
+    }
+
+    /// Dependents of an asset as strings.
+    fn dependents(dependencies: &Dependencies, id: &str) -> Vec<String> {
+        dependencies
+            .dependents(&Id::new(id))
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn direct_dependents() {
+        let dependencies = Dependencies::default();
+        load(&dependencies, "font", &["font", "font.page"]);
+
+        // Reading its own ID is not a dependency
+        assert_eq!(dependents(&dependencies, "font.page"), ["font"]);
+        assert!(dependents(&dependencies, "font").is_empty());
+    }
+
+    #[test]
+    fn transitive_dependents() {
+        let dependencies = Dependencies::default();
+
+        // Assets loaded while loading another asset are dependencies of it
+        dependencies.start(&Id::new("level"));
+        load(&dependencies, "tileset", &["tileset.png"]);
+        dependencies.finish();
+
+        // Direct dependents come first
+        assert_eq!(
+            dependents(&dependencies, "tileset.png"),
+            ["tileset", "level"]
+        );
+        assert_eq!(dependents(&dependencies, "tileset"), ["level"]);
+        assert!(dependents(&dependencies, "level").is_empty());
+    }
+
+    #[test]
+    fn cycle() {
+        let dependencies = Dependencies::default();
+        load(&dependencies, "a", &["b"]);
+        load(&dependencies, "b", &["c"]);
+        load(&dependencies, "c", &["a"]);
+
+        // Every asset is only returned once and never itself
+        assert_eq!(dependents(&dependencies, "a"), ["c", "b"]);
+        assert_eq!(dependents(&dependencies, "c"), ["b", "a"]);
+    }
+
+    #[test]
+    fn forget_and_reload() {
+        let dependencies = Dependencies::default();
+        load(&dependencies, "font", &["font.page1", "font.page2"]);
+
+        // After forgetting nothing depends on the old pages anymore
+        dependencies.forget(&Id::new("font"));
+        assert!(dependents(&dependencies, "font.page1").is_empty());
+        assert!(dependents(&dependencies, "font.page2").is_empty());
+
+        // Reloading with different dependencies only registers the new ones
+        load(&dependencies, "font", &["font.page2"]);
+        assert!(dependents(&dependencies, "font.page1").is_empty());
+        assert_eq!(dependents(&dependencies, "font.page2"), ["font"]);
+    }
+}
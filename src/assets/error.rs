@@ -0,0 +1,129 @@
+//! Errors that can occur while loading assets.
+
+use std::{
+    error::Error,
+    fmt::{Display, Formatter},
+};
+
+use nanoserde::DeRonErr;
+
+use super::Id;
+
+/// Error that occurs when an asset could not be loaded.
+///
+/// Returned by [`crate::Context::try_asset`] and [`crate::Context::try_sprite`], the other asset methods panic with it.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum AssetError {
+    /// Asset doesn't exist in any of the supported formats.
+    NotFound {
+        /// ID of the asset.
+        id: Id,
+    },
+    /// File exists but its contents could not be decoded.
+    Decode {
+        /// ID of the asset.
+        id: Id,
+        /// Error of the decoder.
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// Image is decoded but its pixels can't be used.
+    PixelFormat {
+        /// ID of the asset.
+        id: Id,
+        /// Description of the pixel format of the image.
+        format: String,
+    },
+    /// RON file could not be parsed.
+    Metadata {
+        /// ID of the asset.
+        id: Id,
+        /// Line in the file where parsing failed, starting at `1`.
+        line: usize,
+        /// Column in the line where parsing failed, starting at `1`.
+        column: usize,
+        /// What went wrong.
+        message: String,
+    },
+}
+
+impl AssetError {
+    /// Create an error for a file that could not be decoded.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of the asset.
+    /// * `source` - Error of the decoder, can also be a string describing what went wrong.
+    #[inline]
+    #[must_use]
+    pub fn decode(id: &Id, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        Self::Decode {
+            id: id.clone(),
+            source: source.into(),
+        }
+    }
+
+    /// Create an error for a RON file that could not be parsed.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - ID of the asset.
+    /// * `err` - Error returned by `nanoserde`.
+    #[inline]
+    #[must_use]
+    pub fn metadata(id: &Id, err: DeRonErr) -> Self {
+        Self::Metadata {
+            id: id.clone(),
+            line: err.line + 1,
+            column: err.col + 1,
+            message: err.msg,
+        }
+    }
+
+    /// ID of the asset that could not be loaded.
+    #[inline]
+    #[must_use]
+    pub const fn id(&self) -> &Id {
+        match self {
+            Self::NotFound { id }
+            | Self::Decode { id, .. }
+            | Self::PixelFormat { id, .. }
+            | Self::Metadata { id, .. } => id,
+        }
+    }
+}
+
+impl Display for AssetError {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound { id } => write!(f, "Error loading asset: '{id}' does not exist"),
+            Self::Decode { id, source } => {
+                write!(f, "Error loading asset with ID '{id}': {source}")
+            }
+            Self::PixelFormat { id, format } => write!(
+                f,
+                "Error loading asset with ID '{id}': image is {format} instead of 8 bit RGBA"
+            ),
+            Self::Metadata {
+                id,
+                line,
+                column,
+                message,
+            } => write!(
+                f,
+                "Error loading RON asset with ID '{id}' at line {line}, column {column}: {message}"
+            ),
+        }
+    }
+}
+
+impl Error for AssetError {
+    #[inline]
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Decode { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
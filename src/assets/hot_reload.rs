@@ -7,28 +7,28 @@ use notify_debouncer_mini::{
 };
 
 use std::{
-    path::{Path, PathBuf},
+    path::PathBuf,
     sync::{Mutex, OnceLock},
     time::Duration,
 };
 
 use crate::context::ContextInner;
 
-use super::Id;
+use super::{source::path_to_id, Id};
 
 /// How long another change between multiple changes is triggered.
 const DEBOUNCE_DELAY: Duration = Duration::from_millis(100);
 
-/// Remove reloaded assets from asset manager so they can be reloaded.
+/// Reload the assets of which the files changed.
 #[inline]
 pub(crate) fn handle_changed_asset_files(ctx: &mut ContextInner) {
-    // Remove each asset so they will be reloaded
+    // Reload each asset, keeping the previous version when it fails
     global_assets_updated()
         .lock()
         .unwrap()
         .drain()
         .for_each(|changed_asset| {
-            ctx.remove(&changed_asset);
+            ctx.reload(&changed_asset);
         });
 }
 
@@ -53,7 +53,7 @@ pub(crate) fn watch_assets_folder(assets_dir: impl Into<PathBuf>) -> Debouncer<R
                         }
 
                         // Convert the changed file's path to ID
-                        let Some(id) = path_to_id(&event.path, &assets_dir) else {
+                        let Some((id, _extension)) = path_to_id(&event.path, &assets_dir) else {
                             eprintln!(
                                 "Error converting changed file asset path {} to ID",
                                 event.path.display()
@@ -81,28 +81,6 @@ pub(crate) fn watch_assets_folder(assets_dir: impl Into<PathBuf>) -> Debouncer<R
     debouncer
 }
 
-/// Convert a path to an ID.
-#[inline]
-fn path_to_id(path: &Path, assets_dir: &Path) -> Option<Id> {
-    // Extract the extension, ignore the file if not found
-    let extension = path.extension()?.to_string_lossy();
-
-    // Get the path relative to the asset dir
-    let relative_path = path.strip_prefix(assets_dir).ok()?;
-
-    // Create an ID from the path
-    let id = relative_path
-        .iter()
-        .map(|path| path.to_string_lossy())
-        .collect::<Vec<_>>()
-        .join(".");
-
-    // Remove the extension
-    let id = id.strip_suffix(&format!(".{extension}"))?;
-
-    Some(Id::new(id))
-}
-
 /// Get a reference to the static map of all assets hat got updated and need to be reloaded.
 #[inline]
 #[must_use]
@@ -1,23 +1,248 @@
 //! Audio asset.
 
 use kira::sound::static_sound::StaticSoundData;
+use nanoserde::{DeRon, DeRonState, DeRonTok};
 
 use crate::{
-    assets::{loader::ogg::OggLoader, Id},
+    assets::{
+        loader::{flac::FlacLoader, ogg::OggLoader, ron::RonLoader, wav::WavLoader, Loader},
+        AssetError, Id,
+    },
     context::ContextInner,
+    random::random,
+    sfxr::Sfxr,
 };
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::assets::loader::ogg::OggStream;
+
 use super::Loadable;
 
 /// Audio asset that can be loaded with metadata.
-pub(crate) struct Audio(pub(crate) StaticSoundData);
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum Audio {
+    /// Single sound.
+    Sound(StaticSoundData),
+    /// Group of sounds of which a random variant is played.
+    Group(SoundGroup),
+}
 
 impl Loadable for Audio {
-    fn load_if_exists(id: &Id, ctx: &mut ContextInner) -> Option<Self> {
-        let sound_data = ctx
+    fn load_if_exists(id: &Id, ctx: &mut ContextInner) -> Result<Option<Self>, AssetError> {
+        // Preloaded OGG files are kept encoded on native because they might be streamed as music
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(stream) = ctx
+            .asset_source
+            .take_preloaded::<OggStream>(id, <OggLoader as Loader<OggStream>>::EXTENSION)
+        {
+            let sound_data = <OggLoader as Loader<StaticSoundData>>::load(&stream?.0, id)?;
+
+            return Ok(Some(Self::Sound(sound_data)));
+        }
+
+        // Try the supported formats in order
+        if let Some(sound_data) = ctx
+            .asset_source
+            .load_if_exists::<OggLoader, StaticSoundData>(id)?
+        {
+            return Ok(Some(Self::Sound(sound_data)));
+        }
+        if let Some(sound_data) = ctx
             .asset_source
-            .load_if_exists::<OggLoader, StaticSoundData>(id)?;
+            .load_if_exists::<WavLoader, StaticSoundData>(id)?
+        {
+            return Ok(Some(Self::Sound(sound_data)));
+        }
+        if let Some(sound_data) = ctx
+            .asset_source
+            .load_if_exists::<FlacLoader, StaticSoundData>(id)?
+        {
+            return Ok(Some(Self::Sound(sound_data)));
+        }
+
+        // Load a sound group or synthesize the sound effect from a RON file with parameters
+        let audio = ctx
+            .asset_source
+            .load_if_exists::<RonLoader, AudioRon>(id)?
+            .map(|audio| match audio {
+                AudioRon::SoundGroup(group) => Self::Group(group),
+                AudioRon::Sfxr(sfxr) => Self::Sound(sfxr.sound_data()),
+            });
+
+        Ok(audio)
+    }
+}
+
+/// Group of sound variants played as a single audio asset, loaded from RON.
+///
+/// Every time the group is played a random variant is picked, with a random volume and pitch.
+///
+/// # Example
+///
+/// A `footsteps.ron` file for three footstep sounds, of which at most four can play at the same time.
+/// The parameters must be wrapped in `SoundGroup(..)`, otherwise the file is loaded as [`crate::sfxr::Sfxr`] parameters:
+///
+/// ```ron
+/// SoundGroup((
+///     variants: ["footstep1", "footstep2", "footstep3"],
+///     min_volume: 0.8,
+///     min_pitch: 0.9,
+///     max_pitch: 1.1,
+///     max_voices: 4,
+///     voice_limit: StealOldest,
+/// ))
+/// ```
+#[derive(Debug, Clone, DeRon)]
+pub struct SoundGroup {
+    /// Asset paths of the sounds to pick from.
+    pub(crate) variants: Vec<String>,
+    /// Lowest volume multiplication factor.
+    #[nserde(default = "1.0")]
+    pub(crate) min_volume: f32,
+    /// Highest volume multiplication factor.
+    #[nserde(default = "1.0")]
+    pub(crate) max_volume: f32,
+    /// Lowest playback rate, lower is slower and deeper.
+    #[nserde(default = "1.0")]
+    pub(crate) min_pitch: f32,
+    /// Highest playback rate, higher is faster and higher.
+    #[nserde(default = "1.0")]
+    pub(crate) max_pitch: f32,
+    /// Maximum amount of sounds of the group that can play at the same time.
+    ///
+    /// Defaults to no limit.
+    #[nserde(default = "usize::MAX")]
+    pub(crate) max_voices: usize,
+    /// What to do when a sound is played while the maximum amount is already playing.
+    #[nserde(default)]
+    pub(crate) voice_limit: VoiceLimit,
+}
+
+impl SoundGroup {
+    /// Pick a random variant.
+    ///
+    /// The loader ensures there's always at least one variant.
+    pub(crate) fn variant(&self) -> &str {
+        &self.variants[fastrand::usize(..self.variants.len())]
+    }
+
+    /// Pick a random volume multiplication factor.
+    pub(crate) fn volume(&self) -> f32 {
+        random(self.min_volume, self.max_volume)
+    }
+
+    /// Pick a random playback rate.
+    pub(crate) fn pitch(&self) -> f32 {
+        random(self.min_pitch, self.max_pitch)
+    }
+}
+
+/// What to do when a sound of a group is played while the maximum amount of voices is already playing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, DeRon)]
+#[non_exhaustive]
+pub enum VoiceLimit {
+    /// Stop the sound of the group that started first to make room for the new sound.
+    #[default]
+    StealOldest,
+    /// Don't play the new sound.
+    Ignore,
+}
+
+/// Any audio asset that can be defined in a RON file.
+enum AudioRon {
+    /// Group of sound variants.
+    SoundGroup(SoundGroup),
+    /// Sound effect synthesizer parameters.
+    Sfxr(Sfxr),
+}
+
+/// Audio asset in a RON file tagged with the name of the type, so it can't be confused with another type.
+#[derive(DeRon)]
+enum TaggedAudioRon {
+    /// Group of sound variants.
+    SoundGroup(SoundGroup),
+    /// Sound effect synthesizer parameters.
+    Sfxr(Sfxr),
+}
+
+impl Loader<AudioRon> for RonLoader {
+    const EXTENSION: &'static str = "ron";
+
+    #[inline]
+    fn load(bytes: &[u8], id: &Id) -> Result<AudioRon, AssetError> {
+        // Convert raw bytes to a valid UTF-8 string
+        let string = String::from_utf8_lossy(bytes);
+
+        // Read the first token, skipping whitespace and comments
+        let mut state = DeRonState::default();
+        let mut chars = string.chars();
+        state.next(&mut chars);
+        state
+            .next_tok(&mut chars)
+            .map_err(|err| AssetError::metadata(id, err))?;
+
+        // Untagged files are sound effect parameters
+        let audio = if state.tok == DeRonTok::ParenOpen {
+            AudioRon::Sfxr(
+                Sfxr::deserialize_ron(&string).map_err(|err| AssetError::metadata(id, err))?,
+            )
+        } else {
+            match TaggedAudioRon::deserialize_ron(&string)
+                .map_err(|err| AssetError::metadata(id, err))?
+            {
+                TaggedAudioRon::SoundGroup(group) => AudioRon::SoundGroup(group),
+                TaggedAudioRon::Sfxr(sfxr) => AudioRon::Sfxr(sfxr),
+            }
+        };
+
+        // Playing a group needs something to pick from
+        if let AudioRon::SoundGroup(group) = &audio {
+            if group.variants.is_empty() {
+                return Err(AssetError::decode(id, "sound group has no variants"));
+            }
+        }
+
+        Ok(audio)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Load the audio RON file from a string.
+    fn load(ron: &str) -> Result<AudioRon, AssetError> {
+        RonLoader::load(ron.as_bytes(), &Id::new("test"))
+    }
+
+    #[test]
+    fn sound_group_tag() {
+        // Only tagged groups are sound groups, a comment mentioning variants doesn't change that
+        let Ok(AudioRon::SoundGroup(group)) =
+            load("// Footsteps\nSoundGroup((variants: [\"step1\", \"step2\"], max_voices: 2))")
+        else {
+            panic!("expected sound group");
+        };
+        assert_eq!(group.variants, ["step1", "step2"]);
+        assert_eq!(group.max_voices, 2);
+        assert!(matches!(
+            load("/* variants */ (wave: Noise)"),
+            Ok(AudioRon::Sfxr(_))
+        ));
+        assert!(matches!(load("Sfxr((wave: Noise))"), Ok(AudioRon::Sfxr(_))));
+
+        // A misspelled field in a group is reported as an error in the group
+        assert!(matches!(
+            load("SoundGroup((variant: [\"step1\"]))"),
+            Err(AssetError::Metadata { .. })
+        ));
+    }
 
-        Some(Self(sound_data))
+    #[test]
+    fn sound_group_without_variants() {
+        assert!(matches!(
+            load("SoundGroup((variants: []))"),
+            Err(AssetError::Decode { .. })
+        ));
     }
 }
@@ -1,13 +1,30 @@
 //! Font asset.
 
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{Display, Formatter},
+    rc::Rc,
+};
+
 use nanoserde::DeRon;
 
 use crate::{
-    assets::{loader::ron::RonLoader, Id},
+    assets::{
+        loader::{
+            bmfont::{BmFont, BmFontLoader},
+            ron::RonLoader,
+        },
+        AssetError, Id,
+    },
     context::ContextInner,
+    graphics::atlas::TextureRef,
 };
 
-use super::{sprite::Sprite, Loadable};
+use super::{
+    sprite::{Sprite, SpriteMetadata, SpriteOffset},
+    Loadable,
+};
 
 /// Font asset that can be loaded with metadata.
 ///
@@ -15,33 +32,284 @@ use super::{sprite::Sprite, Loadable};
 pub(crate) struct Font {
     /// Sprites the font is made up of.
     pub(crate) sprites: Vec<Sprite>,
+    /// Index into the sprites for each character in the font.
+    pub(crate) glyphs: hashbrown::HashMap<char, usize>,
+    /// Horizontal distance to the next glyph for each sprite in pixels.
+    pub(crate) advances: Vec<f32>,
+    /// Horizontal adjustment between pairs of characters in pixels.
+    pub(crate) kerning: hashbrown::HashMap<(char, char), f32>,
+    /// Index into the sprites of the glyph drawn for characters that are not in the font.
+    pub(crate) fallback_glyph: Option<usize>,
     /// Font metadata.
     pub(crate) metadata: FontMetadata,
 }
 
+impl Font {
+    /// Textures in the atlas used by the glyphs, every page only once.
+    pub(crate) fn textures(&self) -> Vec<TextureRef> {
+        let mut textures = self
+            .sprites
+            .iter()
+            .map(|sprite| sprite.texture)
+            .collect::<Vec<_>>();
+        textures.sort_unstable();
+        textures.dedup();
+
+        textures
+    }
+
+    /// Sprite and horizontal distance to the next glyph of a character.
+    ///
+    /// Returns `None` when the character is not in the font image.
+    pub(crate) fn glyph(&self, ch: char) -> Option<(&Sprite, f32)> {
+        self.glyph_at(*self.glyphs.get(&ch)?)
+    }
+
+    /// Sprite and horizontal distance to the next glyph of a glyph index.
+    fn glyph_at(&self, index: usize) -> Option<(&Sprite, f32)> {
+        Some((self.sprites.get(index)?, *self.advances.get(index)?))
+    }
+
+    /// Horizontal distance the cursor moves for a character that's not in the font image in pixels.
+    pub(crate) fn advance(&self, ch: char) -> f32 {
+        // Tabs are as wide as 4 monospace characters
+        if ch == '\t' {
+            return self.metadata.glyph_width * 4.0;
+        }
+
+        self.metadata
+            .advances
+            .get(ch.encode_utf8(&mut [0; 4]) as &str)
+            .copied()
+            .unwrap_or(self.metadata.glyph_width)
+    }
+
+    /// Horizontal adjustment when a character is drawn after another character in pixels.
+    pub(crate) fn kerning(&self, previous: char, ch: char) -> Option<f32> {
+        self.kerning.get(&(previous, ch)).copied()
+    }
+
+    /// Vertical distance between the top of two lines in pixels.
+    pub(crate) fn line_height(&self) -> f32 {
+        self.metadata.glyph_height + self.metadata.line_spacing
+    }
+
+    /// Detect the horizontal distance to the next glyph from the rightmost non-transparent column of each glyph.
+    fn detect_advances(sprites: &[Sprite], metadata: &FontMetadata, pixels: &[u32]) -> Vec<f32> {
+        // The glyphs are all on a single row, so the width of the image is the combined width of all glyphs
+        let image_width = (metadata.glyph_width * sprites.len() as f32).round() as usize;
+        let glyph_width = metadata.glyph_width.round() as usize;
+        let glyph_height = metadata.glyph_height.round() as usize;
+
+        sprites
+            .iter()
+            .map(|sprite| {
+                let glyph_x = sprite.sub_rectangle.0.round() as usize;
+
+                // Find the rightmost column with a visible pixel
+                (0..glyph_width)
+                    .rev()
+                    .find(|column| {
+                        (0..glyph_height).any(|row| {
+                            pixels
+                                .get(row * image_width + glyph_x + column)
+                                .is_some_and(|pixel| pixel >> 24 > 0)
+                        })
+                    })
+                    // Empty glyphs keep the full width so they can be used as spaces
+                    .map_or(metadata.glyph_width, |column| {
+                        column as f32 + 1.0 + metadata.glyph_spacing
+                    })
+            })
+            .collect()
+    }
+}
+
+impl Font {
+    /// Create the font from a `BMFont` descriptor, loading all its page images.
+    ///
+    /// # Errors
+    ///
+    /// - When a page image could not be loaded.
+    fn from_bmfont(id: &Id, bmfont: &BmFont, ctx: &mut ContextInner) -> Result<Self, AssetError> {
+        // Page file names are relative to the descriptor, convert them to IDs in the same directory
+        let directory = id.rsplit_once('.').map(|(directory, _)| directory);
+        let pages = bmfont
+            .pages
+            .iter()
+            .map(|file| {
+                let file = file.strip_suffix(".png").ok_or_else(|| {
+                    AssetError::decode(id, format!("page '{file}' is not a PNG image"))
+                })?;
+                let page_id = file.replace(['/', '\\'], ".");
+
+                Ok(directory.map_or_else(
+                    || Id::new(&page_id),
+                    |directory| Id::new(format!("{directory}.{page_id}")),
+                ))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Upload each page
+        let page_sprites = pages
+            .iter()
+            .map(|page_id| {
+                Sprite::load_if_exists_without_metadata(page_id, ctx)?.ok_or_else(|| {
+                    AssetError::NotFound {
+                        id: page_id.clone(),
+                    }
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut sprites = Vec::with_capacity(bmfont.chars.len());
+        let mut glyphs = hashbrown::HashMap::with_capacity(bmfont.chars.len());
+        let mut advances = Vec::with_capacity(bmfont.chars.len());
+        for bmfont_char in &bmfont.chars {
+            // Skip characters that are not valid Unicode
+            let Some(ch) = char::from_u32(bmfont_char.id) else {
+                continue;
+            };
+
+            let page = page_sprites.get(bmfont_char.page).ok_or_else(|| {
+                AssetError::decode(
+                    id,
+                    format!(
+                        "character '{ch}' refers to missing page {}",
+                        bmfont_char.page
+                    ),
+                )
+            })?;
+
+            // Draw the glyph from the page, moved by its offset
+            let (offset_x, offset_y) = bmfont_char.offset;
+            let metadata = SpriteMetadata {
+                offset: SpriteOffset::Custom {
+                    x: -offset_x,
+                    y: -offset_y,
+                },
+                ..Default::default()
+            };
+
+            glyphs.insert(ch, sprites.len());
+            advances.push(bmfont_char.advance);
+            sprites.push(Sprite {
+                sub_rectangle: bmfont_char.rectangle,
+                metadata,
+                ..page.clone()
+            });
+        }
+
+        // Convert the kerning pairs to characters
+        let kerning = bmfont
+            .kerning_pairs
+            .iter()
+            .filter_map(|pair| {
+                Some((
+                    (char::from_u32(pair.first)?, char::from_u32(pair.second)?),
+                    pair.amount,
+                ))
+            })
+            .collect();
+
+        // Characters without a glyph, such as tabs, use the width of a space
+        let glyph_width = glyphs
+            .get(&' ')
+            .and_then(|index| advances.get(*index))
+            .copied()
+            .unwrap_or(bmfont.line_height / 2.0);
+        let metadata = FontMetadata {
+            glyph_width,
+            glyph_height: bmfont.line_height,
+            ..Default::default()
+        };
+
+        Ok(Self {
+            sprites,
+            glyphs,
+            advances,
+            kerning,
+            fallback_glyph: None,
+            metadata,
+        })
+    }
+}
+
 impl Loadable for Font {
-    fn load_if_exists(id: &Id, ctx: &mut ContextInner) -> Option<Self> {
+    fn load_if_exists(id: &Id, ctx: &mut ContextInner) -> Result<Option<Self>, AssetError> {
+        // Prefer a `BMFont` descriptor, its page image could have the same name as the font
+        if let Some(bmfont) = ctx.asset_source.load_if_exists::<BmFontLoader, _>(id)? {
+            return Self::from_bmfont(id, &bmfont, ctx).map(Some);
+        }
+
         // Load the base sprite, but don't load the sprite metadata because we can only load one RON file
-        let base = Sprite::load_if_exists_without_metadata(id, ctx)?;
+        let Some(base) = Sprite::load_if_exists_without_metadata(id, ctx)? else {
+            return Ok(None);
+        };
 
         // Load the metadata
-        let metadata = FontMetadata::load_if_exists(id, ctx).unwrap_or_default();
+        let metadata = FontMetadata::load_if_exists(id, ctx)?.unwrap_or_default();
 
         // Split the sprite into multiple sub-sprites for each character
-        let sprites = base.horizontal_parts(metadata.glyph_width);
+        let sprites = base
+            .horizontal_parts(metadata.glyph_width)
+            .map_err(|err| AssetError::decode(id, err))?;
 
-        assert_eq!(
-            metadata.last_char - metadata.first_char,
-            sprites.len() - 1,
-            "Font not properly defined, last char does not match length of parsed glyphs"
-        );
+        // Map each character to its glyph
+        let glyphs = metadata
+            .glyphs(sprites.len())
+            .map_err(|err| AssetError::decode(id, err))?;
+
+        // Pixels are only kept in memory with the `read-texture` feature flag
+        #[cfg(feature = "read-texture")]
+        let pixels = ctx.graphics.atlas.textures.get(&base.texture);
+        #[cfg(not(feature = "read-texture"))]
+        let pixels: Option<&Vec<u32>> = None;
 
-        Some(Self { sprites, metadata })
+        // Use the same width for every glyph unless the font is proportional
+        let mut advances = if metadata.proportional {
+            let pixels = pixels.ok_or_else(|| {
+                AssetError::decode(
+                    id,
+                    "proportional font needs the pixels of its image, which are only kept with the `read-texture` feature flag for images that are not embedded",
+                )
+            })?;
+
+            Self::detect_advances(&sprites, &metadata, pixels)
+        } else {
+            vec![metadata.glyph_width; sprites.len()]
+        };
+
+        // Explicitly defined widths always take precedence
+        for (ch, index) in &glyphs {
+            if let Some(defined) = metadata.advances.get(ch.encode_utf8(&mut [0; 4]) as &str) {
+                advances[*index] = *defined;
+            }
+        }
+
+        // Convert the kerning pairs to characters
+        let kerning = metadata
+            .kerning_pairs()
+            .map_err(|err| AssetError::decode(id, err))?;
+
+        // Find the glyph for missing characters
+        let fallback_glyph = metadata
+            .fallback_glyph(&glyphs)
+            .map_err(|err| AssetError::decode(id, err))?;
+
+        Ok(Some(Self {
+            sprites,
+            glyphs,
+            advances,
+            kerning,
+            fallback_glyph,
+            metadata,
+        }))
     }
 }
 
 /// Font metadata to load from RON.
-#[derive(Debug, Clone, Copy, DeRon)]
+#[derive(Debug, Clone, DeRon)]
 pub struct FontMetadata {
     /// Width of a single character in pixels.
     pub(crate) glyph_width: f32,
@@ -50,17 +318,140 @@ pub struct FontMetadata {
     /// First character in the image.
     ///
     /// Uses the ASCII table, the default value is `33` which equals `'!'`.
+    /// Ignored when `chars` or `ranges` is set.
     #[nserde(default = "'!' as usize")]
     pub(crate) first_char: usize,
     /// Last character in the image.
     ///
     /// Uses the ASCII table, the default value is `127` which equals `'~'`.
+    /// Ignored when `chars` or `ranges` is set.
     #[nserde(default = "'~' as usize")]
     pub(crate) last_char: usize,
+    /// All characters in the image from left to right, such as `"AÄBCĆ"`.
+    ///
+    /// Takes precedence over `ranges`.
+    #[nserde(default)]
+    pub(crate) chars: String,
+    /// Inclusive ranges of Unicode code points in the image from left to right, such as `[(33, 126), (12353, 12438)]`.
+    #[nserde(default)]
+    pub(crate) ranges: Vec<(usize, usize)>,
+    /// Character in the image drawn for characters that are not in any font, such as `"?"`.
+    ///
+    /// When empty missing characters are skipped.
+    #[nserde(default)]
+    pub(crate) fallback_glyph: String,
+    /// Asset path of a font to look for characters that are not in this font.
+    ///
+    /// The fallback font can have a fallback font of its own.
+    #[nserde(default)]
+    pub(crate) fallback_font: String,
+    /// Whether the width of each glyph is detected from its transparent columns on the right.
+    ///
+    /// Requires the `read-texture` feature flag and an image that's not embedded, otherwise loading the font fails.
+    #[nserde(default)]
+    pub(crate) proportional: bool,
+    /// Empty pixels added after each detected glyph width when the font is proportional.
+    #[nserde(default = "1.0")]
+    pub(crate) glyph_spacing: f32,
+    /// Horizontal distance to the next glyph for specific characters in pixels, overrides the detected widths.
+    ///
+    /// Can also be used for characters outside of the image such as `" "`.
+    #[nserde(default)]
+    pub(crate) advances: HashMap<String, f32>,
+    /// Horizontal adjustment in pixels when the second character of the pair follows the first, such as `"AV": -1`.
+    #[nserde(default)]
+    pub(crate) kerning: HashMap<String, f32>,
+    /// Extra vertical pixels between lines.
+    #[nserde(default)]
+    pub(crate) line_spacing: f32,
+}
+
+impl FontMetadata {
+    /// Map each character to the index of its glyph in the image.
+    ///
+    /// # Errors
+    ///
+    /// - When the amount of characters doesn't match the amount of glyphs.
+    /// - When a character is defined twice.
+    /// - When a range contains an invalid Unicode code point.
+    fn glyphs(&self, glyph_count: usize) -> Result<hashbrown::HashMap<char, usize>, FontError> {
+        // Collect the characters in the order of the image
+        let chars = if self.chars.is_empty() {
+            let ranges = if self.ranges.is_empty() {
+                &[(self.first_char, self.last_char)][..]
+            } else {
+                &self.ranges
+            };
+
+            ranges
+                .iter()
+                .flat_map(|(first, last)| *first..=*last)
+                .map(|code_point| {
+                    u32::try_from(code_point)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or(FontError::InvalidCodePoint(code_point))
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            self.chars.chars().collect()
+        };
+
+        if chars.len() != glyph_count {
+            return Err(FontError::GlyphCountMismatch {
+                chars: chars.len(),
+                glyphs: glyph_count,
+            });
+        }
+
+        let mut glyphs = hashbrown::HashMap::with_capacity(chars.len());
+        for (index, ch) in chars.into_iter().enumerate() {
+            if glyphs.insert(ch, index).is_some() {
+                return Err(FontError::DuplicateChar(ch));
+            }
+        }
+
+        Ok(glyphs)
+    }
+
+    /// Convert the kerning pairs to characters.
+    ///
+    /// # Errors
+    ///
+    /// - When a pair doesn't consist of exactly two characters.
+    fn kerning_pairs(&self) -> Result<hashbrown::HashMap<(char, char), f32>, FontError> {
+        self.kerning
+            .iter()
+            .map(|(pair, adjustment)| {
+                single_chars::<2>(pair)
+                    .map(|chars| (chars.into(), *adjustment))
+                    .ok_or_else(|| FontError::InvalidKerningPair(pair.clone()))
+            })
+            .collect()
+    }
+
+    /// Find the glyph for missing characters.
+    ///
+    /// # Errors
+    ///
+    /// - When the fallback glyph is not a single character in the font.
+    fn fallback_glyph(
+        &self,
+        glyphs: &hashbrown::HashMap<char, usize>,
+    ) -> Result<Option<usize>, FontError> {
+        if self.fallback_glyph.is_empty() {
+            return Ok(None);
+        }
+
+        single_chars::<1>(&self.fallback_glyph)
+            .and_then(|[ch]| glyphs.get(&ch).copied())
+            .map(Some)
+            .ok_or_else(|| FontError::MissingFallbackGlyph(self.fallback_glyph.clone()))
+    }
 }
 
 impl Loadable for FontMetadata {
-    fn load_if_exists(id: &Id, ctx: &mut ContextInner) -> Option<Self> {
+    fn load_if_exists(id: &Id, ctx: &mut ContextInner) -> Result<Option<Self>, AssetError> {
         ctx.asset_source.load_if_exists::<RonLoader, Self>(id)
     }
 }
@@ -72,6 +463,643 @@ impl Default for FontMetadata {
             glyph_height: 8.0,
             first_char: '!' as usize,
             last_char: '~' as usize,
+            chars: String::new(),
+            ranges: Vec::new(),
+            fallback_glyph: String::new(),
+            fallback_font: String::new(),
+            proportional: false,
+            glyph_spacing: 1.0,
+            advances: HashMap::new(),
+            kerning: HashMap::new(),
+            line_spacing: 0.0,
+        }
+    }
+}
+
+/// Font and its fallback fonts, used to find the glyph for each character.
+pub(crate) struct FontChain {
+    /// Primary font followed by its fallback fonts in order.
+    fonts: Vec<Rc<Font>>,
+}
+
+impl FontChain {
+    /// Load the font and all its fallback fonts.
+    ///
+    /// # Panics
+    ///
+    /// - When any of the fonts could not be loaded.
+    pub(crate) fn load(id: &str, ctx: &mut ContextInner) -> Self {
+        let mut ids = vec![id.to_owned()];
+        let mut fonts = vec![ctx.font(id)];
+
+        // Follow the chain, stopping when a font is referenced twice
+        loop {
+            let fallback_font = &fonts[fonts.len() - 1].metadata.fallback_font;
+            if fallback_font.is_empty() || ids.contains(fallback_font) {
+                break;
+            }
+
+            ids.push(fallback_font.clone());
+            fonts.push(ctx.font(fallback_font));
+        }
+
+        Self { fonts }
+    }
+
+    /// Primary font.
+    pub(crate) fn primary(&self) -> &Font {
+        &self.fonts[0]
+    }
+
+    /// Sprite and horizontal distance to the next glyph of a character.
+    ///
+    /// The sprite is taken from the first font containing the character, falling back to the first fallback glyph for visible characters.
+    /// When no sprite is found `None` is returned with the distance to the next glyph in the primary font.
+    pub(crate) fn glyph(&self, ch: char) -> (Option<&Sprite>, f32) {
+        // Find the first font containing the character
+        if let Some((sprite, advance)) = self.fonts.iter().find_map(|font| font.glyph(ch)) {
+            return (Some(sprite), advance);
+        }
+
+        // Never draw the fallback glyph for whitespace
+        if !ch.is_whitespace() {
+            if let Some((sprite, advance)) = self
+                .fonts
+                .iter()
+                .find_map(|font| font.glyph_at(font.fallback_glyph?))
+            {
+                return (Some(sprite), advance);
+            }
+        }
+
+        (None, self.primary().advance(ch))
+    }
+
+    /// Horizontal adjustment when a character is drawn after another character in pixels.
+    pub(crate) fn kerning(&self, previous: char, ch: char) -> f32 {
+        self.fonts
+            .iter()
+            .find_map(|font| font.kerning(previous, ch))
+            .unwrap_or_default()
+    }
+
+    /// Vertical distance between the top of two lines in pixels.
+    pub(crate) fn line_height(&self) -> f32 {
+        self.primary().line_height()
+    }
+
+    /// Height of a single line of glyphs in pixels.
+    pub(crate) fn glyph_height(&self) -> f32 {
+        self.primary().metadata.glyph_height
+    }
+
+    /// Height of lines of text in pixels.
+    pub(crate) fn text_height(&self, lines: usize, line_height: f32) -> f32 {
+        // The last line is only as high as its glyphs
+        line_height.mul_add(lines.saturating_sub(1) as f32, self.glyph_height())
+    }
+
+    /// Position the glyphs of a text in lines.
+    ///
+    /// Lines are wrapped at whitespace when they become wider than the maximum width, words that don't fit on a line by themselves are split.
+    pub(crate) fn layout(&self, items: &[TextItem], max_width: f32) -> Vec<TextLine> {
+        let mut lines = Vec::new();
+        let mut line = TextLine::default();
+        let mut x = 0.0;
+        let mut previous = None;
+
+        // Each word includes its trailing whitespace
+        let mut index = 0;
+        for word in items.split_inclusive(|item| item.is_whitespace()) {
+            // Move the whole word to the next line when its visible part doesn't fit, extra whitespace never moves
+            let visible_word = word
+                .split_last()
+                .filter(|(last, _)| last.is_whitespace())
+                .map_or(word, |(_, visible_word)| visible_word);
+            if !visible_word.is_empty()
+                && !line.glyphs.is_empty()
+                && x + self.measure(visible_word, previous) > max_width
+            {
+                lines.push(std::mem::take(&mut line));
+                x = 0.0;
+                previous = None;
+            }
+
+            for item in word {
+                // Move the cursor to the start of the next line
+                if *item == TextItem::Char('\n') {
+                    lines.push(std::mem::take(&mut line));
+                    x = 0.0;
+                    previous = None;
+                    index += 1;
+
+                    continue;
+                }
+
+                let (kerning, visible, advance) = self.item_metrics(*item, previous);
+                previous = item.char();
+
+                // Split words that are wider than a whole line
+                if !item.is_whitespace()
+                    && !line.glyphs.is_empty()
+                    && x + kerning + advance > max_width
+                {
+                    lines.push(std::mem::take(&mut line));
+                    x = 0.0;
+                } else {
+                    x += kerning;
+                }
+
+                if visible {
+                    line.glyphs.push((index, x));
+                }
+                x += advance;
+                index += 1;
+
+                // Trailing whitespace doesn't count for the width of the line
+                if !item.is_whitespace() {
+                    line.width = x;
+                }
+            }
+        }
+
+        lines.push(line);
+
+        lines
+    }
+
+    /// Width of a single line of text in pixels, without wrapping.
+    fn measure(&self, items: &[TextItem], mut previous: Option<char>) -> f32 {
+        items
+            .iter()
+            .map(|item| {
+                let (kerning, _, advance) = self.item_metrics(*item, previous);
+                previous = item.char();
+
+                kerning + advance
+            })
+            .sum()
+    }
+
+    /// Kerning with the previous character, whether something is drawn, and the horizontal distance to the next item.
+    fn item_metrics(&self, item: TextItem, previous: Option<char>) -> (f32, bool, f32) {
+        match item {
+            TextItem::Char(ch) => {
+                let kerning = previous.map_or(0.0, |previous| self.kerning(previous, ch));
+                let (sprite, advance) = self.glyph(ch);
+
+                (kerning, sprite.is_some(), advance)
+            }
+            TextItem::Icon { width } => (0.0, true, width),
+        }
+    }
+}
+
+/// Single item of text to lay out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum TextItem {
+    /// Character drawn with a glyph from the font.
+    Char(char),
+    /// Inline sprite.
+    Icon {
+        /// Width of the sprite in pixels.
+        width: f32,
+    },
+}
+
+impl TextItem {
+    /// Whether a line can be wrapped after this item.
+    pub(crate) fn is_whitespace(self) -> bool {
+        matches!(self, Self::Char(ch) if ch.is_whitespace())
+    }
+
+    /// Character of the item, used for kerning.
+    const fn char(self) -> Option<char> {
+        match self {
+            Self::Char(ch) => Some(ch),
+            Self::Icon { .. } => None,
+        }
+    }
+}
+
+/// Single line of text with its items positioned.
+#[derive(Default)]
+pub(crate) struct TextLine {
+    /// Index of each visible item with its horizontal position relative to the start of the line.
+    pub(crate) glyphs: Vec<(usize, f32)>,
+    /// Width of the line in pixels, excluding trailing whitespace.
+    pub(crate) width: f32,
+}
+
+/// Error that occurs when a font is not properly defined.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum FontError {
+    /// Amount of characters in the metadata doesn't match the amount of glyphs in the image.
+    GlyphCountMismatch {
+        /// Amount of characters defined in the metadata.
+        chars: usize,
+        /// Amount of glyphs in the image.
+        glyphs: usize,
+    },
+    /// Same character is defined more than once.
+    DuplicateChar(char),
+    /// Range contains a number that's not a valid Unicode code point.
+    InvalidCodePoint(usize),
+    /// Kerning pair doesn't consist of exactly two characters.
+    InvalidKerningPair(String),
+    /// Fallback glyph is not a single character in the font.
+    MissingFallbackGlyph(String),
+}
+
+impl Display for FontError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::GlyphCountMismatch { chars, glyphs } => write!(
+                f,
+                "font defines {chars} characters but the image contains {glyphs} glyphs"
+            ),
+            Self::DuplicateChar(ch) => write!(f, "character '{ch}' is defined more than once"),
+            Self::InvalidCodePoint(code_point) => {
+                write!(f, "{code_point} is not a valid Unicode code point")
+            }
+            Self::InvalidKerningPair(pair) => write!(
+                f,
+                "kerning pair '{pair}' must consist of exactly two characters"
+            ),
+            Self::MissingFallbackGlyph(fallback_glyph) => write!(
+                f,
+                "fallback glyph '{fallback_glyph}' must be a single character in the font"
+            ),
+        }
+    }
+}
+
+impl Error for FontError {}
+
+/// Split a string into exactly `N` characters.
+fn single_chars<const N: usize>(string: &str) -> Option<[char; N]> {
+    string.chars().collect::<Vec<_>>().try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Split an image into glyphs of the size in the metadata.
+    fn sprites(metadata: &FontMetadata, glyph_count: usize) -> Vec<Sprite> {
+        Sprite {
+            texture: 0,
+            sub_rectangle: (
+                0.0,
+                0.0,
+                metadata.glyph_width * glyph_count as f32,
+                metadata.glyph_height,
+            ),
+            frames: Vec::new(),
+            metadata: SpriteMetadata::default(),
+        }
+        .horizontal_parts(metadata.glyph_width)
+        .unwrap()
+    }
+
+    /// Create a monospace font from the metadata, like the loader does without an image.
+    fn font(metadata: FontMetadata, glyph_count: usize) -> Font {
+        let sprites = sprites(&metadata, glyph_count);
+        let glyphs = metadata.glyphs(sprites.len()).unwrap();
+        let advances = vec![metadata.glyph_width; sprites.len()];
+        let kerning = metadata.kerning_pairs().unwrap();
+        let fallback_glyph = metadata.fallback_glyph(&glyphs).unwrap();
+
+        Font {
+            sprites,
+            glyphs,
+            advances,
+            kerning,
+            fallback_glyph,
+            metadata,
+        }
+    }
+
+    #[test]
+    fn detect_advances() {
+        let metadata = FontMetadata {
+            glyph_width: 4.0,
+            glyph_height: 2.0,
+            ..Default::default()
+        };
+        let sprites = sprites(&metadata, 3);
+
+        // Image of 12x2 pixels with a visible pixel in the second column of the first glyph and in the last column of the last glyph
+        let mut pixels = vec![0; 12 * 2];
+        pixels[12 + 1] = 0xFF00_0000;
+        pixels[8 + 3] = 0x0100_0000;
+
+        // Empty glyphs keep the full width, spacing is added to the detected width
+        assert_eq!(
+            Font::detect_advances(&sprites, &metadata, &pixels),
+            [3.0, 4.0, 5.0]
+        );
+        let metadata = FontMetadata {
+            glyph_spacing: 0.0,
+            ..metadata
+        };
+        assert_eq!(
+            Font::detect_advances(&sprites, &metadata, &pixels),
+            [2.0, 4.0, 4.0]
+        );
+
+        // Fully transparent pixels are not visible
+        pixels[12 + 1] = 0x00FF_FFFF;
+        assert_eq!(
+            Font::detect_advances(&sprites, &metadata, &pixels),
+            [4.0, 4.0, 4.0]
+        );
+    }
+
+    #[test]
+    fn kerning() {
+        let font = font(
+            FontMetadata {
+                kerning: HashMap::from([("AV".to_owned(), -1.0), ("Vä".to_owned(), 2.0)]),
+                ..Default::default()
+            },
+            94,
+        );
+
+        // Only in the order of the pair
+        assert_eq!(font.kerning('A', 'V'), Some(-1.0));
+        assert_eq!(font.kerning('V', 'A'), None);
+        assert_eq!(font.kerning('V', 'ä'), Some(2.0));
+
+        // Pairs must be exactly two characters
+        for pair in ["A", "AVA", ""] {
+            let metadata = FontMetadata {
+                kerning: HashMap::from([(pair.to_owned(), 1.0)]),
+                ..Default::default()
+            };
+            assert_eq!(
+                metadata.kerning_pairs().unwrap_err(),
+                FontError::InvalidKerningPair(pair.to_owned())
+            );
+        }
+    }
+
+    #[test]
+    fn advances_and_line_height() {
+        let font = font(
+            FontMetadata {
+                glyph_height: 10.0,
+                line_spacing: 2.0,
+                advances: HashMap::from([(" ".to_owned(), 3.0)]),
+                ..Default::default()
+            },
+            94,
+        );
+
+        // Characters outside of the image use the defined width, the monospace width, or four times that for tabs
+        assert!((font.advance(' ') - 3.0).abs() < f32::EPSILON);
+        assert!((font.advance('ä') - 8.0).abs() < f32::EPSILON);
+        assert!((font.advance('\t') - 32.0).abs() < f32::EPSILON);
+        assert!((font.line_height() - 12.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn glyphs_chars_and_ranges() {
+        // Characters are mapped from left to right
+        let metadata = FontMetadata {
+            chars: "AÄBCĆ".to_owned(),
+            ranges: vec![(0, 1000)],
+            ..Default::default()
+        };
+        let glyphs = metadata.glyphs(5).unwrap();
+        assert_eq!(glyphs.len(), 5);
+        assert_eq!((glyphs[&'Ä'], glyphs[&'Ć']), (1, 4));
+        assert_eq!(
+            metadata.glyphs(4).unwrap_err(),
+            FontError::GlyphCountMismatch {
+                chars: 5,
+                glyphs: 4
+            }
+        );
+
+        // Ranges follow each other
+        let metadata = FontMetadata {
+            ranges: vec![(65, 67), (12353, 12354)],
+            ..Default::default()
+        };
+        let glyphs = metadata.glyphs(5).unwrap();
+        assert_eq!((glyphs[&'A'], glyphs[&'C'], glyphs[&'あ']), (0, 2, 4));
+
+        // Without characters or ranges the printable ASCII characters are used
+        let glyphs = FontMetadata::default().glyphs(94).unwrap();
+        assert_eq!((glyphs[&'!'], glyphs[&'~']), (0, 93));
+
+        // Invalid definitions
+        let metadata = FontMetadata {
+            chars: "ABA".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(
+            metadata.glyphs(3).unwrap_err(),
+            FontError::DuplicateChar('A')
+        );
+        let metadata = FontMetadata {
+            ranges: vec![(0xD800, 0xD800)],
+            ..Default::default()
+        };
+        assert_eq!(
+            metadata.glyphs(1).unwrap_err(),
+            FontError::InvalidCodePoint(0xD800)
+        );
+    }
+
+    #[test]
+    fn fallback_glyph() {
+        let metadata = |fallback_glyph: &str| FontMetadata {
+            chars: "AB?".to_owned(),
+            fallback_glyph: fallback_glyph.to_owned(),
+            ..Default::default()
+        };
+        let glyphs = metadata("").glyphs(3).unwrap();
+
+        assert_eq!(metadata("").fallback_glyph(&glyphs), Ok(None));
+        assert_eq!(metadata("?").fallback_glyph(&glyphs), Ok(Some(2)));
+
+        // Must be a single character in the font
+        for fallback_glyph in ["€", "??"] {
+            assert_eq!(
+                metadata(fallback_glyph).fallback_glyph(&glyphs),
+                Err(FontError::MissingFallbackGlyph(fallback_glyph.to_owned()))
+            );
         }
     }
+
+    #[test]
+    fn font_chain_glyph() {
+        // Primary font with 8 pixel wide glyphs, fallback font with 6 pixel wide glyphs and a fallback glyph
+        let primary = font(
+            FontMetadata {
+                chars: "AB".to_owned(),
+                fallback_font: "fallback".to_owned(),
+                ..Default::default()
+            },
+            2,
+        );
+        let mut fallback = font(
+            FontMetadata {
+                glyph_width: 6.0,
+                chars: "BCä?".to_owned(),
+                fallback_glyph: "?".to_owned(),
+                ..Default::default()
+            },
+            4,
+        );
+        for sprite in &mut fallback.sprites {
+            sprite.texture = 1;
+        }
+        let chain = FontChain {
+            fonts: vec![Rc::new(primary), Rc::new(fallback)],
+        };
+
+        // Texture and horizontal position in the image of the glyph, with the distance to the next glyph
+        let glyph = |ch| {
+            let (sprite, advance) = chain.glyph(ch);
+
+            (
+                sprite.map(|sprite| (sprite.texture, sprite.sub_rectangle.0)),
+                advance,
+            )
+        };
+
+        // The primary font is used first
+        assert_eq!(glyph('A'), (Some((0, 0.0)), 8.0));
+        assert_eq!(glyph('B'), (Some((0, 8.0)), 8.0));
+
+        // Characters only in the fallback font
+        assert_eq!(glyph('ä'), (Some((1, 12.0)), 6.0));
+
+        // Characters in neither font use the fallback glyph
+        assert_eq!(glyph('€'), (Some((1, 18.0)), 6.0));
+
+        // Whitespace is never drawn and uses the width of the primary font
+        assert_eq!(glyph(' '), (None, 8.0));
+    }
+
+    /// Lay out a text with a monospace font of 8 pixel wide glyphs, returning the positioned item indices and width of each line.
+    fn layout(items: &[TextItem], max_width: f32) -> Vec<(Vec<(usize, f32)>, f32)> {
+        let chain = FontChain {
+            fonts: vec![Rc::new(font(FontMetadata::default(), 94))],
+        };
+
+        chain
+            .layout(items, max_width)
+            .into_iter()
+            .map(|line| (line.glyphs, line.width))
+            .collect()
+    }
+
+    /// Split a text into character items.
+    fn chars(text: &str) -> Vec<TextItem> {
+        text.chars().map(TextItem::Char).collect()
+    }
+
+    #[test]
+    fn layout_word_wrap() {
+        // Words are moved to the next line when they don't fit, whitespace is not drawn
+        assert_eq!(
+            layout(&chars("ab cd ef"), 40.0),
+            [
+                (vec![(0, 0.0), (1, 8.0), (3, 24.0), (4, 32.0)], 40.0),
+                (vec![(6, 0.0), (7, 8.0)], 16.0)
+            ]
+        );
+
+        // Trailing whitespace doesn't count for the width or wrapping
+        assert_eq!(
+            layout(&chars("ab cd  "), 40.0),
+            [(vec![(0, 0.0), (1, 8.0), (3, 24.0), (4, 32.0)], 40.0)]
+        );
+
+        // Without a maximum width everything is on a single line
+        assert_eq!(
+            layout(&chars("ab cd ef"), f32::INFINITY),
+            [(
+                vec![
+                    (0, 0.0),
+                    (1, 8.0),
+                    (3, 24.0),
+                    (4, 32.0),
+                    (6, 48.0),
+                    (7, 56.0)
+                ],
+                64.0
+            )]
+        );
+    }
+
+    #[test]
+    fn text_height() {
+        let chain = FontChain {
+            fonts: vec![Rc::new(font(
+                FontMetadata {
+                    line_spacing: 2.0,
+                    ..Default::default()
+                },
+                94,
+            ))],
+        };
+
+        // The last line is only as high as its glyphs
+        assert!((chain.text_height(1, chain.line_height()) - 8.0).abs() < f32::EPSILON);
+        assert!((chain.text_height(3, chain.line_height()) - 28.0).abs() < f32::EPSILON);
+        assert!((chain.text_height(3, 12.0) - 32.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn layout_long_word() {
+        // Words wider than a line are split
+        assert_eq!(
+            layout(&chars("abcdefgh"), 24.0),
+            [
+                (vec![(0, 0.0), (1, 8.0), (2, 16.0)], 24.0),
+                (vec![(3, 0.0), (4, 8.0), (5, 16.0)], 24.0),
+                (vec![(6, 0.0), (7, 8.0)], 16.0)
+            ]
+        );
+
+        // A long word after a short word starts on a new line before being split
+        assert_eq!(
+            layout(&chars("a bcdef"), 24.0),
+            [
+                (vec![(0, 0.0)], 8.0),
+                (vec![(2, 0.0), (3, 8.0), (4, 16.0)], 24.0),
+                (vec![(5, 0.0), (6, 8.0)], 16.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn layout_newlines_and_icons() {
+        // Newlines always start a new line, also when it's empty
+        assert_eq!(
+            layout(&chars("ab\ncd\n"), f32::INFINITY),
+            [
+                (vec![(0, 0.0), (1, 8.0)], 16.0),
+                (vec![(3, 0.0), (4, 8.0)], 16.0),
+                (vec![], 0.0)
+            ]
+        );
+
+        // Icons have their own width and are part of the word
+        let items = [
+            TextItem::Char('a'),
+            TextItem::Icon { width: 5.0 },
+            TextItem::Char(' '),
+            TextItem::Char('b'),
+        ];
+        assert_eq!(
+            layout(&items, 16.0),
+            [(vec![(0, 0.0), (1, 8.0)], 13.0), (vec![(3, 0.0)], 8.0)]
+        );
+    }
 }
@@ -2,13 +2,14 @@
 
 pub(crate) mod audio;
 pub(crate) mod font;
+pub(crate) mod music;
 pub(crate) mod sprite;
 
 use downcast_rs::Downcast;
 
 use crate::context::ContextInner;
 
-use super::Id;
+use super::{AssetError, Id};
 
 /// Any asset that's loadable from any amount of binary files.
 ///
@@ -16,7 +17,7 @@ use super::Id;
 ///
 /// ```
 /// use chuot::{
-///     assets::{loadable::Loadable, loader::ron::RonLoader, Id},
+///     assets::{loadable::Loadable, loader::ron::RonLoader, AssetError, Id},
 ///     context::ContextInner,
 /// };
 /// use nanoserde::DeRon;
@@ -29,38 +30,34 @@ use super::Id;
 /// }
 ///
 /// impl Loadable for Settings {
-///     fn load_if_exists(id: &Id, ctx: &mut ContextInner) -> Option<Self> {
+///     fn load_if_exists(id: &Id, ctx: &mut ContextInner) -> Result<Option<Self>, AssetError> {
 ///         // Use the RON loader to load our asset
 ///         ctx.asset_source.load_if_exists::<RonLoader, Self>(id)
 ///     }
 /// }
 /// ```
 pub trait Loadable: Downcast {
-    /// Convert a file object to this type if it exists, if it doesn't return `None`.
+    /// Convert a file object to this type if it exists, if it doesn't return `Ok(None)`.
     ///
-    /// # Panics
+    /// # Errors
     ///
     /// - When parsing binary bytes of asset into type fails.
-    fn load_if_exists(id: &Id, ctx: &mut ContextInner) -> Option<Self>
+    fn load_if_exists(id: &Id, ctx: &mut ContextInner) -> Result<Option<Self>, AssetError>
     where
         Self: Sized;
 
     /// Convert a file object to this type.
     ///
-    /// # Panics
+    /// # Errors
     ///
     /// - When parsing binary bytes of asset into type fails.
     /// - When asset does not exist in the source.
     #[inline]
-    #[must_use]
-    fn load(id: &Id, ctx: &mut ContextInner) -> Self
+    fn load(id: &Id, ctx: &mut ContextInner) -> Result<Self, AssetError>
     where
         Self: Sized,
     {
-        Self::load_if_exists(id, ctx).map_or_else(
-            || panic!("Error loading asset: '{id}' does not exist"),
-            |asset| asset,
-        )
+        Self::load_if_exists(id, ctx)?.ok_or_else(|| AssetError::NotFound { id: id.clone() })
     }
 
     /// Create a new runtime asset from the default value.
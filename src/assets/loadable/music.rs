@@ -0,0 +1,118 @@
+//! Music asset.
+
+#[cfg(target_arch = "wasm32")]
+use kira::sound::static_sound::StaticSoundData;
+use kira::sound::Region;
+use nanoserde::DeRon;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::assets::loader::ogg::OggStream;
+use crate::{
+    assets::{
+        loader::{ogg::OggLoader, ron::RonLoader},
+        AssetError, Id,
+    },
+    context::ContextInner,
+};
+
+use super::Loadable;
+
+/// Music asset that's streamed while playing, can be loaded with metadata.
+pub(crate) struct Music {
+    /// Audio of the track.
+    pub(crate) data: MusicData,
+    /// Music metadata.
+    pub(crate) metadata: MusicMetadata,
+}
+
+impl Loadable for Music {
+    fn load_if_exists(id: &Id, ctx: &mut ContextInner) -> Result<Option<Self>, AssetError> {
+        // Stream the encoded audio, preloaded tracks are also kept encoded
+        #[cfg(not(target_arch = "wasm32"))]
+        let data = match ctx
+            .asset_source
+            .load_if_exists::<OggLoader, OggStream>(id)?
+        {
+            Some(stream) => MusicData::Stream(stream),
+            None => return Ok(None),
+        };
+        // Streaming is not supported on the web
+        #[cfg(target_arch = "wasm32")]
+        let data = match ctx
+            .asset_source
+            .load_if_exists::<OggLoader, StaticSoundData>(id)?
+        {
+            Some(sound_data) => MusicData::Decoded(sound_data),
+            None => return Ok(None),
+        };
+
+        // Load the metadata if it exists
+        let metadata = MusicMetadata::load_if_exists(id, ctx)?.unwrap_or_default();
+
+        Ok(Some(Self { data, metadata }))
+    }
+}
+
+/// Audio of a music track.
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum MusicData {
+    /// Encoded audio to stream from.
+    #[cfg(not(target_arch = "wasm32"))]
+    Stream(OggStream),
+    /// Fully decoded audio, on the web where streaming is not supported.
+    #[cfg(target_arch = "wasm32")]
+    Decoded(StaticSoundData),
+}
+
+/// Music metadata to load from RON.
+///
+/// # Example
+///
+/// A `track.ron` file next to `track.ogg` that plays an intro of 4 seconds once and then keeps looping until 62.5 seconds:
+///
+/// ```ron
+/// (loop_start: 4.0, loop_end: 62.5)
+/// ```
+#[derive(Debug, Clone, Copy, DeRon)]
+pub struct MusicMetadata {
+    /// Seconds from the start of the track where the loop starts.
+    ///
+    /// Defaults to the start of the track.
+    #[nserde(default)]
+    pub(crate) loop_start: f64,
+    /// Seconds from the start of the track where the loop ends and jumps back to the loop start.
+    ///
+    /// Defaults to the end of the track.
+    #[nserde(default = "f64::INFINITY")]
+    pub(crate) loop_end: f64,
+}
+
+impl MusicMetadata {
+    /// Region of the track that should be looped.
+    pub(crate) fn loop_region(self) -> Region {
+        if self.loop_end.is_finite() {
+            (self.loop_start..self.loop_end).into()
+        } else {
+            (self.loop_start..).into()
+        }
+    }
+}
+
+impl Default for MusicMetadata {
+    fn default() -> Self {
+        Self {
+            loop_start: 0.0,
+            loop_end: f64::INFINITY,
+        }
+    }
+}
+
+impl Loadable for MusicMetadata {
+    #[inline]
+    fn load_if_exists(id: &Id, ctx: &mut ContextInner) -> Result<Option<Self>, AssetError>
+    where
+        Self: Sized,
+    {
+        ctx.asset_source.load_if_exists::<RonLoader, _>(id)
+    }
+}
@@ -1,26 +1,39 @@
 //! Sprite asset.
 
+use std::collections::HashMap;
+
 use glam::{Affine2, Vec2};
 use nanoserde::DeRon;
 
 use crate::{
     assets::{
-        loader::{png::PngLoader, ron::RonLoader},
-        Id,
+        loader::{
+            aseprite::{AseLoader, Aseprite, AsepriteLoader, AsepriteTag},
+            png::{PngImage, PngLoader},
+            ron::RonLoader,
+        },
+        AssetError, Id,
     },
     context::ContextInner,
-    graphics::atlas::TextureRef,
+    graphics::atlas::{self, TextureRef},
 };
 
 use super::Loadable;
 
+/// Default duration of a single animation frame in seconds.
+const DEFAULT_FRAME_DURATION: f32 = 0.1;
+
 /// Sprite asset that can be loaded with metadata.
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub(crate) struct Sprite {
     /// Reference to the texture on the GPU.
     pub(crate) texture: TextureRef,
     /// Sub rectangle of the sprite to draw, can be used to split a sprite sheet.
+    ///
+    /// For animated sprites this is the first frame.
     pub(crate) sub_rectangle: (f32, f32, f32, f32),
+    /// Sub rectangles of all animation frames, empty when the sprite is not animated.
+    pub(crate) frames: Vec<(f32, f32, f32, f32)>,
     /// Sprite metadata.
     pub(crate) metadata: SpriteMetadata,
 }
@@ -44,65 +57,124 @@ impl Sprite {
         // Use default metadata
         let metadata = SpriteMetadata::default();
 
+        // Not animated
+        let frames = Vec::new();
+
         Self {
             texture,
             sub_rectangle,
+            frames,
             metadata,
         }
     }
 
+    /// Textures in the atlas used by the sprite.
+    pub(crate) fn textures(&self) -> Vec<TextureRef> {
+        vec![self.texture]
+    }
+
     /// Split into equal horizontal parts.
-    pub(crate) fn horizontal_parts(&self, part_width: f32) -> Vec<Self> {
+    ///
+    /// # Errors
+    ///
+    /// - When the width of the sprite is not a multiple of the part width.
+    pub(crate) fn horizontal_parts(&self, part_width: f32) -> Result<Vec<Self>, String> {
         let (x, y, width, height) = self.sub_rectangle;
 
         // Ensure that the image can be split into equal parts
-        assert!(
-            width % part_width == 0.0,
-            "Cannot split image into equal horizontal parts of {part_width} pixels"
-        );
+        if width % part_width != 0.0 {
+            return Err(format!(
+                "cannot split image into equal horizontal parts of {part_width} pixels"
+            ));
+        }
 
         // How many images we need to make
         let sub_images = (width / part_width) as usize;
 
-        (0..sub_images)
+        Ok((0..sub_images)
             .map(|index| {
                 // Use the same sub rectangle only changing the position and size
                 let sub_rectangle = (part_width.mul_add(index as f32, x), y, part_width, height);
 
                 Self {
                     sub_rectangle,
-                    ..*self
+                    ..self.clone()
                 }
             })
-            .collect()
+            .collect())
+    }
+
+    /// Get the sub rectangle of the frame of an animation clip at a point in time.
+    ///
+    /// The frames of the clip are validated when loading, so only a sprite without frames uses its own sub rectangle.
+    ///
+    /// # Panics
+    ///
+    /// - When the clip is not defined in the metadata.
+    pub(crate) fn animation_frame(&self, clip: &str, time: f32) -> (f32, f32, f32, f32) {
+        let clip = self
+            .metadata
+            .clips
+            .get(clip)
+            .unwrap_or_else(|| panic!("Sprite has no animation clip named '{clip}'"));
+
+        // A sprite without frames is a single frame
+        let frame = clip.frame_at(time, self.frames.len().max(1));
+
+        self.frames
+            .get(frame)
+            .copied()
+            .unwrap_or(self.sub_rectangle)
     }
 
     /// Calculate the transformation matrix.
     #[inline]
     pub(crate) fn affine_matrix(&self, x: f32, y: f32, rotation: f32) -> Affine2 {
+        self.transformed_affine_matrix(self.sub_rectangle, x, y, rotation, (1.0, 1.0), None)
+    }
+
+    /// Calculate the transformation matrix with scaling and an optional pivot point.
+    ///
+    /// The sub rectangle is needed because the size of animation frames can differ, only the size is used.
+    /// The pivot is relative to the left top of the sprite, when `None` the sprite offset is used.
+    #[inline]
+    pub(crate) fn transformed_affine_matrix(
+        &self,
+        (_, _, width, height): (f32, f32, f32, f32),
+        x: f32,
+        y: f32,
+        rotation: f32,
+        (scale_x, scale_y): (f32, f32),
+        pivot: Option<(f32, f32)>,
+    ) -> Affine2 {
         // Adjust by the sprite offset
-        let (sprite_offset_x, sprite_offset_y) = self
-            .metadata
-            .offset
-            .offset(self.sub_rectangle.2, self.sub_rectangle.3);
+        let (sprite_offset_x, sprite_offset_y) = self.metadata.offset.offset(width, height);
 
-        // Draw with a more optimized version if no rotation needs to be applied
-        if rotation == 0.0 {
-            Affine2::from_translation((x + sprite_offset_x, y + sprite_offset_y).into())
-        } else {
-            // We rotate so first apply the rotation based on the sprite offset
-            let mut affine = Affine2::from_angle(rotation)
-                * Affine2::from_translation((sprite_offset_x, sprite_offset_y).into());
+        // Draw with a more optimized version if no rotation or scaling needs to be applied
+        let scale = Vec2::new(scale_x, scale_y);
+        if rotation == 0.0 && scale == Vec2::ONE {
+            return Affine2::from_translation((x + sprite_offset_x, y + sprite_offset_y).into());
+        }
 
-            // Then apply the world coordinates so it stays rotated in place
-            affine.translation += Vec2::new(x, y);
+        // Point the sprite is rotated, scaled and flipped around, defaults to the sprite offset
+        let (pivot_x, pivot_y) = pivot.unwrap_or((-sprite_offset_x, -sprite_offset_y));
 
-            affine
-        }
+        // Move the pivot to the origin so we can rotate and scale around it
+        let mut affine = Affine2::from_scale_angle_translation(scale, rotation, Vec2::ZERO)
+            * Affine2::from_translation((-pivot_x, -pivot_y).into());
+
+        // Then move the pivot back and apply the world coordinates so it stays transformed in place
+        affine.translation +=
+            Vec2::new(x + sprite_offset_x + pivot_x, y + sprite_offset_y + pivot_y);
+
+        affine
     }
 
     /// Load the sprite without metadata.
-    pub(crate) fn load_if_exists_without_metadata(id: &Id, ctx: &mut ContextInner) -> Option<Self> {
+    pub(crate) fn load_if_exists_without_metadata(
+        id: &Id,
+        ctx: &mut ContextInner,
+    ) -> Result<Option<Self>, AssetError> {
         // Check if there's already an static embedded texture with this ID
         let (texture, width, height) = if let Some(texture) = ctx.asset_source.embedded_texture(id)
         {
@@ -113,20 +185,20 @@ impl Sprite {
             )
         } else {
             // Load the PNG
-            let mut png = ctx.asset_source.load_if_exists::<PngLoader, _>(id)?;
+            let Some(png) = ctx.asset_source.load_if_exists::<PngLoader, PngImage>(id)? else {
+                return Ok(None);
+            };
 
-            // Read the PNG
-            let mut pixels = vec![0_u32; png.output_buffer_size()];
-            let info = png
-                .next_frame(bytemuck::cast_slice_mut(&mut pixels))
-                .expect("Error reading image");
+            // Fail instead of panicking when packing an image that can never fit
+            atlas::ensure_fits_page(png.width, png.height)
+                .map_err(|err| AssetError::decode(id, err))?;
 
             // Upload it to the GPU, returning a reference
             let texture = ctx
                 .graphics
-                .upload_texture(info.width, info.height, &pixels);
+                .upload_texture(png.width, png.height, &png.pixels);
 
-            (texture, info.width as f32, info.height as f32)
+            (texture, png.width as f32, png.height as f32)
         };
 
         // Create the sub rectangle from the size
@@ -135,26 +207,142 @@ impl Sprite {
         // Use default metadata
         let metadata = SpriteMetadata::default();
 
-        Some(Self {
+        // Not animated
+        let frames = Vec::new();
+
+        Ok(Some(Self {
+            texture,
+            sub_rectangle,
+            frames,
+            metadata,
+        }))
+    }
+
+    /// Load the sprite from an Aseprite file, with the frames and the tags as animation clips.
+    fn load_aseprite_if_exists(
+        id: &Id,
+        ctx: &mut ContextInner,
+    ) -> Result<Option<Self>, AssetError> {
+        // Try both extensions
+        let aseprite = match ctx
+            .asset_source
+            .load_if_exists::<AsepriteLoader, Aseprite>(id)?
+        {
+            Some(aseprite) => aseprite,
+            None => match ctx.asset_source.load_if_exists::<AseLoader, Aseprite>(id)? {
+                Some(aseprite) => aseprite,
+                None => return Ok(None),
+            },
+        };
+
+        // Lay out the frames in a grid so the texture doesn't become too wide
+        let frame_width = aseprite.width;
+        let frame_height = aseprite.height;
+        let columns = (aseprite.frames.len() as f32).sqrt().ceil().max(1.0) as u32;
+        let rows = (aseprite.frames.len() as u32).div_ceil(columns).max(1);
+        let width = columns * frame_width;
+        let height = rows * frame_height;
+
+        // Fail instead of panicking when packing a sheet that can never fit
+        atlas::ensure_fits_page(width, height).map_err(|err| AssetError::decode(id, err))?;
+
+        // Copy each frame into the grid
+        let mut pixels = vec![0_u32; (width * height) as usize];
+        let mut rectangles = Vec::with_capacity(aseprite.frames.len());
+        for (index, frame) in aseprite.frames.iter().enumerate() {
+            let x = index as u32 % columns * frame_width;
+            let y = index as u32 / columns * frame_height;
+
+            for (row, frame_row) in frame.pixels.chunks_exact(frame_width as usize).enumerate() {
+                let start = ((y + row as u32) * width + x) as usize;
+                pixels[start..start + frame_width as usize].copy_from_slice(frame_row);
+            }
+
+            rectangles.push((x as f32, y as f32, frame_width as f32, frame_height as f32));
+        }
+
+        // Upload it to the GPU, returning a reference
+        let texture = ctx.graphics.upload_texture(width, height, &pixels);
+
+        // Convert the tags to animation clips
+        let durations = aseprite
+            .frames
+            .iter()
+            .map(|frame| frame.duration)
+            .collect::<Vec<_>>();
+        let clips = aseprite
+            .tags
+            .iter()
+            .map(|tag| (tag.name.clone(), AnimationClip::from_tag(tag, &durations)))
+            .collect();
+
+        // Draw the whole sheet, the frames are split when loading the metadata
+        let sub_rectangle = (0.0, 0.0, width as f32, height as f32);
+        let metadata = SpriteMetadata {
+            frames: SpriteFrames::Rectangles(rectangles),
+            clips,
+            ..Default::default()
+        };
+
+        // Frames are resolved from the metadata
+        let frames = Vec::new();
+
+        Ok(Some(Self {
             texture,
             sub_rectangle,
+            frames,
             metadata,
-        })
+        }))
     }
 }
 
 impl Loadable for Sprite {
-    fn load_if_exists(id: &Id, ctx: &mut ContextInner) -> Option<Self>
+    fn load_if_exists(id: &Id, ctx: &mut ContextInner) -> Result<Option<Self>, AssetError>
     where
         Self: Sized,
     {
-        // Load without metadata
-        let mut sprite = Self::load_if_exists_without_metadata(id, ctx)?;
+        // Load without metadata, falling back to an Aseprite file which already contains the frames and clips
+        let mut sprite = match Self::load_if_exists_without_metadata(id, ctx)? {
+            Some(sprite) => sprite,
+            None => match Self::load_aseprite_if_exists(id, ctx)? {
+                Some(sprite) => sprite,
+                None => return Ok(None),
+            },
+        };
+
+        // Load the metadata if it exists, the clips in it replace the clips with the same name from the Aseprite tags
+        if let Some(SpriteMetadata {
+            offset,
+            frames,
+            clips,
+        }) = SpriteMetadata::load_if_exists(id, ctx)?
+        {
+            sprite.metadata.offset = offset;
+            if frames != SpriteFrames::Single {
+                sprite.metadata.frames = frames;
+            }
+            sprite.metadata.clips.extend(clips);
+        }
+
+        // Split the image into animation frames
+        sprite.frames = sprite
+            .metadata
+            .frames
+            .rectangles(sprite.sub_rectangle.2, sprite.sub_rectangle.3)
+            .map_err(|err| AssetError::decode(id, err))?;
 
-        // Load the metadata, or use the default if it doesn't exit
-        sprite.metadata = SpriteMetadata::load_if_exists(id, ctx).unwrap_or_default();
+        // Ensure the clips only use existing frames, a sprite without frames is a single frame
+        for (name, clip) in &sprite.metadata.clips {
+            clip.validate(sprite.frames.len().max(1))
+                .map_err(|err| AssetError::decode(id, format!("animation clip '{name}': {err}")))?;
+        }
 
-        Some(sprite)
+        // Draw the first frame when no animation is used
+        if let Some(first_frame) = sprite.frames.first() {
+            sprite.sub_rectangle = *first_frame;
+        }
+
+        Ok(Some(sprite))
     }
 }
 
@@ -190,19 +378,354 @@ impl SpriteOffset {
     }
 }
 
+/// How the frames of an animated sprite are laid out in the image.
+#[derive(Debug, Clone, PartialEq, Default, DeRon)]
+pub enum SpriteFrames {
+    /// The whole image is a single frame, the sprite is not animated.
+    #[default]
+    Single,
+    /// Frames of equal size, counted from left to right and then from top to bottom.
+    Grid {
+        /// Width of a single frame in pixels.
+        width: f32,
+        /// Height of a single frame in pixels.
+        height: f32,
+    },
+    /// Explicit `(x, y, width, height)` rectangles of each frame in pixels.
+    Rectangles(Vec<(f32, f32, f32, f32)>),
+}
+
+impl SpriteFrames {
+    /// Get the rectangles of all frames based on the image size.
+    ///
+    /// Returns an empty list when the sprite is not animated.
+    ///
+    /// # Errors
+    ///
+    /// - When the grid cell is empty or bigger than the image.
+    /// - When a rectangle is empty or not fully inside the image.
+    pub(crate) fn rectangles(
+        &self,
+        image_width: f32,
+        image_height: f32,
+    ) -> Result<Vec<(f32, f32, f32, f32)>, String> {
+        match self {
+            Self::Grid { width, height } => {
+                // An empty cell would fit an infinite amount of times
+                if !(*width > 0.0 && *height > 0.0) {
+                    return Err(format!(
+                        "animation frame of {width}x{height} pixels is empty"
+                    ));
+                }
+
+                // How many frames fit in the image
+                let columns = (image_width / width).floor() as usize;
+                let rows = (image_height / height).floor() as usize;
+                if columns == 0 || rows == 0 {
+                    return Err(format!("animation frame of {width}x{height} pixels doesn't fit in image of {image_width}x{image_height} pixels"));
+                }
+
+                Ok((0..rows)
+                    .flat_map(|row| {
+                        (0..columns).map(move |column| {
+                            (column as f32 * width, row as f32 * height, *width, *height)
+                        })
+                    })
+                    .collect())
+            }
+            Self::Single => Ok(Vec::new()),
+            Self::Rectangles(rectangles) => {
+                // Pixels outside of the image belong to other textures in the atlas
+                if let Some((x, y, width, height)) =
+                    rectangles.iter().find(|(x, y, width, height)| {
+                        !(*x >= 0.0
+                            && *y >= 0.0
+                            && *width > 0.0
+                            && *height > 0.0
+                            && x + width <= image_width
+                            && y + height <= image_height)
+                    })
+                {
+                    return Err(format!("animation frame rectangle ({x}, {y}, {width}, {height}) is not inside image of {image_width}x{image_height} pixels"));
+                }
+
+                Ok(rectangles.clone())
+            }
+        }
+    }
+}
+
+/// How an animation clip continues after the last frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, DeRon)]
+pub enum AnimationMode {
+    /// Start again from the first frame.
+    #[default]
+    Loop,
+    /// Play the frames backwards until the first frame, then forwards again.
+    PingPong,
+    /// Stay on the last frame.
+    Once,
+}
+
+/// Named sequence of animation frames.
+#[derive(Debug, Clone, Default, DeRon)]
+pub struct AnimationClip {
+    /// Indices of the frames to show in order.
+    ///
+    /// Defaults to all frames.
+    #[nserde(default)]
+    pub(crate) frames: Vec<usize>,
+    /// Duration of each frame in seconds.
+    ///
+    /// When there are less durations than frames the last duration is used for the remaining frames, defaults to `0.1` seconds.
+    #[nserde(default)]
+    pub(crate) durations: Vec<f32>,
+    /// How the clip continues after the last frame.
+    #[nserde(default)]
+    pub(crate) mode: AnimationMode,
+}
+
+impl AnimationClip {
+    /// Create a clip from a tag in an Aseprite file.
+    ///
+    /// Tags that repeat a limited amount of times are expanded into a clip that's played once.
+    fn from_tag(tag: &AsepriteTag, durations: &[f32]) -> Self {
+        // Frames of a single pass in the direction of the tag
+        let mut pass = (tag.from..=tag.to).collect::<Vec<_>>();
+        if tag.reverse {
+            pass.reverse();
+        }
+
+        let (frames, mode) = match (tag.repeat, tag.ping_pong) {
+            // Infinitely repeating
+            (0, false) => (pass, AnimationMode::Loop),
+            (0, true) => (pass, AnimationMode::PingPong),
+            // Repeat each pass, ping-pong alternates the direction without showing the turning frame twice
+            (repeat, ping_pong) => {
+                let mut frames = pass.clone();
+                for index in 1..repeat {
+                    if ping_pong && index % 2 == 1 {
+                        frames.extend(pass.iter().rev().skip(1));
+                    } else if ping_pong {
+                        frames.extend(pass.iter().skip(1));
+                    } else {
+                        frames.extend(&pass);
+                    }
+                }
+
+                (frames, AnimationMode::Once)
+            }
+        };
+
+        // Use the duration of each frame from the file
+        let durations = frames
+            .iter()
+            .map(|frame| {
+                durations
+                    .get(*frame)
+                    .copied()
+                    .unwrap_or(DEFAULT_FRAME_DURATION)
+            })
+            .collect();
+
+        Self {
+            frames,
+            durations,
+            mode,
+        }
+    }
+
+    /// Ensure all frames of the clip exist in the sprite.
+    ///
+    /// # Errors
+    ///
+    /// - When a frame index is out of range.
+    fn validate(&self, sprite_frames: usize) -> Result<(), String> {
+        self.frames
+            .iter()
+            .find(|frame| **frame >= sprite_frames)
+            .map_or(Ok(()), |frame| {
+                Err(format!(
+                    "frame {frame} doesn't exist, sprite has {sprite_frames} frames"
+                ))
+            })
+    }
+
+    /// Get the index of the frame of the sprite at a point in time.
+    pub(crate) fn frame_at(&self, time: f32, sprite_frames: usize) -> usize {
+        // Use all frames when none are defined
+        let frames = if self.frames.is_empty() {
+            sprite_frames
+        } else {
+            self.frames.len()
+        };
+        let frame = |index: usize| self.frames.get(index).copied().unwrap_or(index);
+        let duration = |index: usize| {
+            self.durations
+                .get(index)
+                .or_else(|| self.durations.last())
+                .copied()
+                .unwrap_or(DEFAULT_FRAME_DURATION)
+        };
+
+        // Order of the frames in a single cycle, ping-pong doesn't repeat the first and last frames
+        let backwards = if self.mode == AnimationMode::PingPong {
+            1..frames.saturating_sub(1)
+        } else {
+            0..0
+        };
+        let sequence = (0..frames).chain(backwards.rev());
+
+        // Move the time inside a single cycle
+        let total_duration: f32 = sequence.clone().map(duration).sum();
+        if total_duration <= 0.0 {
+            return frame(0);
+        }
+        let time = match self.mode {
+            AnimationMode::Loop | AnimationMode::PingPong => time.rem_euclid(total_duration),
+            AnimationMode::Once => time.clamp(0.0, total_duration),
+        };
+
+        // Find the frame the time is in
+        let mut elapsed = 0.0;
+        let mut last = 0;
+        for index in sequence {
+            elapsed += duration(index);
+            last = index;
+
+            if time < elapsed {
+                break;
+            }
+        }
+
+        frame(last)
+    }
+}
+
 /// Sprite metadata to load from data formats.
-#[derive(Debug, Clone, Copy, Default, DeRon)]
+#[derive(Debug, Clone, Default, DeRon)]
 pub struct SpriteMetadata {
     /// Pixel offset to render at.
+    #[nserde(default)]
     pub(crate) offset: SpriteOffset,
+    /// Layout of the animation frames in the image.
+    ///
+    /// Defaults to the whole image being a single frame.
+    #[nserde(default)]
+    pub(crate) frames: SpriteFrames,
+    /// Named animation clips.
+    #[nserde(default)]
+    pub(crate) clips: HashMap<String, AnimationClip>,
 }
 
 impl Loadable for SpriteMetadata {
     #[inline]
-    fn load_if_exists(id: &Id, ctx: &mut ContextInner) -> Option<Self>
+    fn load_if_exists(id: &Id, ctx: &mut ContextInner) -> Result<Option<Self>, AssetError>
     where
         Self: Sized,
     {
         ctx.asset_source.load_if_exists::<RonLoader, _>(id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frames of a clip over time, sampled in the middle of every `0.1` seconds.
+    fn sample(clip: &AnimationClip, sprite_frames: usize, samples: usize) -> Vec<usize> {
+        (0..samples)
+            .map(|index| clip.frame_at((index as f32).mul_add(0.1, 0.05), sprite_frames))
+            .collect()
+    }
+
+    #[test]
+    fn frame_at_modes() {
+        // Looping starts again at the first frame
+        let clip = AnimationClip {
+            frames: vec![2, 3, 4],
+            ..Default::default()
+        };
+        assert_eq!(sample(&clip, 5, 7), [2, 3, 4, 2, 3, 4, 2]);
+
+        // Ping-pong doesn't repeat the first and last frames
+        let clip = AnimationClip {
+            mode: AnimationMode::PingPong,
+            ..clip
+        };
+        assert_eq!(sample(&clip, 5, 9), [2, 3, 4, 3, 2, 3, 4, 3, 2]);
+
+        // Once stays on the last frame, also before the start
+        let clip = AnimationClip {
+            mode: AnimationMode::Once,
+            ..clip
+        };
+        assert_eq!(sample(&clip, 5, 6), [2, 3, 4, 4, 4, 4]);
+        assert_eq!(clip.frame_at(-1.0, 5), 2);
+    }
+
+    #[test]
+    fn frame_at_durations() {
+        // Each frame has its own duration, the last one is used for the remaining frames
+        let clip = AnimationClip {
+            frames: vec![0, 1, 2],
+            durations: vec![0.2, 0.1],
+            ..Default::default()
+        };
+        assert_eq!(sample(&clip, 3, 8), [0, 0, 1, 2, 0, 0, 1, 2]);
+
+        // Without any positive duration the first frame is always shown
+        let clip = AnimationClip {
+            durations: vec![0.0],
+            ..clip
+        };
+        assert_eq!(sample(&clip, 3, 3), [0, 0, 0]);
+    }
+
+    #[test]
+    fn frame_at_empty_frames() {
+        // No frames means all frames of the sprite in order
+        let clip = AnimationClip::default();
+        assert_eq!(sample(&clip, 3, 4), [0, 1, 2, 0]);
+
+        // A sprite without frames is a single frame
+        assert_eq!(sample(&clip, 0, 2), [0, 0]);
+        assert_eq!(sample(&clip, 1, 2), [0, 0]);
+    }
+
+    #[test]
+    fn invalid_frames() {
+        // Empty grid cells
+        SpriteFrames::Grid {
+            width: 0.0,
+            height: 8.0,
+        }
+        .rectangles(16.0, 16.0)
+        .unwrap_err();
+        SpriteFrames::Grid {
+            width: 8.0,
+            height: -1.0,
+        }
+        .rectangles(16.0, 16.0)
+        .unwrap_err();
+
+        // Rectangles outside of the image
+        SpriteFrames::Rectangles(vec![(8.0, 0.0, 9.0, 8.0)])
+            .rectangles(16.0, 16.0)
+            .unwrap_err();
+        SpriteFrames::Rectangles(vec![(-1.0, 0.0, 8.0, 8.0)])
+            .rectangles(16.0, 16.0)
+            .unwrap_err();
+        SpriteFrames::Rectangles(vec![(0.0, 0.0, 16.0, 16.0)])
+            .rectangles(16.0, 16.0)
+            .unwrap();
+
+        // Clip frames that don't exist
+        let clip = AnimationClip {
+            frames: vec![0, 4],
+            ..Default::default()
+        };
+        clip.validate(4).unwrap_err();
+        clip.validate(5).unwrap();
+    }
+}
@@ -0,0 +1,664 @@
+//! Aseprite asset loading.
+//!
+//! Implements the parts of the [file format specification](https://github.com/aseprite/aseprite/blob/main/docs/ase-file-specs.md) needed for drawing animated sprites.
+
+use miniz_oxide::inflate::decompress_to_vec_zlib;
+
+use crate::assets::{AssetError, Id};
+
+use super::Loader;
+
+/// Magic number in the file header.
+const HEADER_MAGIC: u16 = 0xA5E0;
+/// Magic number in each frame header.
+const FRAME_MAGIC: u16 = 0xF1FA;
+
+/// Chunk type of a palette in older files.
+const CHUNK_OLD_PALETTE: u16 = 0x0004;
+/// Chunk type of a layer.
+const CHUNK_LAYER: u16 = 0x2004;
+/// Chunk type of a cel, the image of a layer in a frame.
+const CHUNK_CEL: u16 = 0x2005;
+/// Chunk type of the animation tags.
+const CHUNK_TAGS: u16 = 0x2018;
+/// Chunk type of a palette.
+const CHUNK_PALETTE: u16 = 0x2019;
+
+/// Aseprite file with all visible layers of each frame flattened into a single image, returned from the loader.
+#[derive(Debug, Clone)]
+pub struct Aseprite {
+    /// Width of a single frame in pixels.
+    pub(crate) width: u32,
+    /// Height of a single frame in pixels.
+    pub(crate) height: u32,
+    /// Flattened frames.
+    pub(crate) frames: Vec<AsepriteFrame>,
+    /// Named ranges of frames.
+    pub(crate) tags: Vec<AsepriteTag>,
+}
+
+/// Single flattened frame of an Aseprite file.
+#[derive(Debug, Clone)]
+pub(crate) struct AsepriteFrame {
+    /// RGBA pixels of the frame.
+    pub(crate) pixels: Vec<u32>,
+    /// How long the frame is shown in seconds.
+    pub(crate) duration: f32,
+}
+
+/// Named range of frames in an Aseprite file.
+#[derive(Debug, Clone)]
+pub(crate) struct AsepriteTag {
+    /// Name of the tag.
+    pub(crate) name: String,
+    /// First frame of the tag.
+    pub(crate) from: usize,
+    /// Last frame of the tag, inclusive.
+    pub(crate) to: usize,
+    /// Whether the frames are played backwards.
+    pub(crate) reverse: bool,
+    /// Whether the frames are played forwards and backwards.
+    pub(crate) ping_pong: bool,
+    /// How many times the tag is played, `0` is infinite.
+    pub(crate) repeat: u16,
+}
+
+/// Aseprite asset loader for files with the `.aseprite` extension.
+///
+/// Layer blend modes are ignored, all layers are blended normally.
+#[non_exhaustive]
+pub struct AsepriteLoader;
+
+impl Loader<Aseprite> for AsepriteLoader {
+    const EXTENSION: &'static str = "aseprite";
+
+    #[inline]
+    fn load(bytes: &[u8], id: &Id) -> Result<Aseprite, AssetError> {
+        Aseprite::parse(bytes).map_err(|err| AssetError::decode(id, err))
+    }
+}
+
+/// Aseprite asset loader for files with the `.ase` extension.
+///
+/// Layer blend modes are ignored, all layers are blended normally.
+#[non_exhaustive]
+pub struct AseLoader;
+
+impl Loader<Aseprite> for AseLoader {
+    const EXTENSION: &'static str = "ase";
+
+    #[inline]
+    fn load(bytes: &[u8], id: &Id) -> Result<Aseprite, AssetError> {
+        AsepriteLoader::load(bytes, id)
+    }
+}
+
+impl Aseprite {
+    /// Parse the file and flatten all frames.
+    fn parse(bytes: &[u8]) -> Result<Self, &'static str> {
+        let mut reader = ByteReader::new(bytes);
+
+        // Parse the header
+        let _file_size = reader.dword()?;
+        if reader.word()? != HEADER_MAGIC {
+            return Err("not an Aseprite file");
+        }
+        let frame_count = reader.word()?;
+        let width = u32::from(reader.word()?);
+        let height = u32::from(reader.word()?);
+        let color_depth = reader.word()?;
+        let layer_opacity_valid = reader.dword()? & 1 == 1;
+        // Skip the deprecated speed and two reserved values
+        reader.skip(10)?;
+        let transparent_index = reader.byte()?;
+        // Skip the rest of the header
+        reader.skip(128 - 29)?;
+
+        if !matches!(color_depth, 8 | 16 | 32) {
+            return Err("unsupported color depth");
+        }
+
+        let mut layers = Vec::new();
+        // Visibility of the parent groups for each child level
+        let mut group_visibility = Vec::new();
+        let mut palette = vec![0_u32; 256];
+        let mut tags = Vec::new();
+
+        // Keep the cels of each frame so linked cels can refer to them
+        let mut frame_cels: Vec<Vec<Cel>> = Vec::with_capacity(frame_count.into());
+        let mut frame_durations = Vec::with_capacity(frame_count.into());
+
+        for _ in 0..frame_count {
+            // Parse the frame header
+            let frame_size = reader.dword()? as usize;
+            if reader.word()? != FRAME_MAGIC {
+                return Err("invalid frame header");
+            }
+            let old_chunk_count = reader.word()?;
+            frame_durations.push(f32::from(reader.word()?) / 1000.0);
+            reader.skip(2)?;
+            let new_chunk_count = reader.dword()?;
+            let chunk_count = if new_chunk_count == 0 {
+                u32::from(old_chunk_count)
+            } else {
+                new_chunk_count
+            };
+
+            // Only read inside the frame
+            let mut frame_reader = ByteReader::new(reader.take(frame_size.saturating_sub(16))?);
+
+            let mut cels = Vec::new();
+            for _ in 0..chunk_count {
+                let chunk_size = frame_reader.dword()? as usize;
+                let chunk_type = frame_reader.word()?;
+                let mut chunk = ByteReader::new(frame_reader.take(chunk_size.saturating_sub(6))?);
+
+                match chunk_type {
+                    CHUNK_LAYER => {
+                        let flags = chunk.word()?;
+                        let layer_type = chunk.word()?;
+                        let child_level = usize::from(chunk.word()?);
+                        // Skip the default size and blend mode
+                        chunk.skip(6)?;
+                        let opacity = chunk.byte()?;
+
+                        // Hidden groups hide all their children, reference layers are never shown
+                        let parent_visible = child_level == 0
+                            || group_visibility.get(child_level - 1) == Some(&true);
+                        let visible = parent_visible && flags & 1 == 1 && flags & 64 == 0;
+                        group_visibility.truncate(child_level);
+                        group_visibility.push(visible);
+
+                        layers.push(Layer {
+                            // Only normal image layers are drawn
+                            visible: visible && layer_type == 0,
+                            opacity: if layer_opacity_valid { opacity } else { 255 },
+                        });
+                    }
+                    CHUNK_CEL => {
+                        let layer = usize::from(chunk.word()?);
+                        let x = i32::from(chunk.short()?);
+                        let y = i32::from(chunk.short()?);
+                        let opacity = chunk.byte()?;
+                        let cel_type = chunk.word()?;
+                        let z_index = i32::from(chunk.short()?);
+                        chunk.skip(5)?;
+
+                        let cel = match cel_type {
+                            // Raw or compressed image
+                            0 | 2 => {
+                                let cel_width = u32::from(chunk.word()?);
+                                let cel_height = u32::from(chunk.word()?);
+                                let data = if cel_type == 0 {
+                                    chunk.rest().to_vec()
+                                } else {
+                                    decompress_to_vec_zlib(chunk.rest())
+                                        .or(Err("invalid compressed cel"))?
+                                };
+
+                                Cel {
+                                    layer,
+                                    x,
+                                    y,
+                                    opacity,
+                                    z_index,
+                                    width: cel_width,
+                                    height: cel_height,
+                                    pixels: decode_pixels(
+                                        &data,
+                                        cel_width * cel_height,
+                                        color_depth,
+                                        &palette,
+                                        transparent_index,
+                                    )?,
+                                }
+                            }
+                            // Linked to the cel of the same layer in another frame
+                            1 => {
+                                let linked_frame = usize::from(chunk.word()?);
+
+                                frame_cels
+                                    .get(linked_frame)
+                                    .and_then(|cels| cels.iter().find(|cel| cel.layer == layer))
+                                    .ok_or("linked cel not found")?
+                                    .clone()
+                            }
+                            // Tilemaps are not supported
+                            _ => continue,
+                        };
+
+                        cels.push(cel);
+                    }
+                    CHUNK_PALETTE => {
+                        // Indexed pixels are a single byte, so only the first 256 colors are used
+                        let _size = chunk.dword()?;
+                        let first = chunk.dword()? as usize;
+                        let last = chunk.dword()? as usize;
+                        chunk.skip(8)?;
+
+                        for index in first..=last {
+                            let flags = chunk.word()?;
+                            let rgba = [chunk.byte()?, chunk.byte()?, chunk.byte()?, chunk.byte()?];
+                            // Skip the color name
+                            if flags & 1 == 1 {
+                                chunk.string()?;
+                            }
+
+                            if let Some(color) = palette.get_mut(index) {
+                                *color = u32::from_le_bytes(rgba);
+                            }
+                        }
+                    }
+                    CHUNK_OLD_PALETTE => {
+                        let packets = chunk.word()?;
+
+                        let mut index = 0;
+                        for _ in 0..packets {
+                            index += usize::from(chunk.byte()?);
+                            let colors = match chunk.byte()? {
+                                0 => 256,
+                                colors => usize::from(colors),
+                            };
+
+                            for _ in 0..colors {
+                                let rgba = [chunk.byte()?, chunk.byte()?, chunk.byte()?, 255];
+
+                                if let Some(color) = palette.get_mut(index) {
+                                    *color = u32::from_le_bytes(rgba);
+                                }
+                                index += 1;
+                            }
+                        }
+                    }
+                    CHUNK_TAGS => {
+                        let tag_count = chunk.word()?;
+                        chunk.skip(8)?;
+
+                        for _ in 0..tag_count {
+                            let from = usize::from(chunk.word()?);
+                            let to = usize::from(chunk.word()?);
+                            let direction = chunk.byte()?;
+                            let repeat = chunk.word()?;
+                            // Skip the reserved bytes and the deprecated color
+                            chunk.skip(10)?;
+                            let name = chunk.string()?;
+
+                            tags.push(AsepriteTag {
+                                name,
+                                from,
+                                to,
+                                reverse: matches!(direction, 1 | 3),
+                                ping_pong: matches!(direction, 2 | 3),
+                                repeat,
+                            });
+                        }
+                    }
+                    // Ignore all other chunks
+                    _ => (),
+                }
+            }
+
+            frame_cels.push(cels);
+        }
+
+        // Flatten the layers of each frame
+        let frames = frame_cels
+            .iter()
+            .zip(frame_durations)
+            .map(|(cels, duration)| AsepriteFrame {
+                pixels: flatten(cels, &layers, width, height),
+                duration,
+            })
+            .collect();
+
+        Ok(Self {
+            width,
+            height,
+            frames,
+            tags,
+        })
+    }
+}
+
+/// Layer information needed for flattening.
+struct Layer {
+    /// Whether the layer and all its parents are visible.
+    visible: bool,
+    /// Opacity of the whole layer.
+    opacity: u8,
+}
+
+/// Image of a single layer in a single frame.
+#[derive(Clone)]
+struct Cel {
+    /// Index of the layer.
+    layer: usize,
+    /// Horizontal position in the frame.
+    x: i32,
+    /// Vertical position in the frame.
+    y: i32,
+    /// Opacity of the cel.
+    opacity: u8,
+    /// Order relative to the layer.
+    z_index: i32,
+    /// Width of the image.
+    width: u32,
+    /// Height of the image.
+    height: u32,
+    /// RGBA pixels of the image.
+    pixels: Vec<u32>,
+}
+
+/// Blend all visible cels into a single image.
+fn flatten(cels: &[Cel], layers: &[Layer], width: u32, height: u32) -> Vec<u32> {
+    let mut pixels = vec![0_u32; (width * height) as usize];
+
+    // Draw the cels in the order of their layer, offset by their z-index
+    let mut cels = cels.iter().collect::<Vec<_>>();
+    cels.sort_by_key(|cel| (cel.layer as i32 + cel.z_index, cel.z_index));
+
+    for cel in cels {
+        let Some(layer) = layers.get(cel.layer).filter(|layer| layer.visible) else {
+            continue;
+        };
+        let opacity = f32::from(cel.opacity) / 255.0 * f32::from(layer.opacity) / 255.0;
+
+        for cel_y in 0..cel.height {
+            // Skip the parts outside of the frame
+            let y = cel.y + cel_y as i32;
+            if y < 0 || y >= height as i32 {
+                continue;
+            }
+
+            for cel_x in 0..cel.width {
+                let x = cel.x + cel_x as i32;
+                if x < 0 || x >= width as i32 {
+                    continue;
+                }
+
+                let source = cel.pixels[(cel_y * cel.width + cel_x) as usize];
+                let target = &mut pixels[(y as u32 * width + x as u32) as usize];
+                *target = blend(source, *target, opacity);
+            }
+        }
+    }
+
+    pixels
+}
+
+/// Blend a RGBA pixel over another RGBA pixel.
+fn blend(source: u32, target: u32, opacity: f32) -> u32 {
+    let [source_r, source_g, source_b, source_a] = source.to_le_bytes();
+    let [target_r, target_g, target_b, target_a] = target.to_le_bytes();
+
+    let source_alpha = f32::from(source_a) / 255.0 * opacity;
+    let target_alpha = f32::from(target_a) / 255.0;
+
+    // Nothing to blend
+    if source_alpha <= 0.0 {
+        return target;
+    }
+
+    let alpha = target_alpha.mul_add(1.0 - source_alpha, source_alpha);
+    let channel = |source: u8, target: u8| {
+        let source = f32::from(source) * source_alpha;
+        let target = f32::from(target) * target_alpha * (1.0 - source_alpha);
+
+        ((source + target) / alpha).round() as u8
+    };
+
+    u32::from_le_bytes([
+        channel(source_r, target_r),
+        channel(source_g, target_g),
+        channel(source_b, target_b),
+        (alpha * 255.0).round() as u8,
+    ])
+}
+
+/// Convert the raw pixels of a cel to RGBA pixels.
+///
+/// # Errors
+///
+/// - When the data contains less pixels than the size of the cel.
+fn decode_pixels(
+    data: &[u8],
+    pixel_count: u32,
+    color_depth: u16,
+    palette: &[u32],
+    transparent_index: u8,
+) -> Result<Vec<u32>, &'static str> {
+    // Ignore any data after the last pixel
+    let bytes_per_pixel = usize::from(color_depth / 8);
+    let data = data
+        .get(..pixel_count as usize * bytes_per_pixel)
+        .ok_or("cel contains less pixels than its size")?;
+
+    Ok(match color_depth {
+        // RGBA
+        32 => data
+            .chunks_exact(4)
+            .map(|rgba| rgba.try_into().map_or(0, u32::from_le_bytes))
+            .collect(),
+        // Grayscale with alpha
+        16 => data
+            .chunks_exact(2)
+            .map(|value_alpha| match *value_alpha {
+                [value, alpha] => u32::from_le_bytes([value, value, value, alpha]),
+                _ => 0,
+            })
+            .collect(),
+        // Indexed
+        8 => data
+            .iter()
+            .map(|index| {
+                if *index == transparent_index {
+                    0
+                } else {
+                    palette.get(usize::from(*index)).copied().unwrap_or(0)
+                }
+            })
+            .collect(),
+        _ => return Err("unsupported color depth"),
+    })
+}
+
+/// Read little-endian values from bytes.
+struct ByteReader<'a> {
+    /// Bytes to read.
+    bytes: &'a [u8],
+    /// Current position in the bytes.
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    /// Start reading at the beginning.
+    const fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, position: 0 }
+    }
+
+    /// Read a slice of bytes.
+    fn take(&mut self, amount: usize) -> Result<&'a [u8], &'static str> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + amount)
+            .ok_or("unexpected end of file")?;
+        self.position += amount;
+
+        Ok(bytes)
+    }
+
+    /// Read all remaining bytes.
+    fn rest(&mut self) -> &'a [u8] {
+        let bytes = self.bytes.get(self.position..).unwrap_or_default();
+        self.position = self.bytes.len();
+
+        bytes
+    }
+
+    /// Skip bytes.
+    fn skip(&mut self, amount: usize) -> Result<(), &'static str> {
+        self.take(amount).map(|_| ())
+    }
+
+    /// Read a fixed amount of bytes.
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], &'static str> {
+        self.take(N)?.try_into().or(Err("unexpected end of file"))
+    }
+
+    /// Read an unsigned 8 bit value.
+    fn byte(&mut self) -> Result<u8, &'static str> {
+        Ok(self.take(1)?[0])
+    }
+
+    /// Read an unsigned 16 bit value.
+    fn word(&mut self) -> Result<u16, &'static str> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    /// Read a signed 16 bit value.
+    fn short(&mut self) -> Result<i16, &'static str> {
+        self.array().map(i16::from_le_bytes)
+    }
+
+    /// Read an unsigned 32 bit value.
+    fn dword(&mut self) -> Result<u32, &'static str> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    /// Read a string prefixed with its length.
+    fn string(&mut self) -> Result<String, &'static str> {
+        let length = usize::from(self.word()?);
+
+        Ok(String::from_utf8_lossy(self.take(length)?).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a file with a single frame of 2x2 pixels containing the chunks.
+    fn file(color_depth: u16, chunks: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        // Frame header followed by the chunks with their size and type
+        let mut frame = Vec::new();
+        let chunks_size = chunks.iter().map(|(_, data)| data.len() + 6).sum::<usize>();
+        frame.extend(((16 + chunks_size) as u32).to_le_bytes());
+        frame.extend(FRAME_MAGIC.to_le_bytes());
+        frame.extend((chunks.len() as u16).to_le_bytes());
+        // Duration in milliseconds
+        frame.extend(100_u16.to_le_bytes());
+        frame.extend([0; 6]);
+        for (chunk_type, data) in chunks {
+            frame.extend(((data.len() + 6) as u32).to_le_bytes());
+            frame.extend(chunk_type.to_le_bytes());
+            frame.extend(data);
+        }
+
+        // Header with a valid layer opacity and a transparent index of `0`
+        let mut bytes = Vec::new();
+        bytes.extend(((128 + frame.len()) as u32).to_le_bytes());
+        bytes.extend(HEADER_MAGIC.to_le_bytes());
+        for word in [1, 2, 2, color_depth] {
+            bytes.extend(u16::to_le_bytes(word));
+        }
+        bytes.extend(1_u32.to_le_bytes());
+        bytes.resize(128, 0);
+        bytes.extend(frame);
+
+        bytes
+    }
+
+    /// Visible normal layer chunk.
+    fn layer() -> (u16, Vec<u8>) {
+        let mut data = Vec::new();
+        // Visible flag, image type and no parent
+        for word in [1, 0, 0, 0, 0, 0] {
+            data.extend(u16::to_le_bytes(word));
+        }
+        // Opacity and reserved bytes
+        data.extend([255, 0, 0, 0]);
+        // Name
+        data.extend(5_u16.to_le_bytes());
+        data.extend(b"Layer");
+
+        (CHUNK_LAYER, data)
+    }
+
+    /// Raw cel chunk at a position in the first layer.
+    fn cel(x: i16, y: i16, width: u16, height: u16, pixels: &[u8]) -> (u16, Vec<u8>) {
+        let mut data = Vec::new();
+        data.extend(0_u16.to_le_bytes());
+        data.extend(x.to_le_bytes());
+        data.extend(y.to_le_bytes());
+        // Opacity, raw cel type, z-index and reserved bytes
+        data.push(255);
+        data.extend([0; 9]);
+        data.extend(width.to_le_bytes());
+        data.extend(height.to_le_bytes());
+        data.extend(pixels);
+
+        (CHUNK_CEL, data)
+    }
+
+    #[test]
+    fn rgba_cel() {
+        let pixels = [1, 2, 3, 255, 4, 5, 6, 255, 7, 8, 9, 255, 10, 11, 12, 255];
+        let aseprite = Aseprite::parse(&file(32, &[layer(), cel(0, 0, 2, 2, &pixels)])).unwrap();
+
+        assert_eq!((aseprite.width, aseprite.height), (2, 2));
+        assert_eq!(aseprite.frames.len(), 1);
+        assert!((aseprite.frames[0].duration - 0.1).abs() < f32::EPSILON);
+        assert_eq!(
+            aseprite.frames[0].pixels,
+            [
+                u32::from_le_bytes([1, 2, 3, 255]),
+                u32::from_le_bytes([4, 5, 6, 255]),
+                u32::from_le_bytes([7, 8, 9, 255]),
+                u32::from_le_bytes([10, 11, 12, 255]),
+            ]
+        );
+    }
+
+    #[test]
+    fn indexed_cel() {
+        // Palette with a single red color at index `1`
+        let mut palette = Vec::new();
+        for dword in [2, 1, 1, 0, 0] {
+            palette.extend(u32::to_le_bytes(dword));
+        }
+        palette.extend(0_u16.to_le_bytes());
+        palette.extend([255, 0, 0, 255]);
+
+        // The transparent index is not drawn, the cel is clipped by the frame
+        let aseprite = Aseprite::parse(&file(
+            8,
+            &[
+                (CHUNK_PALETTE, palette),
+                layer(),
+                cel(1, 0, 2, 2, &[1, 1, 0, 1]),
+            ],
+        ))
+        .unwrap();
+
+        let red = u32::from_le_bytes([255, 0, 0, 255]);
+        assert_eq!(aseprite.frames[0].pixels, [0, red, 0, 0]);
+    }
+
+    #[test]
+    fn truncated_cel() {
+        // Cel of 2x2 pixels with only 3 pixels of data
+        let pixels = [255; 12];
+        assert_eq!(
+            Aseprite::parse(&file(32, &[layer(), cel(0, 0, 2, 2, &pixels)])).err(),
+            Some("cel contains less pixels than its size")
+        );
+
+        // File ending in the middle of a chunk
+        let mut bytes = file(32, &[layer()]);
+        bytes.truncate(bytes.len() - 4);
+        assert_eq!(
+            Aseprite::parse(&bytes).err(),
+            Some("unexpected end of file")
+        );
+    }
+}
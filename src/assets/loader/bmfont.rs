@@ -0,0 +1,372 @@
+//! `AngelCode` `BMFont` descriptor asset loading.
+//!
+//! Supports both the text and the binary version of the [file format](https://www.angelcode.com/products/bmfont/doc/file_format.html).
+
+use crate::assets::{AssetError, Id};
+
+use super::Loader;
+
+/// Magic bytes at the start of a binary descriptor.
+const BINARY_MAGIC: &[u8] = b"BMF";
+
+/// Maximum amount of page images, pages are referenced by a single byte in binary descriptors.
+const MAX_PAGES: usize = 256;
+
+/// Block type of the common font information in binary descriptors.
+const BLOCK_COMMON: u8 = 2;
+/// Block type of the page file names in binary descriptors.
+const BLOCK_PAGES: u8 = 3;
+/// Block type of the characters in binary descriptors.
+const BLOCK_CHARS: u8 = 4;
+/// Block type of the kerning pairs in binary descriptors.
+const BLOCK_KERNING_PAIRS: u8 = 5;
+
+/// Parsed `BMFont` descriptor, returned from the loader.
+///
+/// The page images are not loaded.
+#[derive(Debug, Clone, Default)]
+pub struct BmFont {
+    /// Vertical distance between the top of two lines in pixels.
+    pub(crate) line_height: f32,
+    /// File names of the page images relative to the descriptor.
+    pub(crate) pages: Vec<String>,
+    /// All characters in the font.
+    pub(crate) chars: Vec<BmFontChar>,
+    /// Horizontal adjustments between pairs of characters.
+    pub(crate) kerning_pairs: Vec<BmFontKerningPair>,
+}
+
+/// Single character in a `BMFont` descriptor.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct BmFontChar {
+    /// Unicode code point of the character.
+    pub(crate) id: u32,
+    /// Rectangle `(x, y, width, height)` of the glyph in the page image.
+    pub(crate) rectangle: (f32, f32, f32, f32),
+    /// Offset `(x, y)` from the cursor to draw the glyph at.
+    pub(crate) offset: (f32, f32),
+    /// Horizontal distance the cursor moves after drawing the character.
+    pub(crate) advance: f32,
+    /// Index of the page image containing the glyph.
+    pub(crate) page: usize,
+}
+
+/// Horizontal adjustment between two characters in a `BMFont` descriptor.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct BmFontKerningPair {
+    /// Unicode code point of the first character.
+    pub(crate) first: u32,
+    /// Unicode code point of the second character.
+    pub(crate) second: u32,
+    /// Pixels to move the second character.
+    pub(crate) amount: f32,
+}
+
+/// `BMFont` descriptor asset loader.
+///
+/// Detects whether the descriptor is in the text or binary format, the XML format is not supported.
+#[non_exhaustive]
+pub struct BmFontLoader;
+
+impl Loader<BmFont> for BmFontLoader {
+    const EXTENSION: &'static str = "fnt";
+
+    #[inline]
+    fn load(bytes: &[u8], id: &Id) -> Result<BmFont, AssetError> {
+        let result = if bytes.starts_with(BINARY_MAGIC) {
+            BmFont::parse_binary(bytes)
+        } else {
+            BmFont::parse_text(&String::from_utf8_lossy(bytes))
+        };
+
+        result.map_err(|err| AssetError::decode(id, err))
+    }
+}
+
+impl BmFont {
+    /// Parse the text version of the descriptor.
+    fn parse_text(text: &str) -> Result<Self, String> {
+        let mut bmfont = Self::default();
+        let mut page_count = MAX_PAGES;
+
+        for line in text.lines() {
+            // Split the line in the tag and the key value pairs
+            let mut tokens = tokenize(line);
+            let Some(tag) = tokens.next() else {
+                continue;
+            };
+            let attributes = tokens
+                .filter_map(|token| token.split_once('='))
+                .collect::<Vec<_>>();
+
+            // Get the value of a key, missing keys are zero
+            let value = |key: &str| -> Result<f32, String> {
+                attributes
+                    .iter()
+                    .find(|(attribute_key, _)| *attribute_key == key)
+                    .map_or(Ok(0.0), |(_, value)| {
+                        value.parse().map_err(|err| {
+                            format!("invalid value '{value}' for '{key}' in '{line}': {err}")
+                        })
+                    })
+            };
+
+            match tag {
+                "common" => {
+                    bmfont.line_height = value("lineHeight")?;
+
+                    // Older descriptors don't define the amount of pages
+                    let pages = value("pages")? as usize;
+                    if pages > 0 {
+                        page_count = pages.min(MAX_PAGES);
+                    }
+                }
+                "page" => {
+                    let page = value("id")? as usize;
+                    if page >= page_count {
+                        return Err(format!(
+                            "page {page} is out of range of {page_count} pages in '{line}'"
+                        ));
+                    }
+                    let file = attributes
+                        .iter()
+                        .find(|(key, _)| *key == "file")
+                        .map(|(_, file)| file.trim_matches('"').to_owned())
+                        .ok_or_else(|| format!("page without file in '{line}'"))?;
+
+                    // Pages can be defined in any order
+                    if bmfont.pages.len() <= page {
+                        bmfont.pages.resize(page + 1, String::new());
+                    }
+                    bmfont.pages[page] = file;
+                }
+                "char" => bmfont.chars.push(BmFontChar {
+                    id: value("id")? as u32,
+                    rectangle: (value("x")?, value("y")?, value("width")?, value("height")?),
+                    offset: (value("xoffset")?, value("yoffset")?),
+                    advance: value("xadvance")?,
+                    page: value("page")? as usize,
+                }),
+                "kerning" => bmfont.kerning_pairs.push(BmFontKerningPair {
+                    first: value("first")? as u32,
+                    second: value("second")? as u32,
+                    amount: value("amount")?,
+                }),
+                // Ignore all other tags
+                _ => (),
+            }
+        }
+
+        Ok(bmfont)
+    }
+
+    /// Parse the binary version of the descriptor.
+    fn parse_binary(bytes: &[u8]) -> Result<Self, String> {
+        let mut bmfont = Self::default();
+
+        // Skip the magic and the version
+        let mut position = BINARY_MAGIC.len() + 1;
+
+        while position < bytes.len() {
+            // Read the block header
+            let block_type = bytes[position];
+            let size = read_u32(bytes, position + 1)? as usize;
+            let block = bytes
+                .get(position + 5..position + 5 + size)
+                .ok_or("unexpected end of file")?;
+            position += 5 + size;
+
+            match block_type {
+                BLOCK_COMMON => bmfont.line_height = f32::from(read_u16(block, 0)?),
+                BLOCK_PAGES => {
+                    // Null-terminated strings
+                    bmfont.pages = block
+                        .split(|byte| *byte == 0)
+                        .filter(|file| !file.is_empty())
+                        .map(|file| String::from_utf8_lossy(file).into_owned())
+                        .collect();
+                }
+                BLOCK_CHARS => {
+                    for char in block.chunks_exact(20) {
+                        bmfont.chars.push(BmFontChar {
+                            id: read_u32(char, 0)?,
+                            rectangle: (
+                                f32::from(read_u16(char, 4)?),
+                                f32::from(read_u16(char, 6)?),
+                                f32::from(read_u16(char, 8)?),
+                                f32::from(read_u16(char, 10)?),
+                            ),
+                            offset: (
+                                f32::from(read_i16(char, 12)?),
+                                f32::from(read_i16(char, 14)?),
+                            ),
+                            advance: f32::from(read_i16(char, 16)?),
+                            page: usize::from(*char.get(18).ok_or("unexpected end of file")?),
+                        });
+                    }
+                }
+                BLOCK_KERNING_PAIRS => {
+                    for pair in block.chunks_exact(10) {
+                        bmfont.kerning_pairs.push(BmFontKerningPair {
+                            first: read_u32(pair, 0)?,
+                            second: read_u32(pair, 4)?,
+                            amount: f32::from(read_i16(pair, 8)?),
+                        });
+                    }
+                }
+                // Ignore the info block
+                _ => (),
+            }
+        }
+
+        Ok(bmfont)
+    }
+}
+
+/// Split a line of the text descriptor on whitespace, keeping quoted values together.
+fn tokenize(line: &str) -> impl Iterator<Item = &str> {
+    let mut quoted = false;
+
+    line.split(move |ch: char| {
+        if ch == '"' {
+            quoted = !quoted;
+        }
+
+        ch.is_whitespace() && !quoted
+    })
+    .filter(|token| !token.is_empty())
+}
+
+/// Read a little-endian unsigned 32 bit value.
+fn read_u32(bytes: &[u8], position: usize) -> Result<u32, &'static str> {
+    read_array(bytes, position).map(u32::from_le_bytes)
+}
+
+/// Read a little-endian unsigned 16 bit value.
+fn read_u16(bytes: &[u8], position: usize) -> Result<u16, &'static str> {
+    read_array(bytes, position).map(u16::from_le_bytes)
+}
+
+/// Read a little-endian signed 16 bit value.
+fn read_i16(bytes: &[u8], position: usize) -> Result<i16, &'static str> {
+    read_array(bytes, position).map(i16::from_le_bytes)
+}
+
+/// Read a fixed amount of bytes.
+fn read_array<const N: usize>(bytes: &[u8], position: usize) -> Result<[u8; N], &'static str> {
+    bytes
+        .get(position..position + N)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("unexpected end of file")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check that the descriptor matches both test fonts.
+    fn assert_font(bmfont: &BmFont) {
+        assert!((bmfont.line_height - 10.0).abs() < f32::EPSILON);
+        assert_eq!(bmfont.pages, ["first page.png", "second page.png"]);
+
+        let chars = bmfont
+            .chars
+            .iter()
+            .map(|char| {
+                (
+                    char.id,
+                    char.rectangle,
+                    char.offset,
+                    char.advance,
+                    char.page,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            chars,
+            [
+                (65, (0.0, 0.0, 5.0, 7.0), (0.0, 1.0), 6.0, 0),
+                (66, (5.0, 0.0, 5.0, 7.0), (-1.0, 1.0), 6.0, 1),
+            ]
+        );
+
+        let kerning_pairs = bmfont
+            .kerning_pairs
+            .iter()
+            .map(|pair| (pair.first, pair.second, pair.amount))
+            .collect::<Vec<_>>();
+        assert_eq!(kerning_pairs, [(65, 66, -1.0)]);
+    }
+
+    #[test]
+    fn parse_text() {
+        let bmfont = BmFont::parse_text(
+            r#"info face="Pixel Font" size=8
+common lineHeight=10 base=8 scaleW=64 scaleH=64 pages=2
+page id=1 file="second page.png"
+page id=0 file="first page.png"
+chars count=2
+char id=65 x=0 y=0 width=5 height=7 xoffset=0 yoffset=1 xadvance=6 page=0 chnl=15
+char id=66 x=5 y=0 width=5 height=7 xoffset=-1 yoffset=1 xadvance=6 page=1 chnl=15
+kernings count=1
+kerning first=65 second=66 amount=-1
+"#,
+        )
+        .unwrap();
+
+        assert_font(&bmfont);
+    }
+
+    #[test]
+    fn parse_binary() {
+        let mut bytes = b"BMF\x03".to_vec();
+        let mut block = |block_type: u8, data: &[u8]| {
+            bytes.push(block_type);
+            bytes.extend((data.len() as u32).to_le_bytes());
+            bytes.extend(data);
+        };
+
+        // Info block is ignored
+        block(1, &[8, 0, 0, 0]);
+
+        // Line height, base, scale width, scale height and pages followed by the bit field and channels
+        let mut common = Vec::new();
+        for word in [10_u16, 8, 64, 64, 2] {
+            common.extend(word.to_le_bytes());
+        }
+        common.extend([0; 5]);
+        block(BLOCK_COMMON, &common);
+
+        block(BLOCK_PAGES, b"first page.png\0second page.png\0");
+
+        let mut chars = Vec::new();
+        for (id, x, x_offset, page) in [(65_u32, 0_u16, 0_i16, 0_u8), (66, 5, -1, 1)] {
+            chars.extend(id.to_le_bytes());
+            for word in [x, 0, 5, 7] {
+                chars.extend(word.to_le_bytes());
+            }
+            for word in [x_offset, 1, 6] {
+                chars.extend(word.to_le_bytes());
+            }
+            chars.extend([page, 15]);
+        }
+        block(BLOCK_CHARS, &chars);
+
+        let mut kerning_pairs = Vec::new();
+        kerning_pairs.extend(65_u32.to_le_bytes());
+        kerning_pairs.extend(66_u32.to_le_bytes());
+        kerning_pairs.extend((-1_i16).to_le_bytes());
+        block(BLOCK_KERNING_PAIRS, &kerning_pairs);
+
+        assert_font(&BmFont::parse_binary(&bytes).unwrap());
+    }
+
+    #[test]
+    fn page_out_of_range() {
+        // Outside of the defined amount of pages
+        BmFont::parse_text("common lineHeight=10 pages=1\npage id=1 file=\"a.png\"").unwrap_err();
+
+        // Huge page ID without defining the amount of pages
+        BmFont::parse_text("page id=4000000000 file=\"a.png\"").unwrap_err();
+    }
+}
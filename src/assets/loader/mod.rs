@@ -1,10 +1,14 @@
 //! Asset loader helper.
 
+pub mod aseprite;
+pub mod bmfont;
+pub mod flac;
 pub mod ogg;
 pub mod png;
 pub mod ron;
+pub mod wav;
 
-use super::Id;
+use super::{AssetError, Id};
 
 /// How an asset should be loaded.
 pub trait Loader<T> {
@@ -18,8 +22,8 @@ pub trait Loader<T> {
     /// * `bytes` - Raw bytes from the file, could be loaded either from disk or from memory.
     /// * `id` - ID of the asset to load, mainly used for printing somewhat useful panic messages.
     ///
-    /// # Panics
+    /// # Errors
     ///
     /// - When anything went wrong with loading the asset.
-    fn load(bytes: &[u8], id: &Id) -> T;
+    fn load(bytes: &[u8], id: &Id) -> Result<T, AssetError>;
 }
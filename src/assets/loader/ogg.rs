@@ -1,13 +1,25 @@
 //! OGG asset loading.
 
 use std::io::Cursor;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
 
 use kira::sound::static_sound::StaticSoundData;
+#[cfg(not(target_arch = "wasm32"))]
+use kira::sound::streaming::StreamingSoundData;
 
-use crate::assets::Id;
+use crate::assets::{AssetError, Id};
 
 use super::Loader;
 
+/// Encoded OGG audio, returned from the loader.
+///
+/// Decoded in small chunks while it's being played, so a long track doesn't need to fit in memory decoded.
+/// Not available on the web, where audio can't be streamed.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct OggStream(pub(crate) Arc<[u8]>);
+
 /// OGG audio asset loader.
 #[non_exhaustive]
 pub struct OggLoader;
@@ -16,14 +28,28 @@ impl Loader<StaticSoundData> for OggLoader {
     const EXTENSION: &'static str = "ogg";
 
     #[inline]
-    fn load(bytes: &[u8], id: &Id) -> StaticSoundData {
+    fn load(bytes: &[u8], id: &Id) -> Result<StaticSoundData, AssetError> {
         // Allocate the bytes into a cursor
         let bytes = Cursor::new(bytes.to_vec());
 
         // Parse the sound file
-        match StaticSoundData::from_cursor(bytes) {
-            Ok(sound) => sound,
-            Err(err) => panic!("Error loading audio file from ID '{id}': {err}"),
-        }
+        StaticSoundData::from_cursor(bytes).map_err(|err| AssetError::decode(id, err))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Loader<OggStream> for OggLoader {
+    const EXTENSION: &'static str = "ogg";
+
+    #[inline]
+    fn load(bytes: &[u8], id: &Id) -> Result<OggStream, AssetError> {
+        // Keep the encoded bytes so a decoder can be created every time it's played
+        let bytes: Arc<[u8]> = Arc::from(bytes);
+
+        // Parse the headers once so invalid files fail when loading instead of when playing
+        StreamingSoundData::from_cursor(Cursor::new(Arc::clone(&bytes)))
+            .map_err(|err| AssetError::decode(id, err))?;
+
+        Ok(OggStream(bytes))
     }
 }
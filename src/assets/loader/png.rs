@@ -4,13 +4,25 @@ use std::io::Cursor;
 
 use png::{BitDepth, ColorType, Decoder, Reader, Transformations};
 
-use crate::assets::Id;
+use crate::assets::{AssetError, Id};
 
 use super::Loader;
 
 /// PNG reader type, returned from the loader.
 pub type PngReader = Reader<Cursor<Vec<u8>>>;
 
+/// Fully decoded PNG image, returned from the loader.
+#[derive(Debug, Clone)]
+#[allow(clippy::exhaustive_structs)]
+pub struct PngImage {
+    /// Width of the image in pixels.
+    pub width: u32,
+    /// Height of the image in pixels.
+    pub height: u32,
+    /// RGBA pixels of the image with each color component as a single byte, row by row.
+    pub pixels: Vec<u32>,
+}
+
 /// PNG asset loader.
 ///
 /// Doesn't fully parse the PNG but loads a reader.
@@ -21,7 +33,7 @@ impl Loader<PngReader> for PngLoader {
     const EXTENSION: &'static str = "png";
 
     #[inline]
-    fn load(bytes: &[u8], id: &Id) -> PngReader {
+    fn load(bytes: &[u8], id: &Id) -> Result<PngReader, AssetError> {
         // Copy the bytes into a cursor
         let cursor = Cursor::new(bytes.to_vec());
 
@@ -38,17 +50,44 @@ impl Loader<PngReader> for PngLoader {
             .set_transformations(Transformations::normalize_to_color8() | Transformations::ALPHA);
 
         // Start parsing the PNG
-        let reader = decoder.read_info().unwrap();
+        let reader = decoder
+            .read_info()
+            .map_err(|err| AssetError::decode(id, err))?;
 
         // Ensure we can use the PNG colors
         let (color_type, bits) = reader.output_color_type();
 
         // Must be 8 bit RGBA or indexed
-        assert!(
-            color_type == ColorType::Rgba && bits == BitDepth::Eight,
-            "PNG of asset with ID '{id}' is not 8 bit RGB with an alpha channel"
-        );
+        if color_type != ColorType::Rgba || bits != BitDepth::Eight {
+            return Err(AssetError::PixelFormat {
+                id: id.clone(),
+                format: format!("{color_type:?} with a bit depth of {}", bits as u8),
+            });
+        }
+
+        Ok(reader)
+    }
+}
+
+impl Loader<PngImage> for PngLoader {
+    const EXTENSION: &'static str = "png";
+
+    #[inline]
+    fn load(bytes: &[u8], id: &Id) -> Result<PngImage, AssetError> {
+        // Start parsing the PNG
+        let mut png = <Self as Loader<PngReader>>::load(bytes, id)?;
+
+        // Read the pixels
+        let mut pixels = vec![0_u32; png.output_buffer_size()];
+        let info = png
+            .next_frame(bytemuck::cast_slice_mut(&mut pixels))
+            .map_err(|err| AssetError::decode(id, err))?;
+        pixels.truncate(info.width as usize * info.height as usize);
 
-        reader
+        Ok(PngImage {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
     }
 }
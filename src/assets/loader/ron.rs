@@ -2,7 +2,7 @@
 
 use nanoserde::DeRon;
 
-use crate::assets::Id;
+use crate::assets::{AssetError, Id};
 
 use super::Loader;
 
@@ -14,14 +14,11 @@ impl<T: DeRon> Loader<T> for RonLoader {
     const EXTENSION: &'static str = "ron";
 
     #[inline]
-    fn load(bytes: &[u8], id: &Id) -> T {
+    fn load(bytes: &[u8], id: &Id) -> Result<T, AssetError> {
         // Convert raw bytes to a valid UTF-8 string
         let string = String::from_utf8_lossy(bytes);
 
         // Deserialize the RON
-        match DeRon::deserialize_ron(&string) {
-            Ok(de) => de,
-            Err(err) => panic!("Error loading RON asset with ID '{id}':\n{err}"),
-        }
+        DeRon::deserialize_ron(&string).map_err(|err| AssetError::metadata(id, err))
     }
 }
@@ -0,0 +1,26 @@
+//! WAV asset loading.
+
+use std::io::Cursor;
+
+use kira::sound::static_sound::StaticSoundData;
+
+use crate::assets::{AssetError, Id};
+
+use super::Loader;
+
+/// WAV audio asset loader.
+#[non_exhaustive]
+pub struct WavLoader;
+
+impl Loader<StaticSoundData> for WavLoader {
+    const EXTENSION: &'static str = "wav";
+
+    #[inline]
+    fn load(bytes: &[u8], id: &Id) -> Result<StaticSoundData, AssetError> {
+        // Allocate the bytes into a cursor
+        let bytes = Cursor::new(bytes.to_vec());
+
+        // Parse the sound file
+        StaticSoundData::from_cursor(bytes).map_err(|err| AssetError::decode(id, err))
+    }
+}
@@ -1,9 +1,12 @@
 //! Asset loading and management.
 
+pub(crate) mod dependencies;
+mod error;
 #[cfg(not(target_arch = "wasm32"))]
 pub(crate) mod hot_reload;
 pub mod loadable;
 pub mod loader;
+pub(crate) mod preload;
 #[doc(hidden)]
 pub mod source;
 
@@ -12,7 +15,9 @@ use std::rc::Rc;
 use hashbrown::HashMap;
 use smol_str::SmolStr;
 
+pub use self::error::AssetError;
 use self::loadable::Loadable;
+use crate::context::ContextInner;
 
 /// Identifier for any loadable asset, can be assigned multiple times for different types.
 ///
@@ -48,10 +53,10 @@ impl<T: Loadable> AssetManager<T> {
         asset
     }
 
-    /// Remove a loaded asset, mainly used for hot-reloading.
+    /// Whether an asset with the ID is loaded, mainly used for hot-reloading.
     #[inline]
-    pub(crate) fn remove(&mut self, id: &Id) {
-        self.assets.remove(id);
+    pub(crate) fn contains(&self, id: &Id) -> bool {
+        self.assets.contains_key(id)
     }
 }
 
@@ -63,12 +68,15 @@ impl<T: Loadable> Default for AssetManager<T> {
     }
 }
 
+/// Load a type erased custom asset.
+type DynLoader = fn(&Id, &mut ContextInner) -> Result<Rc<dyn Loadable>, AssetError>;
+
 /// Global asset manager for a any type not known at compile time.
 ///
 /// When hot-reloading is enabled all assets are loaded from disk, otherwise all assets are embedded in the binary.
 pub(crate) struct CustomAssetManager {
-    /// All loaded assets.
-    assets: HashMap<Id, Rc<dyn Loadable + 'static>>,
+    /// All loaded assets with the function to load them again with the same type.
+    assets: HashMap<Id, (Rc<dyn Loadable + 'static>, DynLoader)>,
 }
 
 impl CustomAssetManager {
@@ -79,7 +87,7 @@ impl CustomAssetManager {
         T: Loadable,
     {
         // Try to find the asset
-        let dyn_asset = self.assets.get(id)?;
+        let (dyn_asset, _) = self.assets.get(id)?;
 
         // Try to downcast it to the requested type
         Rc::clone(dyn_asset)
@@ -97,7 +105,8 @@ impl CustomAssetManager {
         let asset: Rc<dyn Loadable> = Rc::new(asset);
 
         // Store the asset so it can be accessed later again
-        self.assets.insert(id, Rc::clone(&asset));
+        self.assets
+            .insert(id, (Rc::clone(&asset), Self::load_dyn::<T>));
 
         // Safe to unwrap because we created the type here
         asset
@@ -105,10 +114,26 @@ impl CustomAssetManager {
             .unwrap_or_else(|_| panic!("Error downcasting type"))
     }
 
-    /// Remove a loaded asset, mainly used for hot-reloading.
+    /// Function to load the asset again with the same type, mainly used for hot-reloading.
+    #[inline]
+    pub(crate) fn loader(&self, id: &Id) -> Option<DynLoader> {
+        self.assets.get(id).map(|(_, loader)| *loader)
+    }
+
+    /// Replace a loaded asset with a new version of the same type, mainly used for hot-reloading.
     #[inline]
-    pub(crate) fn remove(&mut self, id: &Id) {
-        self.assets.remove(id);
+    pub(crate) fn replace(&mut self, id: &Id, asset: Rc<dyn Loadable>) {
+        if let Some((old_asset, _)) = self.assets.get_mut(id) {
+            *old_asset = asset;
+        }
+    }
+
+    /// Load a custom asset as a type erased asset.
+    fn load_dyn<T>(id: &Id, ctx: &mut ContextInner) -> Result<Rc<dyn Loadable>, AssetError>
+    where
+        T: Loadable,
+    {
+        T::load(id, ctx).map(|asset| Rc::new(asset) as Rc<dyn Loadable>)
     }
 }
 
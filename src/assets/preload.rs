@@ -0,0 +1,419 @@
+//! Decode asset files in the background before they are used.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::{
+    mpsc::{Receiver, Sender},
+    Arc, Mutex,
+};
+#[cfg(target_arch = "wasm32")]
+use std::time::Duration;
+use std::{
+    any::Any,
+    cell::Cell,
+    collections::VecDeque,
+    panic::{catch_unwind, AssertUnwindSafe},
+    rc::Rc,
+};
+
+use hashbrown::{hash_map::Entry, HashMap};
+use kira::sound::static_sound::StaticSoundData;
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::loader::ogg::OggStream;
+use super::{
+    loader::{
+        aseprite::{AseLoader, Aseprite, AsepriteLoader},
+        bmfont::{BmFont, BmFontLoader},
+        flac::FlacLoader,
+        ogg::OggLoader,
+        png::{PngImage, PngLoader},
+        wav::WavLoader,
+        Loader,
+    },
+    source::{AssetBytes, AssetFile, AssetSource},
+    AssetError, Id,
+};
+
+/// Maximum time spent decoding files every frame on the web, where there are no threads.
+#[cfg(target_arch = "wasm32")]
+const FRAME_BUDGET: Duration = Duration::from_millis(8);
+
+/// Type erased asset decoded in the background or why it failed.
+pub(crate) type DecodeResult = Result<Box<dyn Any + Send>, AssetError>;
+
+/// Decode the bytes of a file into a type erased asset.
+type Decoder = fn(&[u8], &Id) -> DecodeResult;
+
+/// Decoder for OGG files, only reads them on native because a track might be streamed as music.
+///
+/// Decoding a long track fully would take a lot of time and memory that are wasted when it's streamed.
+#[cfg(not(target_arch = "wasm32"))]
+const OGG_DECODER: (&str, Decoder) = (
+    <OggLoader as Loader<OggStream>>::EXTENSION,
+    decode::<OggLoader, OggStream>,
+);
+
+/// Decoder for OGG files, on the web music is also fully decoded.
+#[cfg(target_arch = "wasm32")]
+const OGG_DECODER: (&str, Decoder) = (
+    <OggLoader as Loader<StaticSoundData>>::EXTENSION,
+    decode::<OggLoader, StaticSoundData>,
+);
+
+/// Decoders for every file extension that's worth decoding in the background.
+///
+/// RON files are not included, they are cheap to parse and the type they are parsed into is only known when loading.
+const DECODERS: &[(&str, Decoder)] = &[
+    (
+        <PngLoader as Loader<PngImage>>::EXTENSION,
+        decode::<PngLoader, PngImage>,
+    ),
+    (
+        <AsepriteLoader as Loader<Aseprite>>::EXTENSION,
+        decode::<AsepriteLoader, Aseprite>,
+    ),
+    (
+        <AseLoader as Loader<Aseprite>>::EXTENSION,
+        decode::<AseLoader, Aseprite>,
+    ),
+    (
+        <BmFontLoader as Loader<BmFont>>::EXTENSION,
+        decode::<BmFontLoader, BmFont>,
+    ),
+    OGG_DECODER,
+    (
+        <WavLoader as Loader<StaticSoundData>>::EXTENSION,
+        decode::<WavLoader, StaticSoundData>,
+    ),
+    (
+        <FlacLoader as Loader<StaticSoundData>>::EXTENSION,
+        decode::<FlacLoader, StaticSoundData>,
+    ),
+];
+
+/// Amount of files of a single preload that are decoded.
+#[derive(Debug)]
+pub(crate) struct PreloadProgress {
+    /// Amount of files to decode.
+    pub(crate) total: usize,
+    /// Amount of files that are decoded, including the ones that failed.
+    pub(crate) decoded: Cell<usize>,
+}
+
+/// File that must be decoded.
+struct Job {
+    /// ID of the asset.
+    id: Id,
+    /// Extension the decoded file is stored with.
+    extension: &'static str,
+    /// Where the bytes of the file can be read.
+    bytes: AssetBytes,
+    /// How to decode the bytes.
+    decoder: Decoder,
+}
+
+impl Job {
+    /// Read and decode the file.
+    ///
+    /// A decoder that panics is treated as a file that could not be decoded, so the preload still finishes.
+    fn run(self) -> Decoded {
+        let result = self.bytes.read().map_or_else(
+            || {
+                Err(AssetError::NotFound {
+                    id: self.id.clone(),
+                })
+            },
+            |bytes| {
+                catch_unwind(AssertUnwindSafe(|| (self.decoder)(&bytes, &self.id))).unwrap_or_else(
+                    |panic| {
+                        // Panics with a message contain either a static or a formatted string
+                        let message = panic
+                            .downcast_ref::<&str>()
+                            .map(|message| (*message).to_owned())
+                            .or_else(|| panic.downcast_ref::<String>().cloned())
+                            .unwrap_or_default();
+
+                        Err(AssetError::decode(
+                            &self.id,
+                            format!("decoder panicked: {message}"),
+                        ))
+                    },
+                )
+            },
+        );
+
+        Decoded {
+            id: self.id,
+            extension: self.extension,
+            result,
+        }
+    }
+}
+
+/// File that's decoded.
+struct Decoded {
+    /// ID of the asset.
+    id: Id,
+    /// Extension the decoded file is stored with.
+    extension: &'static str,
+    /// Decoded asset or why it failed.
+    result: DecodeResult,
+}
+
+/// Decodes files of assets in the background.
+///
+/// On native platforms the files are decoded on worker threads, on the web a few are decoded every frame.
+pub(crate) struct Preloader {
+    /// Progress of every preload that's not finished yet, by index.
+    preloads: Vec<(usize, Rc<PreloadProgress>)>,
+    /// Index of the next preload.
+    next_preload: usize,
+    /// Indices of the preloads waiting for each file that's queued but not stored yet.
+    queued: HashMap<(Id, &'static str), Vec<usize>>,
+    /// Sender cloned into every worker thread.
+    #[cfg(not(target_arch = "wasm32"))]
+    sender: Sender<Decoded>,
+    /// Receiver of the files decoded by the worker threads.
+    #[cfg(not(target_arch = "wasm32"))]
+    receiver: Receiver<Decoded>,
+    /// Files still to decode.
+    #[cfg(target_arch = "wasm32")]
+    jobs: VecDeque<Job>,
+}
+
+impl Preloader {
+    /// Start decoding all files of which the ID matches any of the patterns.
+    ///
+    /// Files that are already decoded or of which the asset is already loaded are skipped.
+    /// Files that are already queued by another preload are not queued again, but are counted for both.
+    pub(crate) fn start(
+        &mut self,
+        patterns: &[String],
+        asset_source: &AssetSource,
+        is_loaded: impl Fn(&Id) -> bool,
+    ) -> Rc<PreloadProgress> {
+        let preload = self.next_preload;
+        self.next_preload += 1;
+
+        // Find all files to decode
+        let mut total = 0;
+        let jobs = asset_source
+            .files()
+            .into_iter()
+            .filter(|file| patterns.iter().any(|pattern| matches(pattern, &file.id)))
+            .filter(|file| !is_loaded(&file.id))
+            .filter_map(
+                |AssetFile {
+                     id,
+                     extension,
+                     bytes,
+                 }| {
+                    let (extension, decoder) = DECODERS
+                        .iter()
+                        .find(|(decoder_extension, _)| *decoder_extension == extension)
+                        .copied()?;
+
+                    if asset_source.is_preloaded(&id, extension) {
+                        return None;
+                    }
+                    total += 1;
+
+                    // Wait for the file when it's already being decoded
+                    match self.queued.entry((id.clone(), extension)) {
+                        Entry::Occupied(mut waiting) => {
+                            waiting.get_mut().push(preload);
+
+                            None
+                        }
+                        Entry::Vacant(waiting) => {
+                            waiting.insert(vec![preload]);
+
+                            Some(Job {
+                                id,
+                                extension,
+                                bytes,
+                                decoder,
+                            })
+                        }
+                    }
+                },
+            )
+            .collect::<VecDeque<_>>();
+
+        let progress = Rc::new(PreloadProgress {
+            total,
+            decoded: Cell::new(0),
+        });
+        if total > 0 {
+            self.preloads.push((preload, Rc::clone(&progress)));
+        }
+        if !jobs.is_empty() {
+            self.decode(jobs);
+        }
+
+        progress
+    }
+
+    /// Store the decoded files so they can be taken by the loaders.
+    pub(crate) fn update(&mut self, asset_source: &AssetSource) {
+        // Nothing to do most of the time
+        if self.preloads.is_empty() {
+            return;
+        }
+
+        // Receive the files decoded by the worker threads
+        #[cfg(not(target_arch = "wasm32"))]
+        while let Ok(decoded) = self.receiver.try_recv() {
+            self.store(decoded, asset_source);
+        }
+
+        // Decode files until the time for this frame is up
+        #[cfg(target_arch = "wasm32")]
+        {
+            let start = web_time::Instant::now();
+            while start.elapsed() < FRAME_BUDGET {
+                let Some(job) = self.jobs.pop_front() else {
+                    break;
+                };
+
+                self.store(job.run(), asset_source);
+            }
+        }
+
+        // Forget the finished preloads
+        self.preloads
+            .retain(|(_, progress)| progress.decoded.get() < progress.total);
+    }
+
+    /// Store a decoded file and update the progress of all preloads waiting for it.
+    fn store(&mut self, decoded: Decoded, asset_source: &AssetSource) {
+        let waiting = self
+            .queued
+            .remove(&(decoded.id.clone(), decoded.extension))
+            .unwrap_or_default();
+        for (_, progress) in self
+            .preloads
+            .iter()
+            .filter(|(preload, _)| waiting.contains(preload))
+        {
+            progress.decoded.set(progress.decoded.get() + 1);
+        }
+
+        asset_source.insert_preloaded(decoded.id, decoded.extension, decoded.result);
+    }
+
+    /// Decode the files on worker threads.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn decode(&self, jobs: VecDeque<Job>) {
+        // Use a thread for every core, but not more than there are files
+        let threads = std::thread::available_parallelism()
+            .map_or(1, usize::from)
+            .min(jobs.len());
+
+        let jobs = Arc::new(Mutex::new(jobs));
+        for _ in 0..threads {
+            let jobs = Arc::clone(&jobs);
+            let sender = self.sender.clone();
+
+            std::thread::spawn(move || loop {
+                // Take the next file, stop when all are taken
+                let Some(job) = jobs.lock().unwrap().pop_front() else {
+                    break;
+                };
+
+                // Stop when the context is gone
+                if sender.send(job.run()).is_err() {
+                    break;
+                }
+            });
+        }
+    }
+
+    /// Queue the files to be decoded a few every frame.
+    #[cfg(target_arch = "wasm32")]
+    fn decode(&mut self, jobs: VecDeque<Job>) {
+        self.jobs.extend(jobs);
+    }
+}
+
+impl Default for Preloader {
+    fn default() -> Self {
+        let preloads = Vec::new();
+        let next_preload = 0;
+        let queued = HashMap::new();
+        #[cfg(not(target_arch = "wasm32"))]
+        let (sender, receiver) = std::sync::mpsc::channel();
+        #[cfg(target_arch = "wasm32")]
+        let jobs = VecDeque::new();
+
+        Self {
+            preloads,
+            next_preload,
+            queued,
+            #[cfg(not(target_arch = "wasm32"))]
+            sender,
+            #[cfg(not(target_arch = "wasm32"))]
+            receiver,
+            #[cfg(target_arch = "wasm32")]
+            jobs,
+        }
+    }
+}
+
+/// Decode the bytes of a file with a loader into a type erased asset.
+fn decode<L, T>(bytes: &[u8], id: &Id) -> DecodeResult
+where
+    L: Loader<T>,
+    T: Send + 'static,
+{
+    L::load(bytes, id).map(|asset| Box::new(asset) as Box<dyn Any + Send>)
+}
+
+/// Whether an ID matches a pattern.
+///
+/// A pattern ending with `*` matches all IDs starting with the rest of the pattern, otherwise the ID must be equal.
+fn matches(pattern: &str, id: &str) -> bool {
+    pattern
+        .strip_suffix('*')
+        .map_or(pattern == id, |prefix| id.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panicking_decoder() {
+        // A decoder that panics should still send a result, as a failed decode
+        let job = Job {
+            id: Id::new("broken"),
+            extension: "png",
+            bytes: AssetBytes::Embedded(&[]),
+            decoder: |_, _| panic!("index out of bounds"),
+        };
+        let decoded = job.run();
+
+        assert_eq!(decoded.id, Id::new("broken"));
+        assert!(matches!(decoded.result, Err(AssetError::Decode { .. })));
+    }
+
+    #[test]
+    fn matches_exact() {
+        assert!(matches("music.boss", "music.boss"));
+        assert!(!matches("music.boss", "music.boss2"));
+        assert!(!matches("music.boss", "music"));
+    }
+
+    #[test]
+    fn matches_prefix() {
+        assert!(matches("level2.*", "level2.tiles"));
+        assert!(matches("level2.*", "level2.enemies.slime"));
+        assert!(!matches("level2.*", "level2"));
+        assert!(!matches("level2.*", "level3.tiles"));
+
+        // Only a trailing wildcard is special
+        assert!(matches("*", "anything"));
+        assert!(matches("level*", "level2.tiles"));
+        assert!(!matches("*.tiles", "level2.tiles"));
+    }
+}
@@ -1,15 +1,18 @@
 //! How and where assets are loaded.
 
 use std::{
-    path::{PathBuf, MAIN_SEPARATOR},
+    borrow::Cow,
+    cell::RefCell,
+    path::{Path, PathBuf, MAIN_SEPARATOR},
     str::FromStr,
 };
 
+use hashbrown::HashMap;
 use phf::Map;
 
 use crate::graphics::atlas::TextureRef;
 
-use super::{loader::Loader, Id};
+use super::{dependencies::Dependencies, loader::Loader, preload::DecodeResult, AssetError, Id};
 
 /// Empty array when embedding nothing.
 static RUNTIME_EMBEDDED_ASSETS: &[EmbeddedRawAsset] = &[];
@@ -38,6 +41,10 @@ pub struct AssetSource {
     #[cfg(not(target_arch = "wasm32"))]
     hot_reload_folder_watcher:
         Option<notify_debouncer_mini::Debouncer<notify_debouncer_mini::notify::RecommendedWatcher>>,
+    /// Files decoded in the background by ID and extension, taken by the loader when the asset is loaded.
+    preloaded: RefCell<HashMap<(Id, &'static str), DecodeResult>>,
+    /// Assets read while loading other assets, used to reload them together.
+    pub(crate) dependencies: Dependencies,
 }
 
 impl AssetSource {
@@ -50,6 +57,8 @@ impl AssetSource {
         let runtime_asset_dir = None;
         let embedded_assets = RUNTIME_EMBEDDED_ASSETS;
         let embedded_atlas = RUNTIME_EMBEDDED_ATLAS;
+        let preloaded = RefCell::default();
+        let dependencies = Dependencies::default();
 
         Self {
             runtime_asset_dir,
@@ -57,6 +66,8 @@ impl AssetSource {
             embedded_atlas,
             #[cfg(not(target_arch = "wasm32"))]
             hot_reload_folder_watcher: None,
+            preloaded,
+            dependencies,
         }
     }
 
@@ -101,19 +112,35 @@ impl AssetSource {
     }
 
     /// Load a new asset based on the loader.
+    ///
+    /// # Returns
+    ///
+    /// - `Ok(None)` when no file with the extension of the loader exists for the ID.
+    ///
+    /// # Errors
+    ///
+    /// - When the file exists but the loader failed loading it.
     #[inline]
-    #[must_use]
-    pub fn load_if_exists<L, T>(&self, id: &Id) -> Option<T>
+    pub fn load_if_exists<L, T>(&self, id: &Id) -> Result<Option<T>, AssetError>
     where
         L: Loader<T>,
+        T: 'static,
     {
+        // The asset that's currently being loaded depends on this file
+        self.dependencies.read(id);
+
+        // Use the file if it's already decoded in the background
+        if let Some(preloaded) = self.take_preloaded(id, L::EXTENSION) {
+            return preloaded.map(Some);
+        }
+
         // First try to read from memory
         // TODO: use a map for this
         if let Some(bytes) = self.embedded_assets.iter().find_map(|raw_asset| {
             (raw_asset.id == id && raw_asset.extension == L::EXTENSION).then_some(raw_asset.bytes)
         }) {
             // Create object
-            return Some(L::load(bytes, id));
+            return L::load(bytes, id).map(Some);
         }
 
         // If not found load from disk if dir set
@@ -126,12 +153,109 @@ impl AssetSource {
             ));
 
             // Read the file, return None if it failed for whatever reason
-            let bytes = std::fs::read(file_path).ok()?;
+            let Ok(bytes) = std::fs::read(file_path) else {
+                return Ok(None);
+            };
 
             // Create object
-            Some(L::load(&bytes, id))
+            L::load(&bytes, id).map(Some)
         } else {
-            None
+            Ok(None)
+        }
+    }
+
+    /// All files in the source.
+    ///
+    /// Files on disk with the same ID and extension as an embedded file are skipped.
+    pub(crate) fn files(&self) -> Vec<AssetFile> {
+        let mut files = self
+            .embedded_assets
+            .iter()
+            .map(|raw_asset| AssetFile {
+                id: Id::new(raw_asset.id),
+                extension: Cow::Borrowed(raw_asset.extension),
+                bytes: AssetBytes::Embedded(raw_asset.bytes),
+            })
+            .collect::<Vec<_>>();
+
+        // Walk over each file in each subdirectory of the runtime directory
+        if let Some(runtime_asset_dir) = &self.runtime_asset_dir {
+            let mut directories = vec![runtime_asset_dir.clone()];
+            while let Some(directory) = directories.pop() {
+                let Ok(entries) = std::fs::read_dir(&directory) else {
+                    continue;
+                };
+
+                for path in entries.filter_map(Result::ok).map(|entry| entry.path()) {
+                    if path.is_dir() {
+                        directories.push(path);
+
+                        continue;
+                    }
+
+                    let Some((id, extension)) = path_to_id(&path, runtime_asset_dir) else {
+                        continue;
+                    };
+
+                    if !files
+                        .iter()
+                        .any(|file| file.id == id && file.extension == extension)
+                    {
+                        files.push(AssetFile {
+                            id,
+                            extension: Cow::Owned(extension),
+                            bytes: AssetBytes::File(path),
+                        });
+                    }
+                }
+            }
+        }
+
+        files
+    }
+
+    /// Store a file decoded in the background so the loader can take it.
+    pub(crate) fn insert_preloaded(&self, id: Id, extension: &'static str, decoded: DecodeResult) {
+        self.preloaded.borrow_mut().insert((id, extension), decoded);
+    }
+
+    /// Whether a file is decoded in the background and not taken by a loader yet.
+    pub(crate) fn is_preloaded(&self, id: &Id, extension: &'static str) -> bool {
+        self.preloaded
+            .borrow()
+            .contains_key(&(id.clone(), extension))
+    }
+
+    /// Throw away all files decoded in the background for an ID, mainly used for hot-reloading.
+    pub(crate) fn forget_preloaded(&self, id: &Id) {
+        self.preloaded
+            .borrow_mut()
+            .retain(|(preloaded_id, _), _| preloaded_id != id);
+    }
+
+    /// Take a file decoded in the background if it's decoded into the requested type.
+    pub(crate) fn take_preloaded<T>(
+        &self,
+        id: &Id,
+        extension: &'static str,
+    ) -> Option<Result<T, AssetError>>
+    where
+        T: 'static,
+    {
+        let mut preloaded = self.preloaded.borrow_mut();
+        let key = (id.clone(), extension);
+
+        match preloaded.remove(&key)? {
+            Ok(decoded) => match decoded.downcast::<T>() {
+                Ok(decoded) => Some(Ok(*decoded)),
+                Err(decoded) => {
+                    // Decoded into another type, keep it for the loader of that type
+                    preloaded.insert(key, Ok(decoded));
+
+                    None
+                }
+            },
+            Err(err) => Some(Err(err)),
         }
     }
 
@@ -157,6 +281,56 @@ impl Default for AssetSource {
     }
 }
 
+/// Single file in the source.
+pub(crate) struct AssetFile {
+    /// Parsed ID, excludes the file extension.
+    pub(crate) id: Id,
+    /// File extension excluding the first `'.'`.
+    pub(crate) extension: Cow<'static, str>,
+    /// Where the bytes of the file can be read.
+    pub(crate) bytes: AssetBytes,
+}
+
+/// Where the bytes of a file can be read.
+#[derive(Debug, Clone)]
+pub(crate) enum AssetBytes {
+    /// Embedded in the binary.
+    Embedded(&'static [u8]),
+    /// File on disk.
+    File(PathBuf),
+}
+
+impl AssetBytes {
+    /// Read the bytes, `None` when the file on disk could not be read.
+    pub(crate) fn read(&self) -> Option<Cow<'static, [u8]>> {
+        match self {
+            Self::Embedded(bytes) => Some(Cow::Borrowed(bytes)),
+            Self::File(path) => std::fs::read(path).ok().map(Cow::Owned),
+        }
+    }
+}
+
+/// Convert a path of a file in the asset directory to an ID and the extension.
+pub(crate) fn path_to_id(path: &Path, assets_dir: &Path) -> Option<(Id, String)> {
+    // Extract the extension, ignore the file if not found
+    let extension = path.extension()?.to_string_lossy();
+
+    // Get the path relative to the asset dir
+    let relative_path = path.strip_prefix(assets_dir).ok()?;
+
+    // Create an ID from the path
+    let id = relative_path
+        .iter()
+        .map(|path| path.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(".");
+
+    // Remove the extension
+    let id = id.strip_suffix(&format!(".{extension}"))?;
+
+    Some((Id::new(id), extension.into_owned()))
+}
+
 /// Single embedded asset in the binary.
 #[allow(clippy::exhaustive_structs)]
 pub struct EmbeddedRawAsset {
@@ -0,0 +1,479 @@
+//! Mix sounds in named buses with their own volume and effects, place them in the world and limit how many play at once.
+
+use std::{cell::RefCell, rc::Rc};
+
+use hashbrown::HashMap;
+use kira::{
+    effect::{
+        filter::{FilterBuilder, FilterHandle},
+        reverb::{ReverbBuilder, ReverbHandle},
+    },
+    manager::{AudioManager, DefaultBackend},
+    track::{TrackBuilder, TrackHandle},
+    OutputDestination,
+};
+
+use crate::{
+    assets::{loadable::audio::VoiceLimit, Id},
+    context::audio::{tween, AudioFalloff, AudioHandle, AudioState, SoundHandle},
+};
+
+/// Seconds to fade the volume of a bus when it starts or stops being ducked.
+const DUCK_FADE: f32 = 0.25;
+
+/// Seconds to transition the volume and panning of positional sounds when they or the listener moved.
+const SPATIAL_FADE: f32 = 0.05;
+
+/// Seconds to fade out a sound of a group when it's stopped to make room for a new sound.
+const VOICE_STEAL_FADE: f32 = 0.02;
+
+/// Cutoff frequency of the low-pass filter when it's not applied, above what can be heard.
+const LOW_PASS_DISABLED_CUTOFF: f64 = 20_000.0;
+
+/// Settings of a single audio bus.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct AudioBusSettings {
+    /// Volume multiplication factor of all sounds in the bus.
+    pub(crate) volume: f32,
+    /// Cutoff frequency in hertz of the low-pass filter, `None` when disabled.
+    pub(crate) low_pass: Option<f32>,
+    /// How much reverb is mixed in the bus, `0.0` is none.
+    pub(crate) reverb: f32,
+    /// Buses with their volume multiplication factor while any sound plays on this bus.
+    pub(crate) ducks: Vec<(Id, f32)>,
+}
+
+impl Default for AudioBusSettings {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            low_pass: None,
+            reverb: 0.0,
+            ducks: Vec::new(),
+        }
+    }
+}
+
+/// Single named kira sub-track.
+struct AudioBus {
+    /// Kira track all sounds in the bus are played on.
+    track: TrackHandle,
+    /// Low-pass filter effect on the track.
+    low_pass: FilterHandle,
+    /// Reverb effect on the track.
+    reverb: ReverbHandle,
+    /// Settings applied to the track.
+    settings: AudioBusSettings,
+    /// Volume multiplication factor applied because the bus is ducked by another bus.
+    duck: f32,
+    /// Playing sounds, only tracked when this bus ducks other buses.
+    sounds: Vec<Rc<RefCell<SoundHandle>>>,
+}
+
+impl AudioBus {
+    /// Create a new sub-track on the manager.
+    fn new(audio_manager: &mut AudioManager<DefaultBackend>) -> Option<Self> {
+        let settings = AudioBusSettings::default();
+
+        // Add both effects to the track, they don't change the sound until they are mixed in
+        let mut builder = TrackBuilder::new();
+        let low_pass = builder.add_effect(
+            FilterBuilder::new()
+                .cutoff(LOW_PASS_DISABLED_CUTOFF)
+                .mix(0.0),
+        );
+        let reverb = builder.add_effect(ReverbBuilder::new().mix(f64::from(settings.reverb)));
+
+        // Create the track
+        let track = match audio_manager.add_sub_track(builder) {
+            Ok(track) => track,
+            Err(err) => {
+                eprintln!("Error creating audio bus: {err}");
+
+                return None;
+            }
+        };
+
+        let duck = 1.0;
+        let sounds = Vec::new();
+
+        Some(Self {
+            track,
+            low_pass,
+            reverb,
+            settings,
+            duck,
+            sounds,
+        })
+    }
+
+    /// Apply new settings to the track.
+    fn apply(&mut self, settings: AudioBusSettings, fade: f32) {
+        // Set the volume
+        if (settings.volume - self.settings.volume).abs() > f32::EPSILON {
+            self.track
+                .set_volume(f64::from(settings.volume * self.duck), tween(fade));
+        }
+
+        // Mix in the low-pass filter
+        if settings.low_pass != self.settings.low_pass {
+            let (cutoff, mix) = settings
+                .low_pass
+                .map_or((LOW_PASS_DISABLED_CUTOFF, 0.0), |cutoff| {
+                    (f64::from(cutoff), 1.0)
+                });
+            self.low_pass.set_cutoff(cutoff, tween(fade));
+            self.low_pass.set_mix(mix, tween(fade));
+        }
+
+        // Mix in the reverb
+        if (settings.reverb - self.settings.reverb).abs() > f32::EPSILON {
+            self.reverb.set_mix(f64::from(settings.reverb), tween(fade));
+        }
+
+        // Stop tracking sounds when nothing is ducked anymore
+        if settings.ducks.is_empty() {
+            self.sounds.clear();
+        }
+
+        self.settings = settings;
+    }
+
+    /// Whether any sound that can duck other buses is playing.
+    fn is_ducking(&mut self) -> bool {
+        // Forget the sounds that finished
+        self.sounds
+            .retain(|sound| sound.borrow().state() != AudioState::Stopped);
+
+        !self.settings.ducks.is_empty() && !self.sounds.is_empty()
+    }
+}
+
+/// All named audio buses.
+#[derive(Default)]
+pub(crate) struct AudioBuses {
+    /// Buses by name, created when first used.
+    buses: HashMap<Id, AudioBus>,
+}
+
+impl AudioBuses {
+    /// Settings of a bus, the default settings when the bus doesn't exist yet.
+    pub(crate) fn settings(&self, name: &str) -> AudioBusSettings {
+        self.buses
+            .get(name)
+            .map(|bus| bus.settings.clone())
+            .unwrap_or_default()
+    }
+
+    /// Apply new settings to a bus, creating it if it doesn't exist.
+    pub(crate) fn apply(
+        &mut self,
+        name: &str,
+        settings: AudioBusSettings,
+        fade: f32,
+        audio_manager: &mut AudioManager<DefaultBackend>,
+    ) {
+        if let Some(bus) = self.get_or_create(name, audio_manager) {
+            bus.apply(settings, fade);
+        }
+    }
+
+    /// Where a sound on a bus should be played, creating the bus if it doesn't exist.
+    ///
+    /// Falls back to the main track when the bus could not be created.
+    pub(crate) fn output_destination(
+        &mut self,
+        name: &str,
+        audio_manager: &mut AudioManager<DefaultBackend>,
+    ) -> OutputDestination {
+        self.get_or_create(name, audio_manager)
+            .map_or(OutputDestination::MAIN_TRACK, |bus| (&bus.track).into())
+    }
+
+    /// Register a sound that started playing on a bus, so other buses can be ducked while it plays.
+    pub(crate) fn track_sound(&mut self, name: &str, sound: &AudioHandle) {
+        if let Some(bus) = self.buses.get_mut(name) {
+            if !bus.settings.ducks.is_empty() {
+                bus.sounds.push(Rc::clone(&sound.handle));
+            }
+        }
+    }
+
+    /// Duck the volume of buses while sounds play on the buses ducking them.
+    pub(crate) fn update(&mut self) {
+        // Find the lowest duck volume for every bus
+        let mut ducks = HashMap::<Id, f32>::new();
+        for bus in self.buses.values_mut() {
+            if bus.is_ducking() {
+                for (target, volume) in &bus.settings.ducks {
+                    let duck = ducks.entry(target.clone()).or_insert(1.0);
+                    *duck = duck.min(*volume);
+                }
+            }
+        }
+
+        // Fade the volume of the buses where it changed
+        for (name, bus) in &mut self.buses {
+            let duck = ducks.get(name).copied().unwrap_or(1.0);
+            if (duck - bus.duck).abs() > f32::EPSILON {
+                bus.duck = duck;
+                bus.track
+                    .set_volume(f64::from(bus.settings.volume * duck), tween(DUCK_FADE));
+            }
+        }
+    }
+
+    /// Get a bus or create it if it doesn't exist yet.
+    fn get_or_create(
+        &mut self,
+        name: &str,
+        audio_manager: &mut AudioManager<DefaultBackend>,
+    ) -> Option<&mut AudioBus> {
+        if !self.buses.contains_key(name) {
+            let bus = AudioBus::new(audio_manager)?;
+            self.buses.insert(Id::new(name), bus);
+        }
+
+        self.buses.get_mut(name)
+    }
+}
+
+/// Position and attenuation of a sound emitted in the world.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AudioEmitter {
+    /// World position of the emitter.
+    pub(crate) position: (f32, f32),
+    /// Volume of the sound when it's at the position of the listener.
+    pub(crate) volume: f32,
+    /// How the volume decreases with distance.
+    pub(crate) falloff: AudioFalloff,
+    /// Distance in pixels at which the sound can't be heard anymore.
+    pub(crate) max_distance: f32,
+    /// Whether the emitter changed since the sound was last updated.
+    pub(crate) changed: bool,
+}
+
+impl AudioEmitter {
+    /// Calculate the volume and panning of the sound as heard by the listener.
+    pub(crate) fn volume_and_panning(&self, listener: (f32, f32)) -> (f32, f32) {
+        let delta_x = self.position.0 - listener.0;
+        let delta_y = self.position.1 - listener.1;
+
+        // Attenuate the volume by the distance to the listener
+        let distance = delta_x.hypot(delta_y) / self.max_distance.max(f32::EPSILON);
+        let volume = self.volume * self.falloff.attenuation(distance.min(1.0));
+
+        // Pan to the side the sound is coming from
+        let panning = (delta_x / self.max_distance.max(f32::EPSILON))
+            .clamp(-1.0, 1.0)
+            .mul_add(0.5, 0.5);
+
+        (volume, panning)
+    }
+}
+
+/// Sounds attached to emitters in the world and the listener hearing them.
+#[derive(Default)]
+pub(crate) struct SpatialAudio {
+    /// World position of the listener.
+    listener: (f32, f32),
+    /// Whether the listener moved since the sounds were last updated.
+    listener_moved: bool,
+    /// Playing sounds with their emitters.
+    #[allow(clippy::type_complexity)]
+    sounds: Vec<(Rc<RefCell<SoundHandle>>, Rc<RefCell<AudioEmitter>>)>,
+}
+
+impl SpatialAudio {
+    /// World position of the listener.
+    pub(crate) const fn listener(&self) -> (f32, f32) {
+        self.listener
+    }
+
+    /// Move the listener.
+    pub(crate) fn set_listener(&mut self, listener: (f32, f32)) {
+        self.listener = listener;
+        self.listener_moved = true;
+    }
+
+    /// Register a sound that started playing so it's updated when it or the listener moves.
+    pub(crate) fn track_sound(&mut self, sound: &AudioHandle) {
+        if let Some(emitter) = &sound.emitter {
+            self.sounds
+                .push((Rc::clone(&sound.handle), Rc::clone(emitter)));
+        }
+    }
+
+    /// Update the volume and panning of all sounds where the emitter or listener moved.
+    pub(crate) fn update(&mut self) {
+        // Forget the sounds that finished
+        self.sounds
+            .retain(|(sound, _)| sound.borrow().state() != AudioState::Stopped);
+
+        for (sound, emitter) in &self.sounds {
+            let mut emitter = emitter.borrow_mut();
+            if !self.listener_moved && !emitter.changed {
+                continue;
+            }
+            emitter.changed = false;
+
+            // Smoothly move to the new values to prevent clicks
+            let (volume, panning) = emitter.volume_and_panning(self.listener);
+            let mut sound = sound.borrow_mut();
+            sound.set_volume(volume, SPATIAL_FADE);
+            sound.set_panning(panning, SPATIAL_FADE);
+        }
+
+        self.listener_moved = false;
+    }
+}
+
+/// Playing sounds of sound groups, to limit how many of a group play at the same time.
+#[derive(Default)]
+pub(crate) struct SoundGroupVoices {
+    /// Playing sounds by group, the oldest first.
+    voices: HashMap<Id, Vec<Rc<RefCell<SoundHandle>>>>,
+}
+
+impl SoundGroupVoices {
+    /// Make room for a new sound of a group.
+    ///
+    /// Returns whether the new sound can be played.
+    pub(crate) fn claim(
+        &mut self,
+        group: &str,
+        max_voices: usize,
+        voice_limit: VoiceLimit,
+    ) -> bool {
+        let Some(voices) = self.voices.get_mut(group) else {
+            return voices_to_steal(0, max_voices, voice_limit).is_some();
+        };
+
+        // Forget the sounds that finished
+        voices.retain(|sound| sound.borrow().state() != AudioState::Stopped);
+
+        let Some(steal) = voices_to_steal(voices.len(), max_voices, voice_limit) else {
+            return false;
+        };
+
+        // Quickly fade out the oldest sounds to prevent clicks
+        for sound in voices.drain(..steal) {
+            sound.borrow_mut().stop(VOICE_STEAL_FADE);
+        }
+
+        true
+    }
+
+    /// Register a sound of a group that started playing.
+    pub(crate) fn track_sound(&mut self, group: &str, sound: &AudioHandle) {
+        if let Some(voices) = self.voices.get_mut(group) {
+            voices.push(Rc::clone(&sound.handle));
+        } else {
+            self.voices
+                .insert(Id::new(group), vec![Rc::clone(&sound.handle)]);
+        }
+    }
+}
+
+/// How many of the oldest playing sounds of a group must be stopped to play a new sound.
+///
+/// Returns `None` when the new sound can't be played.
+const fn voices_to_steal(
+    playing: usize,
+    max_voices: usize,
+    voice_limit: VoiceLimit,
+) -> Option<usize> {
+    if playing < max_voices {
+        return Some(0);
+    }
+
+    match voice_limit {
+        VoiceLimit::StealOldest if max_voices > 0 => Some(playing + 1 - max_voices),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Emitter with a linear falloff.
+    fn emitter(position: (f32, f32), max_distance: f32) -> AudioEmitter {
+        AudioEmitter {
+            position,
+            volume: 0.8,
+            falloff: AudioFalloff::Linear,
+            max_distance,
+            changed: false,
+        }
+    }
+
+    /// Check the volume and panning of an emitter heard from the listener.
+    fn assert_heard(emitter: &AudioEmitter, listener: (f32, f32), expected: (f32, f32)) {
+        let (volume, panning) = emitter.volume_and_panning(listener);
+
+        assert!(
+            (volume - expected.0).abs() < 1e-6 && (panning - expected.1).abs() < 1e-6,
+            "expected {expected:?}, got {:?}",
+            (volume, panning)
+        );
+    }
+
+    #[test]
+    fn volume_and_panning() {
+        // At the listener the sound is at full volume in the center
+        assert_heard(&emitter((10.0, 20.0), 100.0), (10.0, 20.0), (0.8, 0.5));
+
+        // Panned to the side the sound comes from, not heard at the max distance
+        assert_heard(&emitter((-100.0, 0.0), 100.0), (0.0, 0.0), (0.0, 0.0));
+        assert_heard(&emitter((100.0, 0.0), 100.0), (0.0, 0.0), (0.0, 1.0));
+        assert_heard(&emitter((50.0, 0.0), 100.0), (0.0, 0.0), (0.4, 0.75));
+        assert_heard(&emitter((300.0, 0.0), 100.0), (0.0, 0.0), (0.0, 1.0));
+
+        // Vertical distance only changes the volume
+        assert_heard(&emitter((0.0, -50.0), 100.0), (0.0, 0.0), (0.4, 0.5));
+
+        // Without a max distance the sound is only heard at the listener
+        assert_heard(&emitter((0.0, 0.0), 0.0), (0.0, 0.0), (0.8, 0.5));
+        assert_heard(&emitter((-1.0, 0.0), 0.0), (0.0, 0.0), (0.0, 0.0));
+        assert_heard(&emitter((1.0, 0.0), 0.0), (0.0, 0.0), (0.0, 1.0));
+    }
+
+    #[test]
+    fn voices_to_steal_limits() {
+        // Room left
+        assert_eq!(voices_to_steal(0, 2, VoiceLimit::Ignore), Some(0));
+        assert_eq!(voices_to_steal(1, 2, VoiceLimit::StealOldest), Some(0));
+
+        // Full, either stop the oldest or don't play
+        assert_eq!(voices_to_steal(2, 2, VoiceLimit::StealOldest), Some(1));
+        assert_eq!(voices_to_steal(2, 2, VoiceLimit::Ignore), None);
+
+        // Lowered limit stops enough sounds to stay within it
+        assert_eq!(voices_to_steal(5, 2, VoiceLimit::StealOldest), Some(4));
+
+        // Nothing can be played without any voices
+        assert_eq!(voices_to_steal(0, 0, VoiceLimit::StealOldest), None);
+        assert_eq!(voices_to_steal(0, 0, VoiceLimit::Ignore), None);
+    }
+
+    #[test]
+    fn claim_forgets_stopped_sounds() {
+        let mut voices = SoundGroupVoices::default();
+
+        // Groups that never played only depend on the limit
+        assert!(voices.claim("steps", 1, VoiceLimit::Ignore));
+        assert!(!voices.claim("steps", 0, VoiceLimit::StealOldest));
+
+        // Sounds that failed playing act like stopped sounds and don't take a voice
+        voices.voices.insert(
+            Id::new("steps"),
+            (0..3)
+                .map(|_| Rc::new(RefCell::new(SoundHandle::Failed)))
+                .collect(),
+        );
+        assert!(voices.claim("steps", 1, VoiceLimit::Ignore));
+        assert!(voices.voices["steps"].is_empty());
+        assert!(!voices.claim("steps", 0, VoiceLimit::Ignore));
+    }
+}
@@ -73,6 +73,13 @@ pub struct Config {
     ///
     /// Defaults to `1.0/30.0`, AKA 30 update ticks per second.
     pub update_delta_time: f32,
+    /// Names of the render layers, drawn from first to last.
+    ///
+    /// Sprites and text can be assigned to a layer with [`crate::context::sprite::SpriteContext::layer`] and [`crate::context::text::TextContext::layer`].
+    /// Everything drawn without a layer is drawn on the first layer.
+    ///
+    /// Defaults to no layers.
+    pub layers: Vec<String>,
 }
 
 impl Config {
@@ -187,6 +194,34 @@ impl Config {
 
         self
     }
+
+    /// Set the names of the render layers, drawn from first to last.
+    ///
+    /// Everything drawn without a layer is drawn on the first layer, drawing on a layer that's not in this list panics.
+    #[inline]
+    #[must_use]
+    pub fn with_layers(mut self, layers: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.layers = layers.into_iter().map(Into::into).collect();
+
+        self
+    }
+}
+
+impl Config {
+    /// Get the index of a named render layer.
+    ///
+    /// # Panics
+    ///
+    /// - When no layer with the name is defined.
+    pub(crate) fn layer_index(&self, name: &str) -> u16 {
+        let index = self
+            .layers
+            .iter()
+            .position(|layer| layer == name)
+            .unwrap_or_else(|| panic!("Render layer '{name}' is not defined in the config"));
+
+        u16::try_from(index).expect("Too many render layers")
+    }
 }
 
 impl Default for Config {
@@ -203,6 +238,7 @@ impl Default for Config {
             rotation_algorithm: RotationAlgorithm::default(),
             max_frame_time_secs: 1.0 / 4.0,
             update_delta_time: 1.0 / 30.0,
+            layers: Vec::new(),
         }
     }
 }
@@ -1,8 +1,23 @@
 //! Zero-cost abstraction types for building more complicated audio playbacks.
 
-use kira::sound::Region;
+use std::{cell::RefCell, rc::Rc, time::Duration};
 
-use crate::Context;
+#[cfg(not(target_arch = "wasm32"))]
+use kira::sound::{streaming::StreamingSoundHandle, FromFileError};
+use kira::{
+    sound::{static_sound::StaticSoundHandle, PlaybackState, Region},
+    tween::Tween,
+};
+
+use crate::{
+    assets::{loadable::audio::Audio, Id},
+    audio::AudioEmitter,
+    sfxr::Sfxr,
+    Context,
+};
+
+/// Default distance in pixels at which positional sounds can't be heard anymore.
+const DEFAULT_MAX_DISTANCE: f32 = 256.0;
 
 /// Specify how an audio clip should be played.
 ///
@@ -22,6 +37,14 @@ pub struct AudioContext<'path, 'ctx> {
     pub(crate) loop_region: Option<Region>,
     /// Which part of the song to play.
     pub(crate) playback_region: Option<Region>,
+    /// Name of the audio bus to play on, the main track when not set.
+    pub(crate) bus: Option<Id>,
+    /// World position of the emitter for positional sounds.
+    pub(crate) position: Option<(f32, f32)>,
+    /// How the volume of positional sounds decreases with distance.
+    pub(crate) falloff: AudioFalloff,
+    /// Distance in pixels at which positional sounds can't be heard anymore.
+    pub(crate) max_distance: f32,
 }
 
 impl<'path, 'ctx> AudioContext<'path, 'ctx> {
@@ -88,27 +111,146 @@ impl<'path, 'ctx> AudioContext<'path, 'ctx> {
         self
     }
 
+    /// Play the sound on a named audio bus.
+    ///
+    /// The bus is created when it's used for the first time, see [`crate::Context::audio_bus`] for controlling it.
+    ///
+    /// # Arguments
+    ///
+    /// * `bus` - Name of the bus, such as `"sfx"`, `"ui"` or `"voice"`.
+    #[inline(always)]
+    #[must_use]
+    pub fn on_bus(mut self, bus: &str) -> Self {
+        self.bus = Some(Id::new(bus));
+
+        self
+    }
+
+    /// Emit the sound from a position in the world.
+    ///
+    /// The volume and panning are calculated from the distance and direction to the listener set with [`crate::Context::set_audio_listener`].
+    /// Both are updated when the listener moves or the emitter is moved with [`AudioHandle::set_position`].
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - Absolute `(x, y)` world position in pixels.
+    #[inline(always)]
+    #[must_use]
+    pub fn at(mut self, position: impl Into<(f32, f32)>) -> Self {
+        self.position = Some(position.into());
+
+        self
+    }
+
+    /// Set how the volume of a positional sound decreases with the distance to the listener.
+    ///
+    /// Only used together with [`Self::at`].
+    ///
+    /// # Arguments
+    ///
+    /// * `falloff` - Attenuation curve, defaults to [`AudioFalloff::Linear`].
+    #[inline(always)]
+    #[must_use]
+    pub const fn falloff(mut self, falloff: AudioFalloff) -> Self {
+        self.falloff = falloff;
+
+        self
+    }
+
+    /// Set the distance at which a positional sound can't be heard anymore.
+    ///
+    /// Only used together with [`Self::at`].
+    ///
+    /// # Arguments
+    ///
+    /// * `max_distance` - Distance in pixels, defaults to `256.0`.
+    #[inline(always)]
+    #[must_use]
+    pub const fn max_distance(mut self, max_distance: f32) -> Self {
+        self.max_distance = max_distance;
+
+        self
+    }
+
+    /// Create a new audio asset from a synthesized sound effect.
+    ///
+    /// Replaces the audio asset with the same path if it exists, after which it can be played like any other audio asset.
+    ///
+    /// # Arguments
+    ///
+    /// * `sfxr` - Parameters of the sound effect to synthesize.
+    #[inline]
+    pub fn create(self, sfxr: &Sfxr) {
+        self.ctx.write(|ctx| {
+            // Synthesize the samples
+            let asset = Audio::Sound(sfxr.sound_data());
+
+            // Register the audio
+            ctx.audio.insert(Id::new(self.path), asset);
+        });
+    }
+
     /// Play the audio from start to end.
     ///
+    /// The returned handle can be ignored to play the sound until it's finished, or stored to control the playback.
+    /// When the sound could not be played on the manager an error is printed and the returned handle is always stopped.
+    /// The returned handle is also always stopped when the path is a sound group which already plays its maximum amount of voices with the `Ignore` voice limit.
+    ///
+    /// # Returns
+    ///
+    /// - A handle to control the playing sound.
+    ///
     /// # Panics
     ///
     /// - When asset failed loading.
-    /// - When the sound could not be played on the manager.
+    /// - When a sound group has no variants or one of its variants is a sound group.
     #[inline(always)]
-    pub fn play(self) {
+    #[allow(clippy::must_use_candidate)]
+    pub fn play(self) -> AudioHandle {
         self.ctx.write(|ctx| {
-            // Get the sound data and its settings
-            let sound_data = &ctx.audio(self.path).0;
+            // Get the sound data, picking a random variant with random settings for sound groups
+            let (sound_data, variation, limited) = match &*ctx.audio(self.path) {
+                Audio::Sound(sound_data) => (sound_data.clone(), None, false),
+                Audio::Group(group) => {
+                    // Limit the amount of sounds of the group playing at the same time
+                    if !ctx
+                        .sound_group_voices
+                        .claim(self.path, group.max_voices, group.voice_limit)
+                    {
+                        return AudioHandle::failed();
+                    }
+
+                    let sound_data = match &*ctx.audio(group.variant()) {
+                        Audio::Sound(sound_data) => sound_data.clone(),
+                        Audio::Group(_) => panic!(
+                            "Error playing sound group '{}': variants can't be sound groups",
+                            self.path
+                        ),
+                    };
+
+                    (
+                        sound_data,
+                        Some((group.volume(), group.pitch())),
+                        group.max_voices < usize::MAX,
+                    )
+                }
+            };
             let mut settings = sound_data.settings;
 
-            // Set the volume
-            if let Some(volume) = self.volume {
-                settings = settings.volume(volume as f64);
+            // Set the volume, randomized for sound groups
+            let volume = self.volume.unwrap_or(1.0) * variation.map_or(1.0, |(volume, _)| volume);
+            if self.volume.is_some() || variation.is_some() {
+                settings = settings.volume(f64::from(volume));
+            }
+
+            // Set the randomized pitch of sound groups
+            if let Some((_, pitch)) = variation {
+                settings = settings.playback_rate(f64::from(pitch));
             }
 
             // Set the panning
             if let Some(panning) = self.panning {
-                settings = settings.panning(panning as f64);
+                settings = settings.panning(f64::from(panning));
             }
 
             // Set the loop region
@@ -116,6 +258,32 @@ impl<'path, 'ctx> AudioContext<'path, 'ctx> {
                 settings = settings.loop_region(loop_region);
             }
 
+            // Calculate the volume and panning from the position in the world
+            let emitter = self.position.map(|position| {
+                let emitter = AudioEmitter {
+                    position,
+                    volume,
+                    falloff: self.falloff,
+                    max_distance: self.max_distance,
+                    changed: false,
+                };
+
+                let (volume, panning) = emitter.volume_and_panning(ctx.spatial_audio.listener());
+                settings = settings
+                    .volume(f64::from(volume))
+                    .panning(f64::from(panning));
+
+                Rc::new(RefCell::new(emitter))
+            });
+
+            // Route to the bus
+            if let Some(bus) = &self.bus {
+                settings = settings.output_destination(
+                    ctx.audio_buses
+                        .output_destination(bus, &mut ctx.audio_manager),
+                );
+            }
+
             // Setup the sound data with the new settings and the playback region
             let mut sound_data = sound_data.with_settings(settings);
 
@@ -124,10 +292,363 @@ impl<'path, 'ctx> AudioContext<'path, 'ctx> {
                 sound_data = sound_data.slice(playback_region);
             }
 
-            ctx.audio_manager
-                .play(sound_data)
-                .expect("Error playing audio");
-        });
+            // Play the sound, don't crash the game when the audio device can't play it
+            let mut handle = match ctx.audio_manager.play(sound_data) {
+                Ok(handle) => AudioHandle::new(SoundHandle::Static(handle)),
+                Err(err) => {
+                    eprintln!("Error playing audio '{}': {err}", self.path);
+
+                    return AudioHandle::failed();
+                }
+            };
+
+            // Let the bus duck other buses while the sound plays
+            if let Some(bus) = &self.bus {
+                ctx.audio_buses.track_sound(bus, &handle);
+            }
+
+            // Count the sound towards the limit of the group
+            if limited {
+                ctx.sound_group_voices.track_sound(self.path, &handle);
+            }
+
+            // Keep updating the positional sound while it plays
+            handle.emitter = emitter;
+            ctx.spatial_audio.track_sound(&handle);
+
+            handle
+        })
+    }
+}
+
+/// Call the same method on any kind of kira sound handle.
+///
+/// Evaluates to the default expression when the sound could not be played.
+macro_rules! with_handle {
+    ($handle:expr, $sound:ident => $call:expr, $default:expr) => {
+        match $handle {
+            SoundHandle::Static($sound) => $call,
+            #[cfg(not(target_arch = "wasm32"))]
+            SoundHandle::Streaming($sound) => $call,
+            SoundHandle::Failed => $default,
+        }
+    };
+}
+
+/// Handle to control a playing sound.
+///
+/// Returned by [`AudioContext::play`], the music started with [`crate::context::music::MusicContext::play`] is controlled from [`Context`].
+/// Dropping the handle doesn't stop the sound.
+///
+/// All methods that change the sound take a `fade` argument, which is the amount of seconds to smoothly transition to the new value.
+/// A fade of `0.0` applies the change immediately.
+///
+/// # Example
+///
+/// ```no_run
+/// use chuot::{context::audio::AudioHandle, Context, Game};
+///
+/// struct MyGame {
+///     engine: Option<AudioHandle>,
+/// }
+///
+/// impl Game for MyGame {
+///     fn update(&mut self, ctx: Context) {
+///         // Start the looping engine sound once
+///         let engine = self
+///             .engine
+///             .get_or_insert_with(|| ctx.audio("engine").with_loop().play());
+///
+///         // Pitch the engine up with the horizontal mouse position
+///         if let Some((mouse_x, _mouse_y)) = ctx.mouse() {
+///             engine.set_playback_rate(1.0 + mouse_x / ctx.width(), 0.1);
+///         }
+///
+///         // Fade out the engine in half a second when a key is pressed
+///         if ctx.key_pressed(chuot::context::KeyCode::Space) {
+///             engine.stop(0.5);
+///         }
+///     }
+///
+///     fn render(&mut self, _ctx: Context) {}
+/// }
+/// ```
+#[derive(Debug)]
+pub struct AudioHandle {
+    /// Kira handle of the playing sound, shared with the audio buses that need to know when it stops.
+    pub(crate) handle: Rc<RefCell<SoundHandle>>,
+    /// Emitter in the world for positional sounds, shared with the spatial audio that updates the sound.
+    pub(crate) emitter: Option<Rc<RefCell<AudioEmitter>>>,
+}
+
+impl AudioHandle {
+    /// Wrap a kira handle.
+    pub(crate) fn new(handle: SoundHandle) -> Self {
+        let handle = Rc::new(RefCell::new(handle));
+        let emitter = None;
+
+        Self { handle, emitter }
+    }
+
+    /// Create a handle for a sound that could not be played.
+    pub(crate) fn failed() -> Self {
+        Self::new(SoundHandle::Failed)
+    }
+
+    /// Pause the sound.
+    ///
+    /// # Arguments
+    ///
+    /// * `fade` - Seconds to fade out before the sound is paused.
+    #[inline]
+    pub fn pause(&mut self, fade: f32) {
+        with_handle!(&mut *self.handle.borrow_mut(), handle => handle.pause(tween(fade)), ());
+    }
+
+    /// Resume a paused sound.
+    ///
+    /// # Arguments
+    ///
+    /// * `fade` - Seconds to fade in after the sound is resumed.
+    #[inline]
+    pub fn resume(&mut self, fade: f32) {
+        with_handle!(&mut *self.handle.borrow_mut(), handle => handle.resume(tween(fade)), ());
+    }
+
+    /// Stop the sound.
+    ///
+    /// A stopped sound can't be resumed.
+    ///
+    /// # Arguments
+    ///
+    /// * `fade_out` - Seconds to fade out before the sound is stopped.
+    #[inline]
+    pub fn stop(&mut self, fade_out: f32) {
+        self.handle.borrow_mut().stop(fade_out);
+    }
+
+    /// Move the playback position to an absolute time.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - Seconds from the start of the sound.
+    #[inline]
+    pub fn seek_to(&mut self, position: f32) {
+        with_handle!(&mut *self.handle.borrow_mut(), handle => handle.seek_to(f64::from(position)), ());
+    }
+
+    /// Move the playback position relative to the current position.
+    ///
+    /// # Arguments
+    ///
+    /// * `amount` - Seconds to move forward, negative values move backwards.
+    #[inline]
+    pub fn seek_by(&mut self, amount: f32) {
+        with_handle!(&mut *self.handle.borrow_mut(), handle => handle.seek_by(f64::from(amount)), ());
+    }
+
+    /// Change the volume of the sound.
+    ///
+    /// For positional sounds this is the volume at the position of the listener, which is applied at the next update tick without fading.
+    ///
+    /// # Arguments
+    ///
+    /// * `volume` - Volume multiplication factor in the range `0.0..=1.0`.
+    /// * `fade` - Seconds to transition to the new volume.
+    #[inline]
+    pub fn set_volume(&mut self, volume: f32, fade: f32) {
+        if let Some(emitter) = &self.emitter {
+            // Let the spatial audio calculate the attenuated volume
+            let mut emitter = emitter.borrow_mut();
+            emitter.volume = volume;
+            emitter.changed = true;
+        } else {
+            self.handle.borrow_mut().set_volume(volume, fade);
+        }
+    }
+
+    /// Change the panning of the sound.
+    ///
+    /// Does nothing for positional sounds, their panning is based on their position.
+    ///
+    /// # Arguments
+    ///
+    /// * `panning` - Which of the stereo speakers to use, `0.0` is hard left, `1.0` is hard right and `0.5` is both equally.
+    /// * `fade` - Seconds to transition to the new panning.
+    #[inline]
+    pub fn set_panning(&mut self, panning: f32, fade: f32) {
+        if self.emitter.is_none() {
+            self.handle.borrow_mut().set_panning(panning, fade);
+        }
+    }
+
+    /// Move the emitter of a positional sound started with [`AudioContext::at`].
+    ///
+    /// The volume and panning are updated at the next update tick.
+    /// Does nothing for sounds that are not positional.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - Absolute `(x, y)` world position in pixels.
+    #[inline]
+    pub fn set_position(&mut self, position: impl Into<(f32, f32)>) {
+        if let Some(emitter) = &self.emitter {
+            let mut emitter = emitter.borrow_mut();
+            emitter.position = position.into();
+            emitter.changed = true;
+        }
+    }
+
+    /// Change the playback rate of the sound.
+    ///
+    /// This changes both the speed and the pitch.
+    ///
+    /// # Arguments
+    ///
+    /// * `playback_rate` - Speed multiplication factor, `1.0` is the original speed, `2.0` is twice as fast and an octave higher.
+    /// * `fade` - Seconds to transition to the new playback rate.
+    #[inline]
+    pub fn set_playback_rate(&mut self, playback_rate: f32, fade: f32) {
+        with_handle!(&mut *self.handle.borrow_mut(), handle => handle.set_playback_rate(f64::from(playback_rate), tween(fade)), ());
+    }
+
+    /// Current playback state of the sound.
+    ///
+    /// # Returns
+    ///
+    /// - [`AudioState::Stopped`] when the sound finished, was stopped or could not be played.
+    #[inline]
+    #[must_use]
+    pub fn state(&self) -> AudioState {
+        self.handle.borrow().state()
+    }
+
+    /// Whether the sound is currently playing.
+    ///
+    /// # Returns
+    ///
+    /// - `true` when the sound is playing or fading out to pause or stop.
+    #[inline]
+    #[must_use]
+    pub fn is_playing(&self) -> bool {
+        matches!(
+            self.state(),
+            AudioState::Playing | AudioState::Pausing | AudioState::Stopping
+        )
+    }
+
+    /// Current playback position of the sound.
+    ///
+    /// # Returns
+    ///
+    /// - Seconds from the start of the sound, `0.0` when the sound could not be played.
+    #[inline]
+    #[must_use]
+    pub fn position(&self) -> f32 {
+        with_handle!(&*self.handle.borrow(), handle => handle.position() as f32, 0.0)
+    }
+}
+
+/// How the volume of a positional sound decreases with the distance to the listener.
+///
+/// Used by [`AudioContext::falloff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum AudioFalloff {
+    /// Volume decreases evenly until the max distance.
+    #[default]
+    Linear,
+    /// Volume decreases quickly close to the emitter and slowly further away.
+    Quadratic,
+    /// Volume stays loud close to the emitter and decreases smoothly near the max distance.
+    Smooth,
+}
+
+impl AudioFalloff {
+    /// Volume multiplication factor at a distance.
+    ///
+    /// # Arguments
+    ///
+    /// * `distance` - Distance relative to the max distance in the range `0.0..=1.0`.
+    pub(crate) fn attenuation(self, distance: f32) -> f32 {
+        let distance = distance.clamp(0.0, 1.0);
+
+        match self {
+            Self::Linear => 1.0 - distance,
+            Self::Quadratic => (1.0 - distance).powi(2),
+            Self::Smooth => (distance * distance).mul_add(2.0_f32.mul_add(distance, -3.0), 1.0),
+        }
+    }
+}
+
+/// Kira handle for any kind of sound.
+#[derive(Debug)]
+pub(crate) enum SoundHandle {
+    /// Sound fully decoded in memory.
+    Static(StaticSoundHandle),
+    /// Sound decoded while playing.
+    #[cfg(not(target_arch = "wasm32"))]
+    Streaming(StreamingSoundHandle<FromFileError>),
+    /// Sound could not be played, acts like a stopped sound.
+    Failed,
+}
+
+impl SoundHandle {
+    /// Current playback state of the sound.
+    pub(crate) fn state(&self) -> AudioState {
+        with_handle!(self, handle => handle.state().into(), AudioState::Stopped)
+    }
+
+    /// Change the volume of the sound.
+    pub(crate) fn set_volume(&mut self, volume: f32, fade: f32) {
+        with_handle!(self, handle => handle.set_volume(f64::from(volume), tween(fade)), ());
+    }
+
+    /// Stop the sound.
+    pub(crate) fn stop(&mut self, fade_out: f32) {
+        with_handle!(self, handle => handle.stop(tween(fade_out)), ());
+    }
+
+    /// Change the panning of the sound.
+    pub(crate) fn set_panning(&mut self, panning: f32, fade: f32) {
+        with_handle!(self, handle => handle.set_panning(f64::from(panning), tween(fade)), ());
+    }
+}
+
+/// Playback state of a sound, returned by [`AudioHandle::state`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AudioState {
+    /// Sound is playing normally.
+    Playing,
+    /// Sound is fading out, after which it will be paused.
+    Pausing,
+    /// Sound is paused and can be resumed.
+    Paused,
+    /// Sound is fading out, after which it will be stopped.
+    Stopping,
+    /// Sound finished or was stopped and can't be resumed.
+    Stopped,
+}
+
+impl From<PlaybackState> for AudioState {
+    #[inline]
+    fn from(state: PlaybackState) -> Self {
+        match state {
+            PlaybackState::Playing => Self::Playing,
+            PlaybackState::Pausing => Self::Pausing,
+            PlaybackState::Paused => Self::Paused,
+            PlaybackState::Stopping => Self::Stopping,
+            PlaybackState::Stopped => Self::Stopped,
+        }
+    }
+}
+
+/// Convert a fade duration in seconds to a linear tween.
+pub(crate) fn tween(fade: f32) -> Tween {
+    Tween {
+        duration: Duration::from_secs_f32(fade.max(0.0)),
+        ..Default::default()
     }
 }
 
@@ -138,9 +659,29 @@ impl Context {
     /// This will load the audio asset from disk.
     /// Check the [`AudioContext`] documentation for drawing options available.
     ///
+    /// A `.ron` file can also define a sound group, every time the group is played a random variant is picked with a random volume and pitch.
+    /// The amount of sounds of a group playing at the same time can be limited, when the limit is reached either the oldest sound is stopped or the new sound is ignored.
+    /// The group must be wrapped in `SoundGroup(..)`, a `.ron` file without it is loaded as [`crate::sfxr::Sfxr`] sound effect parameters:
+    ///
+    /// ```ron
+    /// SoundGroup((
+    ///     // Asset paths of the sounds to pick from
+    ///     variants: ["footstep1", "footstep2", "footstep3"],
+    ///     // Volume and pitch ranges, all default to `1.0`
+    ///     min_volume: 0.8,
+    ///     max_volume: 1.0,
+    ///     min_pitch: 0.9,
+    ///     max_pitch: 1.1,
+    ///     // Optional limit of sounds of the group playing at the same time
+    ///     max_voices: 4,
+    ///     // `StealOldest` (default) or `Ignore`
+    ///     voice_limit: StealOldest,
+    /// ))
+    /// ```
+    ///
     /// # Arguments
     ///
-    /// * `path` - Asset path of the `.ogg` audio file, see [`Self`] for more information about asset loading and storing.
+    /// * `path` - Asset path of the `.ogg`, `.wav` or `.flac` audio file or the `.ron` sound group or sound effect parameters, tried in that order, see [`Self`] for more information about asset loading and storing.
     ///
     /// # Panics
     ///
@@ -172,6 +713,52 @@ impl Context {
             panning: None,
             loop_region: None,
             playback_region: None,
+            bus: None,
+            position: None,
+            falloff: AudioFalloff::Linear,
+            max_distance: DEFAULT_MAX_DISTANCE,
+        }
+    }
+
+    /// Set the position in the world where positional sounds are heard from.
+    ///
+    /// Usually the position of the player or the center of the camera.
+    /// Sounds started with [`AudioContext::at`] are attenuated and panned based on their distance and direction to this position.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - Absolute `(x, y)` world position in pixels, defaults to `(0.0, 0.0)`.
+    #[inline(always)]
+    pub fn set_audio_listener(&self, position: impl Into<(f32, f32)>) {
+        self.write(|ctx| ctx.spatial_audio.set_listener(position.into()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn falloff_attenuation() {
+        for (falloff, expected) in [
+            (AudioFalloff::Linear, [1.0, 0.5, 0.0]),
+            (AudioFalloff::Quadratic, [1.0, 0.25, 0.0]),
+            (AudioFalloff::Smooth, [1.0, 0.5, 0.0]),
+        ] {
+            for (distance, expected) in [0.0, 0.5, 1.0].into_iter().zip(expected) {
+                assert!(
+                    (falloff.attenuation(distance) - expected).abs() < 1e-6,
+                    "{falloff:?} at {distance}"
+                );
+            }
+
+            // Distances outside of the range are clamped
+            assert!((falloff.attenuation(-1.0) - 1.0).abs() < 1e-6);
+            assert!(falloff.attenuation(2.0).abs() < 1e-6);
         }
+
+        // Quadratic is quieter and smooth is louder than linear close to the emitter
+        assert!(AudioFalloff::Quadratic.attenuation(0.25) < AudioFalloff::Linear.attenuation(0.25));
+        assert!(AudioFalloff::Smooth.attenuation(0.25) > AudioFalloff::Linear.attenuation(0.25));
     }
 }
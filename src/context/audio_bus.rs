@@ -0,0 +1,172 @@
+//! Zero-cost abstraction types for controlling audio buses.
+
+use crate::{assets::Id, audio::AudioBusSettings, Context};
+
+/// Specify the volume and effects of a named audio bus.
+///
+/// Must call [`Self::apply`] to finish updating the bus.
+///
+/// Used by [`crate::Context::audio_bus`].
+pub struct AudioBusContext<'name, 'ctx> {
+    /// Name of the bus.
+    pub(crate) name: &'name str,
+    /// Reference to the context the bus will be applied to when finished.
+    pub(crate) ctx: &'ctx Context,
+    /// Bus settings, starts with the current settings of the bus.
+    pub(crate) settings: AudioBusSettings,
+    /// Seconds to transition to the new settings.
+    pub(crate) fade: f32,
+}
+
+impl<'name, 'ctx> AudioBusContext<'name, 'ctx> {
+    /// Set the volume of all sounds on the bus.
+    ///
+    /// # Arguments
+    ///
+    /// * `volume` - Volume multiplication factor in the range `0.0..=1.0`.
+    #[inline(always)]
+    #[must_use]
+    pub const fn volume(mut self, volume: f32) -> Self {
+        self.settings.volume = volume;
+
+        self
+    }
+
+    /// Muffle all sounds on the bus with a low-pass filter, such as when underwater or in a pause menu.
+    ///
+    /// # Arguments
+    ///
+    /// * `cutoff` - Frequency in hertz above which the sound is removed, lower sounds more muffled.
+    #[inline(always)]
+    #[must_use]
+    pub const fn low_pass(mut self, cutoff: f32) -> Self {
+        self.settings.low_pass = Some(cutoff);
+
+        self
+    }
+
+    /// Remove the low-pass filter.
+    #[inline(always)]
+    #[must_use]
+    pub const fn without_low_pass(mut self) -> Self {
+        self.settings.low_pass = None;
+
+        self
+    }
+
+    /// Mix reverb into all sounds on the bus.
+    ///
+    /// # Arguments
+    ///
+    /// * `mix` - Amount of reverb in the range `0.0..=1.0`, `0.0` disables it.
+    #[inline(always)]
+    #[must_use]
+    pub const fn reverb(mut self, mix: f32) -> Self {
+        self.settings.reverb = mix;
+
+        self
+    }
+
+    /// Lower the volume of another bus while any sound plays on this bus.
+    ///
+    /// Useful for making voices understandable over the music.
+    /// Only sounds started after this is applied are taken into account.
+    ///
+    /// # Arguments
+    ///
+    /// * `bus` - Name of the bus to duck.
+    /// * `volume` - Volume multiplication factor of the ducked bus in the range `0.0..=1.0`.
+    #[inline(always)]
+    #[must_use]
+    pub fn duck(mut self, bus: &str, volume: f32) -> Self {
+        // Replace the previous volume if the bus was already ducked
+        self.settings.ducks.retain(|(ducked, _)| ducked != bus);
+        self.settings.ducks.push((Id::new(bus), volume));
+
+        self
+    }
+
+    /// Stop lowering the volume of other buses.
+    #[inline(always)]
+    #[must_use]
+    pub fn without_ducking(mut self) -> Self {
+        self.settings.ducks.clear();
+
+        self
+    }
+
+    /// Smoothly transition the volume and effects to the new values.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - Duration of the transition, `0.0` (default) applies the new values immediately.
+    #[inline(always)]
+    #[must_use]
+    pub const fn fade(mut self, seconds: f32) -> Self {
+        self.fade = seconds;
+
+        self
+    }
+
+    /// Apply the settings to the bus.
+    #[inline(always)]
+    pub fn apply(self) {
+        self.ctx.write(|ctx| {
+            ctx.audio_buses
+                .apply(self.name, self.settings, self.fade, &mut ctx.audio_manager);
+        });
+    }
+}
+
+/// Audio bus methods.
+impl Context {
+    /// Control a named audio bus.
+    ///
+    /// Sounds can be played on a bus with [`crate::context::audio::AudioContext::on_bus`], music tracks are played on the `"music"` bus.
+    /// Every bus has its own volume and effects, which makes them ideal for separate volume sliders in an options menu.
+    /// A bus is created when it's used for the first time, any name can be used.
+    /// Check the [`AudioBusContext`] documentation for the options available.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the bus, such as `"music"`, `"sfx"`, `"ui"` or `"voice"`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use chuot::Context;
+    ///
+    /// # struct Empty; impl Empty {
+    /// // In `Game::update` trait implementation
+    /// // ..
+    /// fn update(&mut self, ctx: Context) {
+    /// # let paused = false;
+    ///   // Lower the music while a voice line is playing
+    ///   ctx.audio_bus("voice").duck("music", 0.3).apply();
+    ///
+    ///   if paused {
+    ///     // Muffle the music in the pause menu in half a second
+    ///     ctx.audio_bus("music").low_pass(800.0).fade(0.5).apply();
+    ///   } else {
+    ///     ctx.audio_bus("music").without_low_pass().fade(0.5).apply();
+    ///   }
+    ///
+    ///   // Play a voice line
+    ///   ctx.audio("hello").on_bus("voice").play();
+    /// }
+    /// # }
+    #[inline(always)]
+    #[must_use]
+    pub fn audio_bus<'name>(&self, name: &'name str) -> AudioBusContext<'name, '_> {
+        // Start with the current settings
+        let settings = self.read(|ctx| ctx.audio_buses.settings(name));
+        let fade = 0.0;
+
+        AudioBusContext {
+            name,
+            ctx: self,
+            settings,
+            fade,
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! Zero-cost abstraction types for building more complicated camera constructions.
+
+use crate::{
+    graphics::{camera::Camera, viewport::Viewport},
+    Context,
+};
+
+/// Specify how the camera should view the world.
+///
+/// Must call [`Self::apply`] to finish updating the camera.
+///
+/// Used by [`crate::Context::camera`].
+pub struct CameraContext<'ctx> {
+    /// Reference to the context the camera will be applied to when finished.
+    pub(crate) ctx: &'ctx Context,
+    /// Index of the viewport the camera belongs to.
+    pub(crate) viewport: usize,
+    /// Camera state, starts with the state of the current camera.
+    pub(crate) camera: Camera,
+}
+
+impl<'ctx> CameraContext<'ctx> {
+    /// Set the world position shown at the left top of the viewport.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - Absolute `(x, y)` world position in pixels.
+    #[inline(always)]
+    #[must_use]
+    pub fn position(mut self, position: impl Into<(f32, f32)>) -> Self {
+        self.camera.position = position.into();
+
+        self
+    }
+
+    /// Set the world position shown at the center of the viewport.
+    ///
+    /// Useful for following a player.
+    ///
+    /// # Arguments
+    ///
+    /// * `center` - Absolute `(x, y)` world position in pixels.
+    #[inline(always)]
+    #[must_use]
+    pub fn center(mut self, center: impl Into<(f32, f32)>) -> Self {
+        let (x, y) = center.into();
+        let (width, height) = self
+            .ctx
+            .read(|ctx| ctx.graphics.viewports[self.viewport].size());
+
+        self.camera.position = (x - width / 2.0, y - height / 2.0);
+
+        self
+    }
+
+    /// Move the camera relative to its current position.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Relative `(x, y)` offset in world pixels.
+    #[inline(always)]
+    #[must_use]
+    pub fn translate(mut self, offset: impl Into<(f32, f32)>) -> Self {
+        let (x, y) = offset.into();
+
+        self.camera.position.0 += x;
+        self.camera.position.1 += y;
+
+        self
+    }
+
+    /// Set the zoom factor.
+    ///
+    /// Zooming happens around the center of the viewport.
+    /// Only integer steps are allowed so every pixel of the world stays the same size.
+    ///
+    /// # Arguments
+    ///
+    /// * `zoom` - How many buffer pixels a single world pixel takes, `0` is treated as `1`.
+    #[inline(always)]
+    #[must_use]
+    pub const fn zoom(mut self, zoom: u32) -> Self {
+        self.camera.zoom = if zoom == 0 { 1 } else { zoom };
+
+        self
+    }
+
+    /// Set the rotation of the camera.
+    ///
+    /// Rotating happens around the center of the viewport.
+    /// The world will be drawn with the rotation algorithm configured in [`crate::Config::rotation_algorithm`].
+    ///
+    /// # Arguments
+    ///
+    /// * `rotation` - Absolute rotation in radians.
+    #[inline(always)]
+    #[must_use]
+    pub const fn rotation(mut self, rotation: f32) -> Self {
+        self.camera.rotation = rotation;
+
+        self
+    }
+
+    /// Set the offset used for shaking the camera.
+    ///
+    /// The offset is applied after clamping to the bounds so shaking is also visible at the edges.
+    /// It stays until it's set again, so it must be reset to `(0.0, 0.0)` when the shaking is done.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Relative `(x, y)` offset in world pixels, usually taken from [`crate::random`].
+    #[inline(always)]
+    #[must_use]
+    pub fn shake(mut self, offset: impl Into<(f32, f32)>) -> Self {
+        self.camera.shake = offset.into();
+
+        self
+    }
+
+    /// Keep the view inside a rectangle of the world.
+    ///
+    /// When the rectangle is smaller than the view the view will be centered on it.
+    ///
+    /// # Arguments
+    ///
+    /// * `bounds` - World rectangle `(x, y, width, height)` in pixels.
+    #[inline(always)]
+    #[must_use]
+    pub fn bounds(mut self, bounds: impl Into<(f32, f32, f32, f32)>) -> Self {
+        self.camera.bounds = Some(bounds.into());
+
+        self
+    }
+
+    /// Allow the view to move anywhere in the world.
+    #[inline(always)]
+    #[must_use]
+    pub const fn without_bounds(mut self) -> Self {
+        self.camera.bounds = None;
+
+        self
+    }
+
+    /// Reset the camera so world coordinates are viewport coordinates again.
+    #[inline(always)]
+    #[must_use]
+    pub const fn reset(mut self) -> Self {
+        self.camera = Camera::new();
+
+        self
+    }
+
+    /// Use the camera when rendering the current and all following frames.
+    #[inline]
+    pub fn apply(self) {
+        self.ctx
+            .write(|ctx| ctx.graphics.viewports[self.viewport].camera = self.camera);
+    }
+}
+
+/// Camera methods.
+impl Context {
+    /// Update the camera viewing the world.
+    ///
+    /// Everything drawn is transformed by the camera, except sprites and text drawn with [`crate::context::sprite::SpriteContext::in_screen_space`] or [`crate::context::text::TextContext::in_screen_space`].
+    /// The camera is applied when rendering, so it's also applied to everything drawn before updating the camera in the same frame.
+    /// Check the [`CameraContext`] documentation for the options available.
+    ///
+    /// When using multiple viewports this is the camera of the first viewport, see [`Self::viewport_camera`].
+    ///
+    /// # Returns
+    ///
+    /// - A helper struct starting with the current camera state, allowing you to change it.
+    #[inline(always)]
+    #[must_use]
+    pub fn camera(&self) -> CameraContext<'_> {
+        self.viewport_camera(0)
+    }
+
+    /// Update the camera of a single viewport when rendering split-screen.
+    ///
+    /// See [`Self::camera`] for more information about cameras and [`Self::set_viewports`] for defining the viewports.
+    ///
+    /// # Arguments
+    ///
+    /// * `viewport` - Index of the viewport, in the order they were passed to [`Self::set_viewports`].
+    ///
+    /// # Returns
+    ///
+    /// - A helper struct starting with the current camera state of the viewport, allowing you to change it.
+    ///
+    /// # Panics
+    ///
+    /// - When the viewport doesn't exist.
+    #[inline(always)]
+    #[must_use]
+    pub fn viewport_camera(&self, viewport: usize) -> CameraContext<'_> {
+        CameraContext {
+            ctx: self,
+            viewport,
+            camera: self.read(|ctx| {
+                ctx.graphics
+                    .viewports
+                    .get(viewport)
+                    .unwrap_or_else(|| panic!("Viewport {viewport} doesn't exist"))
+                    .camera
+            }),
+        }
+    }
+
+    /// Split the buffer into multiple viewports, each showing the world with its own camera.
+    ///
+    /// All sprites and text are drawn once for every viewport, clipped to the rectangle of the viewport.
+    /// Sprites and text drawn in screen space are positioned relative to the whole buffer and drawn only once, on top of all viewports.
+    ///
+    /// Cameras of viewports that already existed are kept, new viewports start without any camera transformation.
+    /// By default there's a single viewport covering the whole buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `rectangles` - Rectangles `(x, y, width, height)` of each viewport inside the buffer in pixels, drawn in order.
+    ///
+    /// # Panics
+    ///
+    /// - When no rectangles are passed.
+    #[inline]
+    pub fn set_viewports(
+        &self,
+        rectangles: impl IntoIterator<Item = impl Into<(f32, f32, f32, f32)>>,
+    ) {
+        self.write(|ctx| {
+            let graphics = &mut ctx.graphics;
+            let buffer_size = (graphics.buffer_width, graphics.buffer_height);
+
+            let mut count = 0;
+            for (index, rectangle) in rectangles.into_iter().enumerate() {
+                let rectangle = rectangle.into();
+
+                // Reuse the existing viewports so their cameras are kept
+                if let Some(viewport) = graphics.viewports.get_mut(index) {
+                    viewport.rectangle = rectangle;
+                } else {
+                    let viewport = Viewport::new(
+                        rectangle,
+                        buffer_size,
+                        &graphics.screen_info,
+                        &graphics.device,
+                    );
+                    graphics.viewports.push(viewport);
+                }
+
+                count += 1;
+            }
+
+            assert!(count > 0, "At least a single viewport is required");
+
+            // Remove the viewports that are not used anymore
+            graphics.viewports.truncate(count);
+        });
+    }
+}
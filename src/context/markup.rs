@@ -0,0 +1,270 @@
+//! Parse rich text markup into characters with inline styling.
+//!
+//! Supported tags:
+//!
+//! | Tag | Effect |
+//! | --- | --- |
+//! | `[color=#F80]..[/color]` | Tint the characters, accepts `#RGB`, `#RRGGBB` and `#AARRGGBB` |
+//! | `[wave]..[/wave]` | Move the characters up and down in a wave |
+//! | `[shake]..[/shake]` | Shake the characters randomly |
+//! | `[icon=path]` | Draw a sprite inline |
+//!
+//! `[[` draws a literal `[`, tags that can't be parsed are drawn as text.
+
+use std::f32::consts::TAU;
+
+/// How many pixels wavy characters move up and down.
+const WAVE_AMPLITUDE: f32 = 2.0;
+/// How many times per second wavy characters move up and down.
+const WAVE_FREQUENCY: f32 = 1.5;
+/// Offset in the wave cycle between two characters.
+const WAVE_PHASE_PER_CHAR: f32 = 0.1;
+/// Maximum pixels shaking characters move from their position.
+const SHAKE_AMPLITUDE: f32 = 1.0;
+/// How many times per second shaking characters move to a new position.
+const SHAKE_FREQUENCY: f32 = 20.0;
+
+/// Single parsed item of rich text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MarkupItem<'text> {
+    /// Character drawn with the font.
+    Char(char),
+    /// Asset path of a sprite drawn inline.
+    Icon(&'text str),
+}
+
+/// Style of a span of rich text.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SpanStyle {
+    /// Color in `0xAARRGGBB` format to tint with.
+    pub(crate) color: Option<u32>,
+    /// Whether the characters move in a wave.
+    pub(crate) wave: bool,
+    /// Whether the characters shake.
+    pub(crate) shake: bool,
+}
+
+impl SpanStyle {
+    /// Offset of an animated character.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Index of the character in the text, so not all characters move the same.
+    /// * `time` - Time in seconds driving the animation.
+    pub(crate) fn offset(self, index: usize, time: f32) -> (f32, f32) {
+        let mut offset = (0.0, 0.0);
+
+        if self.wave {
+            let phase = (index as f32).mul_add(WAVE_PHASE_PER_CHAR, time * WAVE_FREQUENCY);
+            offset.1 += ((phase * TAU).sin() * WAVE_AMPLITUDE).round();
+        }
+
+        if self.shake {
+            // Pick a new pseudo-random position a fixed amount of times per second
+            let step = (time * SHAKE_FREQUENCY).floor() as i64 as u64;
+            let hash = hash(index as u64, step);
+
+            // Use the lower and upper bits for both axes
+            let random_x = (hash & 0xFFFF) as f32 / f32::from(u16::MAX);
+            let random_y = (hash >> 16 & 0xFFFF) as f32 / f32::from(u16::MAX);
+            offset.0 += (random_x.mul_add(2.0, -1.0) * SHAKE_AMPLITUDE).round();
+            offset.1 += (random_y.mul_add(2.0, -1.0) * SHAKE_AMPLITUDE).round();
+        }
+
+        offset
+    }
+}
+
+/// Parse the markup into items with their style.
+pub(crate) fn parse(text: &str) -> Vec<(MarkupItem<'_>, SpanStyle)> {
+    let mut items = Vec::with_capacity(text.len());
+
+    // Keep a stack of colors so nested colors are restored when closed
+    let mut colors = Vec::new();
+    let mut waves = 0_usize;
+    let mut shakes = 0_usize;
+
+    let mut rest = text;
+    while let Some(ch) = rest.chars().next() {
+        let style = SpanStyle {
+            color: colors.last().copied(),
+            wave: waves > 0,
+            shake: shakes > 0,
+        };
+
+        // Escaped bracket
+        if let Some(after) = rest.strip_prefix("[[") {
+            items.push((MarkupItem::Char('['), style));
+            rest = after;
+
+            continue;
+        }
+
+        // Try to parse a tag
+        if let Some((tag, after)) = rest.strip_prefix('[').and_then(|tag| tag.split_once(']')) {
+            let handled = match tag.split_once('=') {
+                Some(("color", color)) => parse_color(color).map(|color| colors.push(color)),
+                Some(("icon", path)) => {
+                    items.push((MarkupItem::Icon(path), style));
+
+                    Some(())
+                }
+                None => match tag {
+                    "/color" => colors.pop().map(|_| ()),
+                    "wave" => {
+                        waves += 1;
+
+                        Some(())
+                    }
+                    "/wave" => waves.checked_sub(1).map(|remaining| waves = remaining),
+                    "shake" => {
+                        shakes += 1;
+
+                        Some(())
+                    }
+                    "/shake" => shakes.checked_sub(1).map(|remaining| shakes = remaining),
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            if handled.is_some() {
+                rest = after;
+
+                continue;
+            }
+        }
+
+        // Regular character
+        items.push((MarkupItem::Char(ch), style));
+        let mut chars = rest.chars();
+        chars.next();
+        rest = chars.as_str();
+    }
+
+    items
+}
+
+/// Parse a hexadecimal color into the `0xAARRGGBB` format.
+fn parse_color(color: &str) -> Option<u32> {
+    // Only allow digits, the radix conversion also accepts a sign
+    let hex = color
+        .strip_prefix('#')
+        .filter(|hex| hex.chars().all(|ch| ch.is_ascii_hexdigit()))?;
+    let value = u32::from_str_radix(hex, 16).ok()?;
+
+    match hex.len() {
+        // Expand each component to two digits
+        3 => {
+            let expand = |shift: u32| (value >> shift & 0xF) * 0x11;
+
+            Some(0xFF_00_00_00 | expand(8) << 16 | expand(4) << 8 | expand(0))
+        }
+        6 => Some(0xFF_00_00_00 | value),
+        8 => Some(value),
+        _ => None,
+    }
+}
+
+/// Mix two numbers into a pseudo-random number.
+const fn hash(a: u64, b: u64) -> u64 {
+    // SplitMix64 finalizer
+    let mut hash = a
+        .wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        .wrapping_add(b.wrapping_mul(0xBF58_476D_1CE4_E5B9));
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+
+    hash ^ (hash >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Text of the parsed characters where the style matches.
+    fn chars_with(text: &str, filter: impl Fn(SpanStyle) -> bool) -> String {
+        parse(text)
+            .into_iter()
+            .filter_map(|(item, style)| match item {
+                MarkupItem::Char(ch) if filter(style) => Some(ch),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nested_tags() {
+        let text = "a[color=#F00]b[wave]c[color=#00FF00]d[/color]e[/wave]f[/color]g";
+        assert_eq!(chars_with(text, |_| true), "abcdefg");
+
+        // The outer color is restored after the inner color is closed
+        assert_eq!(
+            chars_with(text, |style| style.color == Some(0xFF_FF_00_00)),
+            "bcef"
+        );
+        assert_eq!(
+            chars_with(text, |style| style.color == Some(0xFF_00_FF_00)),
+            "d"
+        );
+        assert_eq!(chars_with(text, |style| style.color.is_none()), "ag");
+        assert_eq!(chars_with(text, |style| style.wave), "cde");
+        assert_eq!(chars_with(text, |style| style.shake), "");
+    }
+
+    #[test]
+    fn unclosed_tags() {
+        // Open tags last until the end of the text
+        assert_eq!(chars_with("a[shake]bc", |style| style.shake), "bc");
+
+        // Closing tags without an open tag are drawn as text
+        assert_eq!(
+            chars_with("a[/wave]b[/color]", |_| true),
+            "a[/wave]b[/color]"
+        );
+
+        // Brackets without an end are drawn as text
+        assert_eq!(chars_with("a[wave", |_| true), "a[wave");
+    }
+
+    #[test]
+    fn icon() {
+        let items = parse("a[color=#FFF][icon=ui.coin]b");
+        let white = SpanStyle {
+            color: Some(0xFF_FF_FF_FF),
+            ..SpanStyle::default()
+        };
+
+        assert_eq!(
+            items,
+            [
+                (MarkupItem::Char('a'), SpanStyle::default()),
+                (MarkupItem::Icon("ui.coin"), white),
+                (MarkupItem::Char('b'), white),
+            ]
+        );
+    }
+
+    #[test]
+    fn escaped_brackets() {
+        // Escaped brackets are never parsed as tags
+        assert_eq!(chars_with("[[wave]a]", |style| !style.wave), "[wave]a]");
+        assert_eq!(chars_with("[[[wave]a", |style| !style.wave), "[");
+        assert_eq!(chars_with("[[[wave]a", |style| style.wave), "a");
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(parse_color("#F80"), Some(0xFF_FF_88_00));
+        assert_eq!(parse_color("#12AB34"), Some(0xFF_12_AB_34));
+        assert_eq!(parse_color("#8012AB34"), Some(0x80_12_AB_34));
+
+        // Invalid colors are drawn as text
+        for color in ["F80", "#GG0", "#1234", "#+F8", "#", "red"] {
+            assert_eq!(parse_color(color), None);
+
+            let text = format!("[color={color}]a");
+            assert_eq!(chars_with(&text, |style| style.color.is_none()), text);
+        }
+    }
+}
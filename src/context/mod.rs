@@ -1,6 +1,11 @@
 //! Main interface with the game.
 
 pub mod audio;
+pub mod audio_bus;
+pub mod camera;
+mod markup;
+pub mod music;
+pub mod preload;
 pub mod sprite;
 pub mod text;
 
@@ -12,18 +17,23 @@ use smallvec::SmallVec;
 /// Re-exported `winit` types for [`Context`] arguments.
 pub use winit::{event::MouseButton, keyboard::KeyCode};
 
+pub use crate::graphics::instance::BlendMode;
+
 use std::{cell::RefCell, rc::Rc, sync::Arc};
 
 use winit::window::{Fullscreen, Window};
 
 use crate::{
     assets::{
-        loadable::{audio::Audio, font::Font, sprite::Sprite, Loadable},
+        loadable::{audio::Audio, font::Font, music::Music, sprite::Sprite, Loadable},
+        preload::Preloader,
         source::AssetSource,
-        AssetManager, CustomAssetManager, Id,
+        AssetError, AssetManager, CustomAssetManager, Id,
     },
+    audio::{AudioBuses, SoundGroupVoices, SpatialAudio},
     config::Config,
-    graphics::Graphics,
+    context::audio::AudioHandle,
+    graphics::{atlas::TextureRef, Graphics},
     input::Input,
 };
 
@@ -39,7 +49,9 @@ use crate::{
 /// | --- | --- |
 /// | `ctx.sprite("player")` | `assets/player.png` & `assets/player.toml` (optional) |
 /// | `ctx.sprite("gui.widgets.button")` | `assets/gui/widgets/button.png` & `assets/gui/widgets/button.toml` (optional) |
-/// | `ctx.audio("song")` | `assets/song.ogg` |
+/// | `ctx.audio("song")` | `assets/song.ogg`, `assets/song.wav` or `assets/song.flac` |
+/// | `ctx.audio("footsteps")` | `assets/footsteps.ron` sound group with the variants `assets/footstep1.ogg`, .. |
+/// | `ctx.music("track")` | `assets/track.ogg` & `assets/track.ron` (optional) |
 ///
 /// It's assumed for this table that [`crate::load_assets`] in [`crate::Game`] is called without any arguments or with `chuot::load_assets!("assets/")`.
 #[derive(Clone)]
@@ -227,6 +239,47 @@ impl Context {
         self.read(|ctx| ctx.input.mouse())
     }
 
+    /// Get the position of the mouse in the world if the mouse is inside the viewport frame.
+    ///
+    /// Same as [`Self::mouse`] but with the transformation of the camera inverted, see [`Self::camera`].
+    /// When the buffer is split into multiple viewports the camera of the viewport below the mouse is used, see [`Self::mouse_viewport`].
+    ///
+    /// # Returns
+    ///
+    /// - `None` when the mouse is not on the buffer of pixels or not inside any viewport.
+    /// - `Some(..)` with the world coordinates below the mouse if the mouse is on the buffer of pixels.
+    #[inline]
+    #[must_use]
+    pub fn mouse_world(&self) -> Option<(f32, f32)> {
+        self.read(|ctx| {
+            let (x, y) = ctx.input.mouse()?;
+
+            ctx.graphics
+                .map_buffer_coordinate(x, y)
+                .map(|(_viewport, world)| world)
+        })
+    }
+
+    /// Get the viewport the mouse is inside of.
+    ///
+    /// See [`Self::set_viewports`] for defining multiple viewports.
+    ///
+    /// # Returns
+    ///
+    /// - `None` when the mouse is not on the buffer of pixels or not inside any viewport.
+    /// - `Some(..)` with the index of the viewport, when viewports overlap it's the one drawn last.
+    #[inline]
+    #[must_use]
+    pub fn mouse_viewport(&self) -> Option<usize> {
+        self.read(|ctx| {
+            let (x, y) = ctx.input.mouse()?;
+
+            ctx.graphics
+                .map_buffer_coordinate(x, y)
+                .map(|(viewport, _world)| viewport)
+        })
+    }
+
     /// Whether the mouse button goes from "not pressed" to "pressed".
     ///
     /// # Arguments
@@ -469,6 +522,54 @@ impl Context {
         self.write(|ctx| ctx.custom(path.as_ref()))
     }
 
+    /// Load a read-only reference to a custom defined asset, returning an error when it could not be loaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Asset path of the custom asset, see [`Self`] for more information about asset loading and storing.
+    ///
+    /// # Errors
+    ///
+    /// - When asset with path does not exist.
+    /// - When asset could not be loaded due to an invalid format.
+    ///
+    /// # Panics
+    ///
+    /// - When asset was already loaded with a different type.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use chuot::{assets::AssetError, Context};
+    /// # use chuot::{assets::{loadable::Loadable, loader::ron::RonLoader, Id}, context::ContextInner};
+    /// # use nanoserde::DeRon;
+    /// # #[derive(DeRon)] struct Settings { volume: f32 }
+    /// # impl Loadable for Settings {
+    /// #     fn load_if_exists(id: &Id, ctx: &mut ContextInner) -> Result<Option<Self>, AssetError> {
+    /// #         ctx.asset_source.load_if_exists::<RonLoader, Self>(id)
+    /// #     }
+    /// # }
+    ///
+    /// # struct Empty; impl Empty {
+    /// // In `Game::update` trait implementation
+    /// // ..
+    /// fn update(&mut self, ctx: Context) {
+    ///   match ctx.try_asset::<Settings>("settings") {
+    ///     Ok(settings) => println!("Volume: {}", settings.volume),
+    ///     // Use the defaults when there's no settings file yet
+    ///     Err(AssetError::NotFound { .. }) => (),
+    ///     Err(err) => eprintln!("{err}"),
+    ///   }
+    /// }
+    /// # }
+    #[inline]
+    pub fn try_asset<T>(&self, path: impl AsRef<str>) -> Result<Rc<T>, AssetError>
+    where
+        T: Loadable,
+    {
+        self.write(|ctx| ctx.try_custom(path.as_ref()))
+    }
+
     /// Load a clone of a custom defined asset.
     ///
     /// # Arguments
@@ -486,6 +587,35 @@ impl Context {
     {
         self.write(|ctx| ctx.custom_owned(path.as_ref()))
     }
+
+    /// Asset paths of all assets that are hot-reloaded since the previous update.
+    ///
+    /// Assets are also reloaded when any of the assets they read while loading is reloaded, those are included as well.
+    /// Assets that failed reloading keep their previous version and are not included.
+    ///
+    /// # Returns
+    ///
+    /// - Iterator over the asset paths, empty when nothing is reloaded or hot-reloading is disabled.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use chuot::Context;
+    ///
+    /// # struct Empty; impl Empty {
+    /// // In `Game::update` trait implementation
+    /// // ..
+    /// fn update(&mut self, ctx: Context) {
+    ///   for path in ctx.assets_reloaded() {
+    ///     println!("Reloaded '{path}'");
+    ///   }
+    /// }
+    /// # }
+    /// ```
+    #[inline]
+    pub fn assets_reloaded(&self) -> impl Iterator<Item = Id> {
+        self.read(|ctx| ctx.reloaded_assets.clone()).into_iter()
+    }
 }
 
 /// Internally used methods.
@@ -547,6 +677,12 @@ pub struct ContextInner {
     pub(crate) input: Input,
     /// Audio manager for playing audio.
     pub(crate) audio_manager: AudioManager<DefaultBackend>,
+    /// Named sub-tracks of the audio manager.
+    pub(crate) audio_buses: AudioBuses,
+    /// Positional sounds and the listener.
+    pub(crate) spatial_audio: SpatialAudio,
+    /// Playing sounds of sound groups.
+    pub(crate) sound_group_voices: SoundGroupVoices,
     /// User supplied game configuration.
     pub(crate) config: Config,
     /// Sprite assets.
@@ -555,8 +691,16 @@ pub struct ContextInner {
     pub(crate) fonts: AssetManager<Font>,
     /// Audio assets.
     pub(crate) audio: AssetManager<Audio>,
+    /// Streamed music assets.
+    pub(crate) music: AssetManager<Music>,
+    /// Music track currently playing with its ID.
+    pub(crate) playing_music: Option<(Id, AudioHandle)>,
     /// Custom type erased assets.
     pub(crate) custom: CustomAssetManager,
+    /// Decodes assets in the background.
+    pub(crate) preloader: Preloader,
+    /// IDs of the assets that are hot-reloaded since the previous update.
+    pub(crate) reloaded_assets: Vec<Id>,
     /// Whether to exit.
     pub(crate) exit: bool,
 }
@@ -576,17 +720,26 @@ impl ContextInner {
 
         // Setup the audio manager to play audio
         let audio_manager = AudioManager::new(AudioManagerSettings::default()).unwrap();
+        let audio_buses = AudioBuses::default();
+        let spatial_audio = SpatialAudio::default();
+        let sound_group_voices = SoundGroupVoices::default();
 
         // Setup the assets managers
         let sprites = AssetManager::default();
         let fonts = AssetManager::default();
         let audio = AssetManager::default();
+        let music = AssetManager::default();
         let custom = CustomAssetManager::default();
+        let preloader = Preloader::default();
+        let reloaded_assets = Vec::new();
 
         // Define default values for the timing functions
         let frames_per_second = 0.0;
         let blending_factor = 0.0;
 
+        // No music is playing yet
+        let playing_music = None;
+
         // Default input values and state
         let input = Input::new();
         let exit = false;
@@ -599,11 +752,18 @@ impl ContextInner {
             blending_factor,
             input,
             audio_manager,
+            audio_buses,
+            spatial_audio,
+            sound_group_voices,
             config,
             sprites,
             fonts,
             audio,
+            music,
+            playing_music,
             custom,
+            preloader,
+            reloaded_assets,
             exit,
         }
     }
@@ -612,20 +772,33 @@ impl ContextInner {
     ///
     /// # Panics
     ///
-    /// - When font sprite could not be loaded.
+    /// - When sprite could not be loaded.
     #[inline]
     pub(crate) fn sprite(&mut self, id: &str) -> Rc<Sprite> {
+        self.try_sprite(id).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Get or load a sprite, returning an error when it could not be loaded.
+    ///
+    /// # Errors
+    ///
+    /// - When sprite could not be loaded.
+    #[inline]
+    pub(crate) fn try_sprite(&mut self, id: &str) -> Result<Rc<Sprite>, AssetError> {
         // Create the ID
         let id = Id::new(id);
 
+        // The asset that's currently being loaded depends on this asset
+        self.asset_source.dependencies.read(&id);
+
         // Try to load the asset first
         if let Some(asset) = self.sprites.get(&id) {
-            return asset;
+            return Ok(asset);
         }
 
         // Asset not found, load it
-        let asset = Sprite::load(&id, self);
-        self.sprites.insert(id, asset)
+        let asset = self.load::<Sprite>(&id)?;
+        Ok(self.sprites.insert(id, asset))
     }
 
     /// Get or load a font.
@@ -638,13 +811,16 @@ impl ContextInner {
         // Create the ID
         let id = Id::new(id);
 
+        // The asset that's currently being loaded depends on this asset
+        self.asset_source.dependencies.read(&id);
+
         // Try to load the asset first
         if let Some(asset) = self.fonts.get(&id) {
             return asset;
         }
 
         // Asset not found, load it
-        let asset = Font::load(&id, self);
+        let asset = self.load::<Font>(&id).unwrap_or_else(|err| panic!("{err}"));
         self.fonts.insert(id, asset)
     }
 
@@ -658,45 +834,95 @@ impl ContextInner {
         // Create the ID
         let id = Id::new(id);
 
+        // The asset that's currently being loaded depends on this asset
+        self.asset_source.dependencies.read(&id);
+
         // Try to load the asset first
         if let Some(asset) = self.audio.get(&id) {
             return asset;
         }
 
         // Asset not found, load it
-        let asset = Audio::load(&id, self);
+        let asset = self
+            .load::<Audio>(&id)
+            .unwrap_or_else(|err| panic!("{err}"));
         self.audio.insert(id, asset)
     }
 
+    /// Get or load a streamed music track.
+    ///
+    /// # Panics
+    ///
+    /// - When music asset could not be loaded.
+    #[inline]
+    pub(crate) fn music(&mut self, id: &str) -> Rc<Music> {
+        // Create the ID
+        let id = Id::new(id);
+
+        // The asset that's currently being loaded depends on this asset
+        self.asset_source.dependencies.read(&id);
+
+        // Try to load the asset first
+        if let Some(asset) = self.music.get(&id) {
+            return asset;
+        }
+
+        // Asset not found, load it
+        let asset = self
+            .load::<Music>(&id)
+            .unwrap_or_else(|err| panic!("{err}"));
+        self.music.insert(id, asset)
+    }
+
     /// Get or load a custom asset.
     ///
     /// # Panics
     ///
-    /// - When audio asset could not be loaded.
+    /// - When asset could not be loaded.
     /// - When type used to load the asset mismatches the type used to get it.
     #[inline]
     pub(crate) fn custom<T>(&mut self, id: &str) -> Rc<T>
+    where
+        T: Loadable,
+    {
+        self.try_custom(id).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Get or load a custom asset, returning an error when it could not be loaded.
+    ///
+    /// # Errors
+    ///
+    /// - When asset could not be loaded.
+    ///
+    /// # Panics
+    ///
+    /// - When type used to load the asset mismatches the type used to get it.
+    #[inline]
+    pub(crate) fn try_custom<T>(&mut self, id: &str) -> Result<Rc<T>, AssetError>
     where
         T: Loadable,
     {
         // Create the ID
         let id = Id::new(id);
 
+        // The asset that's currently being loaded depends on this asset
+        self.asset_source.dependencies.read(&id);
+
         // Try to load the asset first
         if let Some(asset) = self.custom.get(&id) {
-            return asset;
+            return Ok(asset);
         }
 
         // Asset not found, load it
-        let asset = T::load(&id, self);
-        self.custom.insert(id, asset)
+        let asset = self.load::<T>(&id)?;
+        Ok(self.custom.insert(id, asset))
     }
 
     /// Get a clone or load a custom asset.
     ///
     /// # Panics
     ///
-    /// - When audio asset could not be loaded.
+    /// - When asset could not be loaded.
     /// - When type used to load the asset mismatches the type used to get it.
     #[inline]
     pub(crate) fn custom_owned<T>(&mut self, id: &str) -> T
@@ -707,12 +933,100 @@ impl ContextInner {
         Rc::<T>::unwrap_or_clone(self.custom(id))
     }
 
-    /// Remove all assets with the specified ID if they exist.
+    /// Load all assets with the specified ID again if they are loaded, together with all assets depending on them.
+    ///
+    /// When an asset fails loading the error is printed and the previous version is kept.
     #[inline]
-    pub(crate) fn remove(&mut self, id: &Id) {
-        self.sprites.remove(id);
-        self.fonts.remove(id);
-        self.audio.remove(id);
-        self.custom.remove(id);
+    pub(crate) fn reload(&mut self, id: &Id) {
+        // Find what depends on the asset before the dependencies are registered again
+        let dependents = self.asset_source.dependencies.dependents(id);
+
+        self.reload_id(id);
+        for dependent in &dependents {
+            self.reload_id(dependent);
+        }
+    }
+
+    /// Load an asset while registering the assets it reads as its dependencies.
+    fn load<T>(&mut self, id: &Id) -> Result<T, AssetError>
+    where
+        T: Loadable,
+    {
+        self.asset_source.dependencies.start(id);
+        let asset = T::load(id, self);
+        self.asset_source.dependencies.finish();
+
+        asset
+    }
+
+    /// Load all assets with the specified ID again if they are loaded.
+    fn reload_id(&mut self, id: &Id) {
+        // Files decoded in the background are outdated
+        self.asset_source.forget_preloaded(id);
+
+        // The dependencies are registered again while loading
+        self.asset_source.dependencies.forget(id);
+
+        let mut reloaded = self.reload_asset(id, |ctx| &mut ctx.sprites, Sprite::textures);
+        reloaded |= self.reload_asset(id, |ctx| &mut ctx.fonts, Font::textures);
+        reloaded |= self.reload_asset(id, |ctx| &mut ctx.audio, |_| Vec::new());
+        reloaded |= self.reload_asset(id, |ctx| &mut ctx.music, |_| Vec::new());
+
+        // Custom assets are type erased, so they are loaded with the loader of their type
+        if let Some(loader) = self.custom.loader(id) {
+            self.asset_source.dependencies.start(id);
+            let asset = loader(id, self);
+            self.asset_source.dependencies.finish();
+
+            match asset {
+                Ok(asset) => {
+                    self.custom.replace(id, asset);
+                    reloaded = true;
+                }
+                Err(err) => eprintln!("Error reloading asset, keeping the previous version: {err}"),
+            }
+        }
+
+        // Let the game know
+        if reloaded {
+            self.reloaded_assets.push(id.clone());
+        }
+    }
+
+    /// Load an asset again if it's loaded, keeping the previous version when it fails.
+    ///
+    /// The atlas textures of the previous version are overwritten in place when the new ones have the same size, the others are removed.
+    ///
+    /// Returns whether the asset is reloaded.
+    fn reload_asset<T>(
+        &mut self,
+        id: &Id,
+        assets: fn(&mut Self) -> &mut AssetManager<T>,
+        textures: fn(&T) -> Vec<TextureRef>,
+    ) -> bool
+    where
+        T: Loadable,
+    {
+        let Some(previous) = assets(self).get(id) else {
+            return false;
+        };
+
+        // Reuse the space in the atlas of the previous version
+        self.graphics.atlas.start_replacing(textures(&previous));
+        let result = self.load::<T>(id);
+        self.graphics.atlas.finish_replacing(result.is_ok());
+
+        match result {
+            Ok(asset) => {
+                assets(self).insert(id.clone(), asset);
+
+                true
+            }
+            Err(err) => {
+                eprintln!("Error reloading asset, keeping the previous version: {err}");
+
+                false
+            }
+        }
     }
 }
@@ -0,0 +1,277 @@
+//! Zero-cost abstraction types for playing streamed music tracks.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::{io::Cursor, sync::Arc};
+
+#[cfg(not(target_arch = "wasm32"))]
+use kira::sound::streaming::StreamingSoundData;
+
+use crate::{
+    assets::{loadable::music::MusicData, Id},
+    context::audio::{tween, AudioHandle, AudioState, SoundHandle},
+    Context,
+};
+
+/// Audio bus music tracks are played on by default.
+const MUSIC_BUS: &str = "music";
+
+/// Specify how a music track should be played.
+///
+/// Must call [`Self::play`] to play the track.
+///
+/// Used by [`crate::Context::music`].
+pub struct MusicContext<'path, 'ctx> {
+    /// Path of the music track to play.
+    pub(crate) path: &'path str,
+    /// Reference to the context the music will play in.
+    pub(crate) ctx: &'ctx Context,
+    /// Volume of the track.
+    pub(crate) volume: Option<f32>,
+    /// Seconds to fade out the previous track while fading in this track.
+    pub(crate) crossfade: f32,
+    /// Whether to keep looping the track.
+    pub(crate) looping: bool,
+    /// Name of the audio bus to play on, the `"music"` bus when not set.
+    pub(crate) bus: Option<Id>,
+}
+
+impl<'path, 'ctx> MusicContext<'path, 'ctx> {
+    /// Set the volume of the track.
+    ///
+    /// # Arguments
+    ///
+    /// * `volume` - Volume multiplication factor in the range `0.0..=1.0`.
+    #[inline(always)]
+    #[must_use]
+    pub const fn with_volume(mut self, volume: f32) -> Self {
+        self.volume = Some(volume);
+
+        self
+    }
+
+    /// Fade out the currently playing track while this track fades in.
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - Duration of the crossfade, `0.0` (default) cuts to the new track immediately.
+    #[inline(always)]
+    #[must_use]
+    pub const fn crossfade(mut self, seconds: f32) -> Self {
+        self.crossfade = seconds;
+
+        self
+    }
+
+    /// Play the track only once instead of looping it.
+    #[inline(always)]
+    #[must_use]
+    pub const fn once(mut self) -> Self {
+        self.looping = false;
+
+        self
+    }
+
+    /// Play the track on another audio bus than the `"music"` bus.
+    ///
+    /// # Arguments
+    ///
+    /// * `bus` - Name of the bus, see [`crate::Context::audio_bus`] for controlling it.
+    #[inline(always)]
+    #[must_use]
+    pub fn on_bus(mut self, bus: &str) -> Self {
+        self.bus = Some(Id::new(bus));
+
+        self
+    }
+
+    /// Start playing the track, replacing the currently playing track.
+    ///
+    /// Nothing happens when the same track is already playing, so it's safe to call this every update.
+    /// The track is looped between the `loop_start` and `loop_end` seconds from the metadata RON file next to it, or fully when there's no metadata.
+    ///
+    /// When the track could not be played on the manager an error is printed and the previous track keeps playing.
+    ///
+    /// # Panics
+    ///
+    /// - When asset failed loading.
+    #[inline(always)]
+    pub fn play(self) {
+        self.ctx.write(|ctx| {
+            let id = Id::new(self.path);
+
+            // Don't restart the track when it's already playing
+            if let Some((playing_id, handle)) = &ctx.playing_music {
+                if *playing_id == id
+                    && !matches!(handle.state(), AudioState::Stopping | AudioState::Stopped)
+                {
+                    return;
+                }
+            }
+
+            // Get the music data
+            let music = ctx.music(self.path);
+            let fade_in = (self.crossfade > 0.0).then(|| tween(self.crossfade));
+            let output_destination = ctx.audio_buses.output_destination(
+                self.bus.as_deref().unwrap_or(MUSIC_BUS),
+                &mut ctx.audio_manager,
+            );
+
+            let result = match &music.data {
+                // Setup a new decoder for the stream
+                #[cfg(not(target_arch = "wasm32"))]
+                MusicData::Stream(stream) => {
+                    StreamingSoundData::from_cursor(Cursor::new(Arc::clone(&stream.0)))
+                        .map_err(|err| err.to_string())
+                        .map(|mut sound_data| {
+                            // Set the volume
+                            if let Some(volume) = self.volume {
+                                sound_data = sound_data.volume(f64::from(volume));
+                            }
+
+                            // Set the loop region
+                            if self.looping {
+                                sound_data = sound_data.loop_region(music.metadata.loop_region());
+                            }
+
+                            sound_data
+                                .fade_in_tween(fade_in)
+                                .output_destination(output_destination)
+                        })
+                        .and_then(|sound_data| {
+                            ctx.audio_manager
+                                .play(sound_data)
+                                .map(SoundHandle::Streaming)
+                                .map_err(|err| err.to_string())
+                        })
+                }
+                // Use the already decoded audio
+                #[cfg(target_arch = "wasm32")]
+                MusicData::Decoded(sound_data) => {
+                    let mut sound_data = sound_data
+                        .fade_in_tween(fade_in)
+                        .output_destination(output_destination);
+
+                    // Set the volume
+                    if let Some(volume) = self.volume {
+                        sound_data = sound_data.volume(f64::from(volume));
+                    }
+
+                    // Set the loop region
+                    if self.looping {
+                        sound_data = sound_data.loop_region(music.metadata.loop_region());
+                    }
+
+                    ctx.audio_manager
+                        .play(sound_data)
+                        .map(SoundHandle::Static)
+                        .map_err(|err| err.to_string())
+                }
+            };
+
+            match result {
+                Ok(handle) => {
+                    // Fade out the previous track
+                    if let Some((_, mut previous)) = ctx.playing_music.take() {
+                        previous.stop(self.crossfade);
+                    }
+
+                    // Let the bus duck other buses while the track plays
+                    let handle = AudioHandle::new(handle);
+                    ctx.audio_buses
+                        .track_sound(self.bus.as_deref().unwrap_or(MUSIC_BUS), &handle);
+
+                    ctx.playing_music = Some((id, handle));
+                }
+                Err(err) => eprintln!("Error playing music '{}': {err}", self.path),
+            }
+        });
+    }
+}
+
+/// Music methods.
+impl Context {
+    /// Play a music track.
+    ///
+    /// Unlike [`Self::audio`] the track is not fully decoded into memory but streamed while playing, which makes it suitable for long tracks.
+    /// Tracks decoded in the background with [`Self::preload`] are played from memory instead.
+    /// Only a single track plays at the same time, starting another track replaces it.
+    /// The tracks are played on the `"music"` audio bus, see [`Self::audio_bus`].
+    /// Check the [`MusicContext`] documentation for the options available.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Asset path of the `.ogg` audio file, see [`Self`] for more information about asset loading and storing.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use chuot::Context;
+    ///
+    /// # struct Empty; impl Empty {
+    /// // In `Game::update` trait implementation
+    /// // ..
+    /// fn update(&mut self, ctx: Context) {
+    /// # let in_battle = false;
+    ///   if in_battle {
+    ///     // Fade from the overworld music into the "battle.ogg" track in two seconds
+    ///     ctx.music("battle").crossfade(2.0).play();
+    ///   } else {
+    ///     ctx.music("overworld").crossfade(2.0).play();
+    ///   }
+    /// }
+    /// # }
+    #[inline(always)]
+    #[must_use]
+    pub const fn music<'path>(&self, path: &'path str) -> MusicContext<'path, '_> {
+        MusicContext {
+            path,
+            ctx: self,
+            volume: None,
+            crossfade: 0.0,
+            looping: true,
+            bus: None,
+        }
+    }
+
+    /// Stop the currently playing music track.
+    ///
+    /// # Arguments
+    ///
+    /// * `fade_out` - Seconds to fade out before the track is stopped.
+    #[inline(always)]
+    pub fn stop_music(&self, fade_out: f32) {
+        self.write(|ctx| {
+            if let Some((_, mut handle)) = ctx.playing_music.take() {
+                handle.stop(fade_out);
+            }
+        });
+    }
+
+    /// Pause the currently playing music track.
+    ///
+    /// # Arguments
+    ///
+    /// * `fade` - Seconds to fade out before the track is paused.
+    #[inline(always)]
+    pub fn pause_music(&self, fade: f32) {
+        self.write(|ctx| {
+            if let Some((_, handle)) = &mut ctx.playing_music {
+                handle.pause(fade);
+            }
+        });
+    }
+
+    /// Resume the paused music track.
+    ///
+    /// # Arguments
+    ///
+    /// * `fade` - Seconds to fade in after the track is resumed.
+    #[inline(always)]
+    pub fn resume_music(&self, fade: f32) {
+        self.write(|ctx| {
+            if let Some((_, handle)) = &mut ctx.playing_music {
+                handle.resume(fade);
+            }
+        });
+    }
+}
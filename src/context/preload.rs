@@ -0,0 +1,136 @@
+//! Decode assets in the background for loading screens.
+
+use std::rc::Rc;
+
+use crate::{assets::preload::PreloadProgress, Context};
+
+/// Handle to check the progress of assets decoded in the background.
+///
+/// Returned by [`crate::Context::preload`].
+/// Dropping the handle doesn't stop the decoding.
+#[derive(Debug, Clone)]
+pub struct PreloadHandle {
+    /// Progress shared with the preloader that updates it.
+    progress: Rc<PreloadProgress>,
+}
+
+impl PreloadHandle {
+    /// How much of the files are decoded.
+    ///
+    /// # Returns
+    ///
+    /// - Fraction in the range `0.0..=1.0`, `1.0` when there was nothing to decode.
+    #[inline]
+    #[must_use]
+    pub fn progress(&self) -> f32 {
+        if self.progress.total == 0 {
+            return 1.0;
+        }
+
+        self.progress.decoded.get() as f32 / self.progress.total as f32
+    }
+
+    /// Whether all files are decoded.
+    ///
+    /// # Returns
+    ///
+    /// - `true` when all files are decoded, including the files that failed decoding.
+    #[inline]
+    #[must_use]
+    pub fn is_done(&self) -> bool {
+        self.progress.decoded.get() >= self.progress.total
+    }
+
+    /// Amount of files that are decoded.
+    #[inline]
+    #[must_use]
+    pub fn decoded(&self) -> usize {
+        self.progress.decoded.get()
+    }
+
+    /// Amount of files to decode.
+    #[inline]
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.progress.total
+    }
+}
+
+/// Asset preloading methods.
+impl Context {
+    /// Decode assets in the background so they don't cause hitches when they are used for the first time.
+    ///
+    /// On native platforms the files are decoded on worker threads, on the web they are decoded a bit every frame.
+    /// Only the upload to the GPU is done when the asset is used for the first time.
+    /// Images, Aseprite files, `BMFont` descriptors and audio are decoded in the background, RON files are still parsed when used.
+    /// On native platforms OGG files are only read, since music is streamed while playing, sounds are decoded when used.
+    /// Errors are returned or panicked when the asset is used, just like assets that are not preloaded.
+    ///
+    /// Assets that are already loaded are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `patterns` - Asset paths to decode, a path ending with `*` decodes all assets starting with the rest of the path, such as `"level2.*"` for everything in the `level2/` directory.
+    ///
+    /// # Returns
+    ///
+    /// - A handle to check the progress of the decoding.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use chuot::{context::preload::PreloadHandle, Context, Game};
+    ///
+    /// struct MyGame {
+    ///     loading: Option<PreloadHandle>,
+    /// }
+    ///
+    /// impl Game for MyGame {
+    ///     fn update(&mut self, ctx: Context) {
+    ///         // Start decoding everything needed for the next level
+    ///         self.loading
+    ///             .get_or_insert_with(|| ctx.preload(["level2.*"]));
+    ///     }
+    ///
+    ///     fn render(&mut self, ctx: Context) {
+    ///         if let Some(loading) = &self.loading {
+    ///             if !loading.is_done() {
+    ///                 // Draw a loading bar
+    ///                 ctx.sprite("loading_bar")
+    ///                     .scale((loading.progress(), 1.0))
+    ///                     .draw();
+    ///
+    ///                 return;
+    ///             }
+    ///         }
+    ///
+    ///         // Draw the level
+    ///         ctx.sprite("level2.background").draw();
+    ///     }
+    /// }
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn preload<I, S>(&self, patterns: I) -> PreloadHandle
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let patterns = patterns
+            .into_iter()
+            .map(|pattern| pattern.as_ref().to_owned())
+            .collect::<Vec<_>>();
+
+        self.write(|ctx| {
+            // Skip the assets that are already loaded
+            let progress = ctx.preloader.start(&patterns, &ctx.asset_source, |id| {
+                ctx.sprites.contains(id)
+                    || ctx.fonts.contains(id)
+                    || ctx.audio.contains(id)
+                    || ctx.music.contains(id)
+            });
+
+            PreloadHandle { progress }
+        })
+    }
+}
@@ -1,7 +1,8 @@
 //! Zero-cost abstraction types for building more complicated sprite drawing constructions.
 
 use crate::{
-    assets::{loadable::sprite::Sprite, Id},
+    assets::{loadable::sprite::Sprite, AssetError, Id},
+    graphics::instance::{BlendMode, Style},
     Context,
 };
 
@@ -21,6 +22,20 @@ pub struct SpriteContext<'path, 'ctx> {
     pub(crate) y: f32,
     /// Rotation in radians.
     pub(crate) rotation: f32,
+    /// Horizontal scaling factor, negative when flipped.
+    pub(crate) scale_x: f32,
+    /// Vertical scaling factor, negative when flipped.
+    pub(crate) scale_y: f32,
+    /// Point relative to the left top of the sprite to rotate, scale and flip around.
+    ///
+    /// When `None` the sprite offset metadata is used.
+    pub(crate) pivot: Option<(f32, f32)>,
+    /// Color, blending and ordering properties.
+    pub(crate) style: Style,
+    /// Name of the animation clip to draw a frame of.
+    pub(crate) animation: Option<&'path str>,
+    /// Time in seconds since the animation clip started.
+    pub(crate) time: f32,
 }
 
 impl<'path, 'ctx> SpriteContext<'path, 'ctx> {
@@ -67,7 +82,7 @@ impl<'path, 'ctx> SpriteContext<'path, 'ctx> {
 
     /// Rotate the sprite.
     ///
-    /// Rotation will always be applied before translation, this mean it will always rotate around the center point specified in the sprite offset metadata.
+    /// Rotation will always be applied before translation, this mean it will always rotate around the center point specified in the sprite offset metadata, or the point set with [`Self::pivot`].
     ///
     /// # Arguments
     ///
@@ -80,9 +95,281 @@ impl<'path, 'ctx> SpriteContext<'path, 'ctx> {
         self
     }
 
+    /// Scale the sprite.
+    ///
+    /// Scaling will always be applied before translation, around the center point specified in the sprite offset metadata, or the point set with [`Self::pivot`].
+    ///
+    /// # Arguments
+    ///
+    /// * `(scale_x, scale_y)` - Horizontal and vertical multiplication factor tuple of the size of the target sprite, negative values flip the sprite.
+    #[inline(always)]
+    #[must_use]
+    pub fn scale(mut self, scale: impl Into<(f32, f32)>) -> Self {
+        let (scale_x, scale_y) = scale.into();
+        self.scale_x *= scale_x;
+        self.scale_y *= scale_y;
+
+        self
+    }
+
+    /// Only scale the sprite horizontally.
+    ///
+    /// # Arguments
+    ///
+    /// * `scale_x` - Horizontal multiplication factor of the width of the target sprite, a negative value flips the sprite.
+    #[inline(always)]
+    #[must_use]
+    pub fn scale_x(mut self, scale_x: f32) -> Self {
+        self.scale_x *= scale_x;
+
+        self
+    }
+
+    /// Only scale the sprite vertically.
+    ///
+    /// # Arguments
+    ///
+    /// * `scale_y` - Vertical multiplication factor of the height of the target sprite, a negative value flips the sprite.
+    #[inline(always)]
+    #[must_use]
+    pub fn scale_y(mut self, scale_y: f32) -> Self {
+        self.scale_y *= scale_y;
+
+        self
+    }
+
+    /// Mirror the sprite horizontally.
+    ///
+    /// The sprite is flipped around the center point specified in the sprite offset metadata, or the point set with [`Self::pivot`].
+    ///
+    /// # Arguments
+    ///
+    /// * `flip` - Whether to flip the sprite, useful for passing the direction a character is facing.
+    #[inline(always)]
+    #[must_use]
+    pub fn flip_x(mut self, flip: bool) -> Self {
+        if flip {
+            self.scale_x = -self.scale_x;
+        }
+
+        self
+    }
+
+    /// Mirror the sprite vertically.
+    ///
+    /// The sprite is flipped around the center point specified in the sprite offset metadata, or the point set with [`Self::pivot`].
+    ///
+    /// # Arguments
+    ///
+    /// * `flip` - Whether to flip the sprite.
+    #[inline(always)]
+    #[must_use]
+    pub fn flip_y(mut self, flip: bool) -> Self {
+        if flip {
+            self.scale_y = -self.scale_y;
+        }
+
+        self
+    }
+
+    /// Set the point the sprite is rotated, scaled and flipped around.
+    ///
+    /// This doesn't change where the sprite is drawn, the sprite offset metadata is still used for positioning.
+    ///
+    /// # Arguments
+    ///
+    /// * `(x, y)` - Position tuple in pixels relative to the left top of the sprite.
+    #[inline(always)]
+    #[must_use]
+    pub fn pivot(mut self, pivot: impl Into<(f32, f32)>) -> Self {
+        self.pivot = Some(pivot.into());
+
+        self
+    }
+
+    /// Multiply the colors of the sprite with a color.
+    ///
+    /// Calling this multiple times will multiply the colors with each other.
+    ///
+    /// # Arguments
+    ///
+    /// * `color` - Color in `0xAARRGGBB` format to multiply each pixel with, `0xFFFFFFFF` (white) leaves the sprite unchanged.
+    #[inline(always)]
+    #[must_use]
+    pub fn tint(mut self, color: u32) -> Self {
+        self.style.tint(color);
+
+        self
+    }
+
+    /// Make the sprite transparent.
+    ///
+    /// Calling this multiple times will multiply the alpha values with each other.
+    ///
+    /// # Arguments
+    ///
+    /// * `alpha` - Opacity in the range `0.0..=1.0`, where `0.0` is invisible and `1.0` is fully opaque.
+    #[inline(always)]
+    #[must_use]
+    pub fn alpha(mut self, alpha: f32) -> Self {
+        self.style.alpha(alpha);
+
+        self
+    }
+
+    /// Mix all visible pixels of the sprite with a solid color.
+    ///
+    /// Useful for flashing white when something is hit.
+    ///
+    /// # Arguments
+    ///
+    /// * `color` - Color in `0xAARRGGBB` format to mix with, the alpha component is ignored.
+    /// * `amount` - How much of the color to mix in the range `0.0..=1.0`, where `1.0` results in a solid color silhouette.
+    #[inline(always)]
+    #[must_use]
+    pub fn flash(mut self, color: u32, amount: f32) -> Self {
+        self.style.flash(color, amount);
+
+        self
+    }
+
+    /// Set how the sprite is combined with what's already drawn below it.
+    ///
+    /// Switching between blend modes splits the drawing into multiple batches, so it's faster to draw everything with the same blend mode after each other.
+    ///
+    /// # Arguments
+    ///
+    /// * `blend_mode` - How to blend the pixels, see [`BlendMode`] for the options.
+    #[inline(always)]
+    #[must_use]
+    pub const fn blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.style.blend_mode = blend_mode;
+
+        self
+    }
+
+    /// Set the order in which the sprite is drawn within its layer.
+    ///
+    /// Everything with a higher z-index is drawn on top of everything with a lower z-index on the same layer, when the z-index is the same the sprite drawn last is shown on top.
+    ///
+    /// # Arguments
+    ///
+    /// * `z` - Draw order within the layer, defaults to `0`.
+    #[inline(always)]
+    #[must_use]
+    pub const fn z(mut self, z: i32) -> Self {
+        self.style.z = z;
+
+        self
+    }
+
+    /// Draw a frame of an animation clip defined in the sprite metadata.
+    ///
+    /// Without an animation the first frame is drawn for animated sprites.
+    ///
+    /// # Arguments
+    ///
+    /// * `clip` - Name of the clip in the `clips` field of the sprite metadata.
+    ///
+    /// # Example
+    ///
+    /// With the following `player.ron` RON configuration file:
+    ///
+    /// ```ron
+    /// (
+    ///     offset: Middle,
+    ///     frames: Grid(width: 16.0, height: 16.0),
+    ///     clips: {
+    ///         "idle": (frames: [0, 1], durations: [0.5]),
+    ///         "run": (frames: [2, 3, 4, 5], durations: [0.1, 0.1, 0.1, 0.2], mode: PingPong),
+    ///         "die": (frames: [6, 7, 8], mode: Once),
+    ///     },
+    /// )
+    /// ```
+    ///
+    /// The running animation can be drawn with:
+    ///
+    /// ```no_run
+    /// # fn call(ctx: chuot::Context, time: f32) {
+    /// ctx.sprite("player").animation("run").time(time).draw();
+    /// # }
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// - When the clip is not defined in the metadata of the sprite, this is checked when drawing.
+    #[inline(always)]
+    #[must_use]
+    pub const fn animation(mut self, clip: &'path str) -> Self {
+        self.animation = Some(clip);
+
+        self
+    }
+
+    /// Set the time used for choosing the frame of the animation clip.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - Seconds since the animation clip started.
+    #[inline(always)]
+    #[must_use]
+    pub const fn time(mut self, time: f32) -> Self {
+        self.time = time;
+
+        self
+    }
+
+    /// Draw the current frame of an animation state.
+    ///
+    /// Same as calling [`Self::animation`] and [`Self::time`] with the values of the state.
+    ///
+    /// # Arguments
+    ///
+    /// * `state` - Playback state of the animation, advanced in the update loop.
+    #[inline(always)]
+    #[must_use]
+    pub fn animation_state(mut self, state: &'path AnimationState) -> Self {
+        self.animation = Some(&state.clip);
+        self.time = state.time;
+
+        self
+    }
+
+    /// Ignore the camera and draw the sprite directly in buffer coordinates.
+    ///
+    /// Useful for HUD elements which must stay at the same position on the screen.
+    /// Screen space is drawn once on top of all viewports, so the layer and z-index only order it with other sprites and text in screen space.
+    #[inline(always)]
+    #[must_use]
+    pub const fn in_screen_space(mut self) -> Self {
+        self.style.screen_space = true;
+
+        self
+    }
+
+    /// Draw the sprite on a named render layer.
+    ///
+    /// Layers are drawn in the order they are defined in [`crate::Config::layers`], everything drawn without a layer is drawn on the first layer.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the layer as defined in [`crate::Config::layers`].
+    ///
+    /// # Panics
+    ///
+    /// - When no layer with the name is defined in the config.
+    /// - When the layer is defined after the first `u16::MAX` layers.
+    #[inline(always)]
+    #[must_use]
+    pub fn layer(mut self, name: &str) -> Self {
+        self.style.layer = self.ctx.read(|ctx| ctx.config.layer_index(name));
+
+        self
+    }
+
     /// Draw the sprite.
     ///
-    /// Sprites that are drawn last are always shown on top of sprites that are drawn earlier.
+    /// Sprites that are drawn last are shown on top of sprites that are drawn earlier on the same layer and z-index.
     ///
     /// # Panics
     ///
@@ -93,22 +380,32 @@ impl<'path, 'ctx> SpriteContext<'path, 'ctx> {
             // Push the instance if the texture is already uploaded
             let sprite = ctx.sprite(self.path);
 
+            // Use the current frame of the animation if applicable
+            let sub_rectangle = self.sub_rectangle(&sprite);
+
             // Create the affine matrix
-            let affine_matrix = sprite.affine_matrix(self.x, self.y, self.rotation);
+            let affine_matrix = sprite.transformed_affine_matrix(
+                sub_rectangle,
+                self.x,
+                self.y,
+                self.rotation,
+                (self.scale_x, self.scale_y),
+                self.pivot,
+            );
 
             // Push the graphics
             ctx.graphics
                 .instances
-                .push(affine_matrix, sprite.sub_rectangle, sprite.texture);
+                .push(affine_matrix, sub_rectangle, sprite.texture, self.style);
         });
     }
 
     /// Optimized way to draw the sprite multiple times with a translation added to each.
     ///
-    /// Calling [`Self::translate`] and/or [`Self::rotate`] before this method will create a base matrix onto which each item translation is applied afterwards.
+    /// Calling [`Self::translate`], [`Self::rotate`] and/or [`Self::scale`] before this method will create a base matrix onto which each item translation is applied afterwards.
     /// This allows you to easily draw thousands of sprites, perfect for particle effects.
     ///
-    /// Sprites that are drawn last are always shown on top of sprites that are drawn earlier.
+    /// Sprites that are drawn last are shown on top of sprites that are drawn earlier on the same layer and z-index.
     ///
     /// # Arguments
     ///
@@ -149,13 +446,22 @@ impl<'path, 'ctx> SpriteContext<'path, 'ctx> {
             // Push the instance if the texture is already uploaded
             let sprite = ctx.sprite(self.path);
 
+            // Use the current frame of the animation if applicable
+            let sub_rectangle = self.sub_rectangle(&sprite);
+
             // Create the affine matrix
-            let affine_matrix = sprite.affine_matrix(self.x, self.y, self.rotation);
+            let affine_matrix = sprite.transformed_affine_matrix(
+                sub_rectangle,
+                self.x,
+                self.y,
+                self.rotation,
+                (self.scale_x, self.scale_y),
+                self.pivot,
+            );
 
             // Push the graphics
-            ctx.graphics
-                .instances
-                .extend(translations.map(|translation| {
+            ctx.graphics.instances.extend(
+                translations.map(|translation| {
                     let (x_offset, y_offset) = translation.into();
 
                     // Copy the matrix
@@ -163,12 +469,10 @@ impl<'path, 'ctx> SpriteContext<'path, 'ctx> {
                     affine_matrix_with_offset.translation.x += x_offset;
                     affine_matrix_with_offset.translation.y += y_offset;
 
-                    (
-                        affine_matrix_with_offset,
-                        sprite.sub_rectangle,
-                        sprite.texture,
-                    )
-                }));
+                    (affine_matrix_with_offset, sub_rectangle, sprite.texture)
+                }),
+                self.style,
+            );
         });
     }
 
@@ -183,6 +487,7 @@ impl<'path, 'ctx> SpriteContext<'path, 'ctx> {
     ///
     /// - When a sprite with the same ID already exists.
     /// - When `width * height != pixels.len()`.
+    /// - When `width` or `height` is bigger than 4096 pixels, the size of an atlas page.
     #[inline]
     pub fn create(self, size: impl Into<(f32, f32)>, pixels: impl AsRef<[u32]>) {
         let (width, height) = size.into();
@@ -283,7 +588,7 @@ impl<'path, 'ctx> SpriteContext<'path, 'ctx> {
     ///
     /// # Returns
     ///
-    /// - `width`, horizontal size of the sprite in pixels.
+    /// - `width`, horizontal size of the sprite in pixels, for animated sprites this is the size of the current frame.
     ///
     /// # Panics
     ///
@@ -291,14 +596,15 @@ impl<'path, 'ctx> SpriteContext<'path, 'ctx> {
     #[inline]
     #[must_use]
     pub fn width(&self) -> f32 {
-        self.ctx.write(|ctx| ctx.sprite(self.path).sub_rectangle.2)
+        self.ctx
+            .write(|ctx| self.sub_rectangle(&ctx.sprite(self.path)).2)
     }
 
     /// Get the height of the sprite in pixels.
     ///
     /// # Returns
     ///
-    /// - `height`, vertical size of the sprite in pixels.
+    /// - `height`, vertical size of the sprite in pixels, for animated sprites this is the size of the current frame.
     ///
     /// # Panics
     ///
@@ -306,7 +612,113 @@ impl<'path, 'ctx> SpriteContext<'path, 'ctx> {
     #[inline]
     #[must_use]
     pub fn height(&self) -> f32 {
-        self.ctx.write(|ctx| ctx.sprite(self.path).sub_rectangle.3)
+        self.ctx
+            .write(|ctx| self.sub_rectangle(&ctx.sprite(self.path)).3)
+    }
+
+    /// Get the sub rectangle to draw, taking the animation into account.
+    fn sub_rectangle(&self, sprite: &Sprite) -> (f32, f32, f32, f32) {
+        self.animation.map_or(sprite.sub_rectangle, |clip| {
+            sprite.animation_frame(clip, self.time)
+        })
+    }
+}
+
+/// Playback state of an animation clip, advanced in the update loop.
+///
+/// Draw it with [`SpriteContext::animation_state`].
+///
+/// # Example
+///
+/// ```no_run
+/// use chuot::{context::sprite::AnimationState, Context, Game};
+///
+/// struct GameState {
+///     animation: AnimationState,
+/// }
+///
+/// impl Game for GameState {
+///     fn update(&mut self, ctx: Context) {
+///         // Switch to the running animation, only restarting when it wasn't already running
+///         self.animation.play("run");
+///
+///         // Move the animation forward
+///         self.animation.update(ctx.delta_time());
+///     }
+///
+///     fn render(&mut self, ctx: Context) {
+///         ctx.sprite("player")
+///             .animation_state(&self.animation)
+///             .draw();
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationState {
+    /// Name of the animation clip.
+    clip: Id,
+    /// Seconds since the clip started.
+    time: f32,
+}
+
+impl AnimationState {
+    /// Start playing an animation clip.
+    ///
+    /// # Arguments
+    ///
+    /// * `clip` - Name of the clip in the `clips` field of the sprite metadata.
+    #[inline]
+    #[must_use]
+    pub fn new(clip: &str) -> Self {
+        let clip = Id::new(clip);
+        let time = 0.0;
+
+        Self { clip, time }
+    }
+
+    /// Switch to another animation clip.
+    ///
+    /// When the clip is already playing nothing happens, otherwise the new clip starts from the beginning.
+    ///
+    /// # Arguments
+    ///
+    /// * `clip` - Name of the clip in the `clips` field of the sprite metadata.
+    #[inline]
+    pub fn play(&mut self, clip: &str) {
+        if self.clip != clip {
+            self.clip = Id::new(clip);
+            self.time = 0.0;
+        }
+    }
+
+    /// Start the current animation clip from the beginning.
+    #[inline]
+    pub fn restart(&mut self) {
+        self.time = 0.0;
+    }
+
+    /// Move the animation forward.
+    ///
+    /// # Arguments
+    ///
+    /// * `delta_time` - Seconds passed since the last update, usually [`Context::delta_time`].
+    #[inline]
+    pub fn update(&mut self, delta_time: f32) {
+        self.time += delta_time;
+    }
+
+    /// Name of the animation clip that's playing.
+    #[inline]
+    #[must_use]
+    pub fn clip(&self) -> &str {
+        &self.clip
+    }
+
+    /// Seconds since the animation clip started.
+    #[inline]
+    #[must_use]
+    pub const fn time(&self) -> f32 {
+        self.time
     }
 }
 
@@ -337,6 +749,56 @@ impl Context {
             x: 0.0,
             y: 0.0,
             rotation: 0.0,
+            scale_x: 1.0,
+            scale_y: 1.0,
+            pivot: None,
+            style: Style::new(),
+            animation: None,
+            time: 0.0,
         }
     }
+
+    /// Handle sprite assets, returning an error when the sprite could not be loaded.
+    ///
+    /// The sprite is loaded immediately, so drawing it afterwards can't fail.
+    /// Useful for assets that might not exist, such as user supplied files.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Asset path of the sprite, see [`Self`] for more information about asset loading and storing.
+    ///
+    /// # Returns
+    ///
+    /// - A helper struct allowing you to specify the transformations of the sprite.
+    ///
+    /// # Errors
+    ///
+    /// - When asset failed loading.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use chuot::Context;
+    ///
+    /// # struct Empty; impl Empty {
+    /// // In `Game::render` trait implementation
+    /// // ..
+    /// fn render(&mut self, ctx: Context) {
+    ///   // Draw a placeholder when the level doesn't have a background
+    ///   match ctx.try_sprite("level.background") {
+    ///     Ok(background) => background.draw(),
+    ///     Err(_) => ctx.sprite("placeholder").draw(),
+    ///   }
+    /// }
+    /// # }
+    #[inline]
+    pub fn try_sprite<'path>(
+        &self,
+        path: &'path str,
+    ) -> Result<SpriteContext<'path, '_>, AssetError> {
+        // Load the sprite so drawing it can't fail
+        self.write(|ctx| ctx.try_sprite(path))?;
+
+        Ok(self.sprite(path))
+    }
 }
@@ -1,6 +1,22 @@
 //! Zero-cost abstraction types for building more complicated text drawing constructions.
 
-use crate::Context;
+use std::rc::Rc;
+
+use glam::Affine2;
+
+use crate::{
+    assets::loadable::{
+        font::{FontChain, TextItem},
+        sprite::Sprite,
+    },
+    graphics::instance::{BlendMode, Style},
+    Context,
+};
+
+use super::{
+    markup::{self, MarkupItem, SpanStyle},
+    ContextInner,
+};
 
 /// Specify how the text should be drawn.
 ///
@@ -18,6 +34,22 @@ pub struct TextContext<'font, 'text, 'ctx> {
     pub(crate) y: f32,
     /// Text to draw.
     pub(crate) text: &'text str,
+    /// Color, blending and ordering properties.
+    pub(crate) style: Style,
+    /// Width in pixels after which lines are wrapped.
+    pub(crate) max_width: f32,
+    /// Horizontal alignment of each line relative to the position.
+    pub(crate) align: TextAlign,
+    /// Vertical position of the text relative to the position.
+    pub(crate) anchor: VerticalAnchor,
+    /// Vertical distance between lines, uses the font line height when not set.
+    pub(crate) line_height: Option<f32>,
+    /// Whether the text is parsed as rich text markup.
+    pub(crate) markup: bool,
+    /// Time in seconds driving the markup effects.
+    pub(crate) time: f32,
+    /// Amount of visible characters to draw, draws all when not set.
+    pub(crate) reveal: Option<usize>,
 }
 
 impl<'font, 'text, 'ctx> TextContext<'font, 'text, 'ctx> {
@@ -62,9 +94,255 @@ impl<'font, 'text, 'ctx> TextContext<'font, 'text, 'ctx> {
         self
     }
 
+    /// Multiply the colors of the text with a color.
+    ///
+    /// Calling this multiple times will multiply the colors with each other.
+    ///
+    /// # Arguments
+    ///
+    /// * `color` - Color in `0xAARRGGBB` format to multiply each pixel with, `0xFFFFFFFF` (white) leaves the text unchanged.
+    #[inline(always)]
+    #[must_use]
+    pub fn tint(mut self, color: u32) -> Self {
+        self.style.tint(color);
+
+        self
+    }
+
+    /// Make the text transparent.
+    ///
+    /// Calling this multiple times will multiply the alpha values with each other.
+    ///
+    /// # Arguments
+    ///
+    /// * `alpha` - Opacity in the range `0.0..=1.0`, where `0.0` is invisible and `1.0` is fully opaque.
+    #[inline(always)]
+    #[must_use]
+    pub fn alpha(mut self, alpha: f32) -> Self {
+        self.style.alpha(alpha);
+
+        self
+    }
+
+    /// Mix all visible pixels of the text with a solid color.
+    ///
+    /// Useful for flashing white when something is hit.
+    ///
+    /// # Arguments
+    ///
+    /// * `color` - Color in `0xAARRGGBB` format to mix with, the alpha component is ignored.
+    /// * `amount` - How much of the color to mix in the range `0.0..=1.0`, where `1.0` results in a solid color silhouette.
+    #[inline(always)]
+    #[must_use]
+    pub fn flash(mut self, color: u32, amount: f32) -> Self {
+        self.style.flash(color, amount);
+
+        self
+    }
+
+    /// Set how the text is combined with what's already drawn below it.
+    ///
+    /// Switching between blend modes splits the drawing into multiple batches, so it's faster to draw everything with the same blend mode after each other.
+    ///
+    /// # Arguments
+    ///
+    /// * `blend_mode` - How to blend the pixels, see [`BlendMode`] for the options.
+    #[inline(always)]
+    #[must_use]
+    pub const fn blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.style.blend_mode = blend_mode;
+
+        self
+    }
+
+    /// Set the order in which the text is drawn within its layer.
+    ///
+    /// Everything with a higher z-index is drawn on top of everything with a lower z-index on the same layer, when the z-index is the same the text drawn last is shown on top.
+    ///
+    /// # Arguments
+    ///
+    /// * `z` - Draw order within the layer, defaults to `0`.
+    #[inline(always)]
+    #[must_use]
+    pub const fn z(mut self, z: i32) -> Self {
+        self.style.z = z;
+
+        self
+    }
+
+    /// Ignore the camera and draw the text directly in buffer coordinates.
+    ///
+    /// Useful for HUD text which must stay at the same position on the screen.
+    /// Screen space is drawn once on top of all viewports, so the layer and z-index only order it with other sprites and text in screen space.
+    #[inline(always)]
+    #[must_use]
+    pub const fn in_screen_space(mut self) -> Self {
+        self.style.screen_space = true;
+
+        self
+    }
+
+    /// Draw the text on a named render layer.
+    ///
+    /// Layers are drawn in the order they are defined in [`crate::Config::layers`], everything drawn without a layer is drawn on the first layer.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the layer as defined in [`crate::Config::layers`].
+    ///
+    /// # Panics
+    ///
+    /// - When no layer with the name is defined in the config.
+    /// - When the layer is defined after the first `u16::MAX` layers.
+    #[inline(always)]
+    #[must_use]
+    pub fn layer(mut self, name: &str) -> Self {
+        self.style.layer = self.ctx.read(|ctx| ctx.config.layer_index(name));
+
+        self
+    }
+
+    /// Wrap lines that become wider than a maximum width.
+    ///
+    /// Lines are wrapped at whitespace, words that are wider than the maximum width are split.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_width` - Maximum width of a line in pixels.
+    #[inline(always)]
+    #[must_use]
+    pub const fn max_width(mut self, max_width: f32) -> Self {
+        self.max_width = max_width;
+
+        self
+    }
+
+    /// Align each line horizontally relative to the position.
+    ///
+    /// # Arguments
+    ///
+    /// * `align` - Whether the position is at the left, center or right of each line, see [`TextAlign`] for the options.
+    #[inline(always)]
+    #[must_use]
+    pub const fn align(mut self, align: TextAlign) -> Self {
+        self.align = align;
+
+        self
+    }
+
+    /// Position the whole text vertically relative to the position.
+    ///
+    /// # Arguments
+    ///
+    /// * `anchor` - Whether the position is at the top, middle or bottom of the text, see [`VerticalAnchor`] for the options.
+    #[inline(always)]
+    #[must_use]
+    pub const fn anchor(mut self, anchor: VerticalAnchor) -> Self {
+        self.anchor = anchor;
+
+        self
+    }
+
+    /// Override the vertical distance between lines.
+    ///
+    /// # Arguments
+    ///
+    /// * `line_height` - Distance between the top of two lines in pixels, defaults to the line height of the font.
+    #[inline(always)]
+    #[must_use]
+    pub const fn line_height(mut self, line_height: f32) -> Self {
+        self.line_height = Some(line_height);
+
+        self
+    }
+
+    /// Parse the text as rich text markup.
+    ///
+    /// The following tags are supported:
+    ///
+    /// | Tag | Effect |
+    /// | --- | --- |
+    /// | `[color=#F80]..[/color]` | Tint the characters, accepts `#RGB`, `#RRGGBB` and `#AARRGGBB` |
+    /// | `[wave]..[/wave]` | Move the characters up and down in a wave |
+    /// | `[shake]..[/shake]` | Shake the characters randomly |
+    /// | `[icon=path]` | Draw the sprite with the asset path inline, vertically centered on the line |
+    ///
+    /// Use `[[` to draw a literal `[`, tags that can't be parsed are drawn as text.
+    #[inline(always)]
+    #[must_use]
+    pub const fn markup(mut self) -> Self {
+        self.markup = true;
+
+        self
+    }
+
+    /// Set the time driving the animated markup effects.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - Time in seconds, pass a value that increases every frame such as the accumulated delta time.
+    #[inline(always)]
+    #[must_use]
+    pub const fn time(mut self, time: f32) -> Self {
+        self.time = time;
+
+        self
+    }
+
+    /// Only draw the first characters, used for revealing text character by character.
+    ///
+    /// Whitespace and markup tags are not counted, inline icons are counted as a single character.
+    /// The text is laid out as if it's fully drawn, so revealing more characters doesn't move the characters that are already drawn.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Amount of visible characters to draw.
+    #[inline(always)]
+    #[must_use]
+    pub const fn reveal(mut self, count: usize) -> Self {
+        self.reveal = Some(count);
+
+        self
+    }
+
+    /// Only draw the characters revealed by a typewriter.
+    ///
+    /// Same as calling [`Self::reveal`] with the revealed characters of the typewriter.
+    ///
+    /// # Arguments
+    ///
+    /// * `typewriter` - Reveal state, advanced in the update loop.
+    #[inline(always)]
+    #[must_use]
+    pub const fn typewriter(self, typewriter: &Typewriter) -> Self {
+        self.reveal(typewriter.revealed)
+    }
+
+    /// Get the size of the text when drawn, taking wrapping and the line height into account.
+    ///
+    /// # Returns
+    ///
+    /// - `(width, height)`, size of the text in pixels, trailing whitespace is not included in the width.
+    ///
+    /// # Panics
+    ///
+    /// - When asset failed loading.
+    #[inline]
+    #[must_use]
+    pub fn size(&self) -> (f32, f32) {
+        self.ctx.write(|ctx| {
+            let (fonts, items, _) = self.prepare(ctx);
+
+            let lines = fonts.layout(&items, self.max_width);
+            let width = lines.iter().map(|line| line.width).fold(0.0, f32::max);
+
+            (width, self.height(lines.len(), &fonts))
+        })
+    }
+
     /// Draw the text.
     ///
-    /// Text glyphs and other sprites that are drawn last are always shown on top of sprites that are drawn earlier.
+    /// Text glyphs and other sprites that are drawn last are shown on top of sprites that are drawn earlier on the same layer and z-index.
     ///
     /// # Panics
     ///
@@ -72,45 +350,405 @@ impl<'font, 'text, 'ctx> TextContext<'font, 'text, 'ctx> {
     #[inline(always)]
     pub fn draw(self) {
         self.ctx.write(|ctx| {
-            // Push the instance if the texture is already uploaded
-            let font = ctx.font(self.font);
-
-            // Put the start position back 1 glyph since the first action is to move the cursor
-            let start_x = self.x - font.metadata.glyph_width;
-            let mut x = start_x;
-            let mut y = self.y;
-
-            // Draw each character from the string
-            self.text.chars().for_each(|ch| {
-                let char_index = ch as usize;
-
-                // Move the cursor
-                x += font.metadata.glyph_width;
-
-                // Don't draw characters that are not in the picture
-                if char_index < font.metadata.first_char || char_index > font.metadata.last_char {
-                    if ch == '\n' {
-                        x = start_x;
-                        y += font.metadata.glyph_height;
-                    } else if ch == '\t' {
-                        x += font.metadata.glyph_width * 3.0;
+            let (fonts, items, decorations) = self.prepare(ctx);
+
+            // Position the glyphs on lines
+            let lines = fonts.layout(&items, self.max_width);
+            let line_height = self.line_height.unwrap_or_else(|| fonts.line_height());
+
+            // Move the top of the text based on the anchor, rounded down to stay pixel aligned
+            let height = self.height(lines.len(), &fonts);
+            let top = self.y - self.anchor.offset(height);
+
+            // Find the first item that's hidden, whitespace is not counted
+            let hidden_from = self.reveal.map_or(items.len(), |count| {
+                items
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, item)| !item.is_whitespace())
+                    .nth(count)
+                    .map_or(items.len(), |(index, _)| index)
+            });
+
+            for (line_index, line) in lines.iter().enumerate() {
+                // Move the start of the line based on the alignment
+                let line_x = self.x - self.align.offset(line.width);
+                let line_y = line_height.mul_add(line_index as f32, top);
+
+                for (index, x) in line
+                    .glyphs
+                    .iter()
+                    .take_while(|(index, _)| *index < hidden_from)
+                {
+                    // Apply the markup style
+                    let (span, icon) = decorations
+                        .get(*index)
+                        .map(|(span, icon)| (*span, icon.as_deref()))
+                        .unwrap_or_default();
+                    let (offset_x, offset_y) = span.offset(*index, self.time);
+                    let x = line_x + x + offset_x;
+                    let y = line_y + offset_y;
+
+                    let mut style = self.style;
+                    if let Some(color) = span.color {
+                        style.tint(color);
                     }
-                    return;
+
+                    // Setup the sprite for the glyph or icon
+                    let (sprite, affine_matrix) = match (items[*index], icon) {
+                        (TextItem::Char(ch), _) => {
+                            let Some(sprite) = fonts.glyph(ch).0 else {
+                                continue;
+                            };
+
+                            (sprite, sprite.affine_matrix(x, y, 0.0))
+                        }
+                        (TextItem::Icon { .. }, Some(icon)) => {
+                            // Center the icon vertically on the glyphs
+                            let icon_y =
+                                y + ((fonts.glyph_height() - icon.sub_rectangle.3) / 2.0).floor();
+
+                            (icon, Affine2::from_translation((x, icon_y).into()))
+                        }
+                        (TextItem::Icon { .. }, None) => continue,
+                    };
+
+                    // Push the graphics
+                    ctx.graphics.instances.push(
+                        affine_matrix,
+                        sprite.sub_rectangle,
+                        sprite.texture,
+                        style,
+                    );
+                }
+            }
+        });
+    }
+
+    /// Load the fonts and split the text into items, parsing the markup when enabled.
+    ///
+    /// Returns the style and the icon sprite of each item when the markup is enabled.
+    #[allow(clippy::type_complexity)]
+    fn prepare(
+        &self,
+        ctx: &mut ContextInner,
+    ) -> (
+        FontChain,
+        Vec<TextItem>,
+        Vec<(SpanStyle, Option<Rc<Sprite>>)>,
+    ) {
+        // Load the font and all fonts it falls back to
+        let fonts = FontChain::load(self.font, ctx);
+
+        if !self.markup {
+            return (
+                fonts,
+                self.text.chars().map(TextItem::Char).collect(),
+                Vec::new(),
+            );
+        }
+
+        // Load the icons so their size is known
+        let (items, decorations) = markup::parse(self.text)
+            .into_iter()
+            .map(|(item, span)| match item {
+                MarkupItem::Char(ch) => (TextItem::Char(ch), (span, None)),
+                MarkupItem::Icon(path) => {
+                    let icon = ctx.sprite(path);
+
+                    (
+                        TextItem::Icon {
+                            width: icon.sub_rectangle.2,
+                        },
+                        (span, Some(icon)),
+                    )
                 }
+            })
+            .unzip();
+
+        (fonts, items, decorations)
+    }
+
+    /// Height of the text in pixels.
+    fn height(&self, lines: usize, fonts: &FontChain) -> f32 {
+        let line_height = self.line_height.unwrap_or_else(|| fonts.line_height());
+
+        fonts.text_height(lines, line_height)
+    }
+}
+
+/// Character that represents an inline icon in the revealed characters of a [`Typewriter`].
+pub const TYPEWRITER_ICON_CHAR: char = '\u{FFFC}';
+
+/// Reveal state of a text shown character by character, advanced in the update loop.
+///
+/// Draw it with [`TextContext::typewriter`].
+///
+/// # Example
+///
+/// ```no_run
+/// use chuot::{context::text::Typewriter, Context, Game};
+///
+/// struct GameState {
+///     typewriter: Typewriter,
+/// }
+///
+/// impl Game for GameState {
+///     fn update(&mut self, ctx: Context) {
+///         // Reveal the next characters
+///         self.typewriter.update(ctx.delta_time());
+///
+///         // Play a sound for every revealed character
+///         for _ in self.typewriter.newly_revealed() {
+///             ctx.audio("blip").play();
+///         }
+///     }
+///
+///     fn render(&mut self, ctx: Context) {
+///         ctx.text("font", "Hello [wave]world[/wave]!")
+///             .markup()
+///             .typewriter(&self.typewriter)
+///             .draw();
+///     }
+/// }
+///
+/// let typewriter = Typewriter::new("Hello [wave]world[/wave]!", 20.0).markup();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Typewriter {
+    /// Text to reveal.
+    text: String,
+    /// Whether the text is parsed as rich text markup.
+    markup: bool,
+    /// Visible characters of the text in order.
+    chars: Vec<char>,
+    /// How many characters are revealed every second.
+    chars_per_second: f32,
+    /// Extra seconds to wait after revealing specific characters.
+    pauses: Vec<(char, f32)>,
+    /// Amount of characters revealed.
+    revealed: usize,
+    /// Amount of characters revealed before the last update.
+    previously_revealed: usize,
+    /// Seconds since the last character was revealed.
+    elapsed: f32,
+}
+
+impl Typewriter {
+    /// Start revealing a text.
+    ///
+    /// By default there's a pause after punctuation, `.`, `!` & `?` wait 0.3 seconds and `,`, `;` & `:` wait 0.15 seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - Text that's drawn, must be the same as passed to [`crate::Context::text`].
+    /// * `chars_per_second` - How many characters are revealed every second.
+    #[inline]
+    #[must_use]
+    pub fn new(text: impl Into<String>, chars_per_second: f32) -> Self {
+        let text = text.into();
+        let markup = false;
+        let chars = Self::visible_chars(&text, markup);
+        let pauses = vec![
+            ('.', 0.3),
+            ('!', 0.3),
+            ('?', 0.3),
+            (',', 0.15),
+            (';', 0.15),
+            (':', 0.15),
+        ];
+
+        Self {
+            text,
+            markup,
+            chars,
+            chars_per_second,
+            pauses,
+            revealed: 0,
+            previously_revealed: 0,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Parse the text as rich text markup, must be set when [`TextContext::markup`] is used.
+    #[inline]
+    #[must_use]
+    pub fn markup(mut self) -> Self {
+        self.markup = true;
+        self.chars = Self::visible_chars(&self.text, self.markup);
 
-                // The sub rectangle offset of the character is based on the starting character and counted using the ASCII index
-                let char_offset = char_index - font.metadata.first_char;
+        self
+    }
+
+    /// Set how long to wait after a character is revealed.
+    ///
+    /// # Arguments
+    ///
+    /// * `ch` - Character to wait after, such as `'.'`.
+    /// * `seconds` - Extra seconds to wait, `0.0` removes the pause.
+    #[inline]
+    #[must_use]
+    pub fn pause_after(mut self, ch: char, seconds: f32) -> Self {
+        self.pauses.retain(|(pause_ch, _)| *pause_ch != ch);
+        if seconds > 0.0 {
+            self.pauses.push((ch, seconds));
+        }
+
+        self
+    }
+
+    /// Reveal the next characters.
+    ///
+    /// The first character is revealed immediately.
+    ///
+    /// # Arguments
+    ///
+    /// * `delta_time` - Seconds passed since the last update, usually [`crate::Context::delta_time`].
+    #[inline]
+    pub fn update(&mut self, delta_time: f32) {
+        self.previously_revealed = self.revealed;
+
+        // Reveal everything at once when there's no speed
+        if self.chars_per_second <= 0.0 {
+            self.revealed = self.chars.len();
+
+            return;
+        }
 
-                // Setup the sprite for the glyph
-                let sprite = font.sprites[char_offset];
-                let affine_matrix = sprite.affine_matrix(x, y, 0.0);
+        self.elapsed += delta_time;
+        while !self.is_finished() {
+            // Wait longer after some characters
+            let delay = self.revealed.checked_sub(1).map_or(0.0, |previous| {
+                let previous = self.chars[previous];
+                let pause = self
+                    .pauses
+                    .iter()
+                    .find(|(pause_ch, _)| *pause_ch == previous)
+                    .map_or(0.0, |(_, pause)| *pause);
 
-                // Push the graphics
-                ctx.graphics
-                    .instances
-                    .push(affine_matrix, sprite.sub_rectangle, sprite.texture);
+                pause + 1.0 / self.chars_per_second
             });
-        });
+            if self.elapsed < delay {
+                break;
+            }
+
+            self.elapsed -= delay;
+            self.revealed += 1;
+        }
+
+        // Don't build up time when everything is revealed
+        if self.is_finished() {
+            self.elapsed = 0.0;
+        }
+    }
+
+    /// Reveal all characters at once, such as when the player presses a button to skip.
+    ///
+    /// The remaining characters are not reported by [`Self::newly_revealed`].
+    #[inline]
+    pub fn skip(&mut self) {
+        self.revealed = self.chars.len();
+        self.previously_revealed = self.revealed;
+    }
+
+    /// Hide all characters and start revealing from the beginning.
+    #[inline]
+    pub fn restart(&mut self) {
+        self.revealed = 0;
+        self.previously_revealed = 0;
+        self.elapsed = 0.0;
+    }
+
+    /// Characters revealed during the last update.
+    ///
+    /// Whitespace is never revealed, inline icons are reported as [`TYPEWRITER_ICON_CHAR`].
+    ///
+    /// # Returns
+    ///
+    /// - An iterator over the characters revealed by the last call to [`Self::update`], usually used for playing a sound for each character.
+    #[inline]
+    pub fn newly_revealed(&self) -> impl Iterator<Item = char> + '_ {
+        self.chars[self.previously_revealed..self.revealed]
+            .iter()
+            .copied()
+    }
+
+    /// Amount of visible characters revealed.
+    #[inline]
+    #[must_use]
+    pub const fn revealed(&self) -> usize {
+        self.revealed
+    }
+
+    /// Whether all characters are revealed.
+    #[inline]
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.revealed >= self.chars.len()
+    }
+
+    /// Get the visible characters of the text, skipping whitespace and markup tags.
+    fn visible_chars(text: &str, markup: bool) -> Vec<char> {
+        if markup {
+            markup::parse(text)
+                .into_iter()
+                .map(|(item, _)| match item {
+                    MarkupItem::Char(ch) => ch,
+                    MarkupItem::Icon(_) => TYPEWRITER_ICON_CHAR,
+                })
+                .filter(|ch| !ch.is_whitespace())
+                .collect()
+        } else {
+            text.chars().filter(|ch| !ch.is_whitespace()).collect()
+        }
+    }
+}
+
+/// Horizontal alignment of each line of text relative to its position.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TextAlign {
+    /// Lines start at the position.
+    #[default]
+    Left,
+    /// Lines are centered on the position.
+    Center,
+    /// Lines end at the position.
+    Right,
+}
+
+impl TextAlign {
+    /// Horizontal distance from the start of a line to the position, rounded down to stay pixel aligned.
+    pub(crate) fn offset(self, line_width: f32) -> f32 {
+        match self {
+            Self::Left => 0.0,
+            Self::Center => (line_width / 2.0).floor(),
+            Self::Right => line_width,
+        }
+    }
+}
+
+/// Vertical position of a text relative to its position.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum VerticalAnchor {
+    /// The top of the first line is at the position.
+    #[default]
+    Top,
+    /// The text is centered on the position.
+    Middle,
+    /// The bottom of the last line is at the position.
+    Bottom,
+}
+
+impl VerticalAnchor {
+    /// Vertical distance from the top of the text to the position, rounded down to stay pixel aligned.
+    pub(crate) fn offset(self, height: f32) -> f32 {
+        match self {
+            Self::Top => 0.0,
+            Self::Middle => (height / 2.0).floor(),
+            Self::Bottom => height,
+        }
     }
 }
 
@@ -145,6 +783,130 @@ impl Context {
             x: 0.0,
             y: 0.0,
             text,
+            style: Style::new(),
+            max_width: f32::INFINITY,
+            align: TextAlign::Left,
+            anchor: VerticalAnchor::Top,
+            line_height: None,
+            markup: false,
+            time: 0.0,
+            reveal: None,
         }
     }
+
+    /// Get the size of a text when drawn in a single line, or multiple when it contains newlines.
+    ///
+    /// Use [`TextContext::size`] when the text is wrapped or uses a different line height.
+    ///
+    /// # Arguments
+    ///
+    /// * `font` - Asset path of the font, see [`Self`] for more information about asset loading and storing.
+    /// * `text` - Text to measure.
+    ///
+    /// # Returns
+    ///
+    /// - `(width, height)`, size of the text in pixels, trailing whitespace is not included in the width.
+    ///
+    /// # Panics
+    ///
+    /// - When asset failed loading.
+    #[inline]
+    #[must_use]
+    pub fn text_size(&self, font: &str, text: &str) -> (f32, f32) {
+        self.text(font, text).size()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn align_and_anchor_offsets() {
+        // Centered offsets are rounded down to whole pixels
+        assert_eq!(
+            [TextAlign::Left, TextAlign::Center, TextAlign::Right]
+                .map(|align| align.offset(15.0) as i32),
+            [0, 7, 15]
+        );
+        assert_eq!(
+            [
+                VerticalAnchor::Top,
+                VerticalAnchor::Middle,
+                VerticalAnchor::Bottom
+            ]
+            .map(|anchor| anchor.offset(28.0) as i32),
+            [0, 14, 28]
+        );
+    }
+
+    /// Update the typewriter and get the characters revealed by it.
+    fn step(typewriter: &mut Typewriter, delta_time: f32) -> String {
+        typewriter.update(delta_time);
+
+        typewriter.newly_revealed().collect()
+    }
+
+    #[test]
+    fn typewriter_speed() {
+        let mut typewriter = Typewriter::new("ab cd", 4.0);
+
+        // The first character is revealed immediately, whitespace is skipped
+        assert_eq!(step(&mut typewriter, 0.0), "a");
+        assert_eq!(step(&mut typewriter, 0.125), "");
+        assert_eq!(step(&mut typewriter, 0.125), "b");
+        assert_eq!(step(&mut typewriter, 0.5), "cd");
+        assert!(typewriter.is_finished());
+        assert_eq!(typewriter.revealed(), 4);
+
+        // Nothing is reported after everything is revealed
+        assert_eq!(step(&mut typewriter, 1.0), "");
+
+        // Restarting hides everything again
+        typewriter.restart();
+        assert_eq!(typewriter.revealed(), 0);
+        assert_eq!(step(&mut typewriter, 0.25), "ab");
+
+        // Skipping reveals everything without reporting it
+        typewriter.skip();
+        assert!(typewriter.is_finished());
+        assert_eq!(typewriter.newly_revealed().count(), 0);
+
+        // Without a speed everything is revealed at once
+        let mut typewriter = Typewriter::new("ab cd", 0.0);
+        assert_eq!(step(&mut typewriter, 0.0), "abcd");
+    }
+
+    #[test]
+    fn typewriter_pauses() {
+        let mut typewriter = Typewriter::new("a.b,c", 4.0)
+            .pause_after('.', 0.5)
+            .pause_after(',', 0.0);
+
+        assert_eq!(step(&mut typewriter, 0.0), "a");
+        assert_eq!(step(&mut typewriter, 0.25), ".");
+
+        // Wait longer after the period
+        assert_eq!(step(&mut typewriter, 0.5), "");
+        assert_eq!(step(&mut typewriter, 0.25), "b");
+
+        // The pause after the comma is removed
+        assert_eq!(step(&mut typewriter, 0.25), ",");
+        assert_eq!(step(&mut typewriter, 0.25), "c");
+    }
+
+    #[test]
+    fn typewriter_markup() {
+        let mut typewriter = Typewriter::new("[wave]hi[/wave] [icon=ui.coin][[", 0.0).markup();
+
+        // Tags are skipped and icons are reported as a single character
+        assert_eq!(
+            step(&mut typewriter, 0.0),
+            format!("hi{TYPEWRITER_ICON_CHAR}[")
+        );
+
+        // Without markup the tags are revealed as text
+        let mut typewriter = Typewriter::new("[wave]a", 0.0);
+        assert_eq!(step(&mut typewriter, 0.0), "[wave]a");
+    }
 }
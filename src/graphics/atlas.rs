@@ -1,71 +1,73 @@
-//! Pack textures into a single atlas to be uploaded to the GPU.
-
-use std::borrow::Cow;
+//! Pack textures into atlas pages to be uploaded to the GPU.
 
 use chuot_packer::Packer;
 
 use super::{uniform::UniformArrayState, PREFERRED_TEXTURE_FORMAT};
 
-/// Virtual packed texture size in pixels for both width and height.
+/// Packed texture size of a single atlas page in pixels for both width and height.
 pub(crate) const ATLAS_TEXTURE_SIZE: u32 = 4096;
 
-/// Index into the atlas rectangles.
+/// Ensure a texture fits in a single atlas page.
+///
+/// # Errors
+///
+/// - When the texture is wider or higher than an atlas page.
+pub(crate) fn ensure_fits_page(width: u32, height: u32) -> Result<(), String> {
+    if width > ATLAS_TEXTURE_SIZE || height > ATLAS_TEXTURE_SIZE {
+        return Err(format!(
+            "texture of {width}x{height} pixels is bigger than an atlas page of {ATLAS_TEXTURE_SIZE}x{ATLAS_TEXTURE_SIZE} pixels"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Reference to a texture in the atlas.
 pub(crate) type TextureRef = u16;
 
+/// Where a texture is packed in the atlas.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TextureSlot {
+    /// Index of the page.
+    pub(crate) page: u16,
+    /// Index into the rectangles of the page, passed to the shader.
+    pub(crate) index: u16,
+}
+
 /// A static packed atlas at compile time by the proc macro.
 ///
 /// Will be unpacked and uploaded to the GPU once at the beginning of the game.
+///
+/// When a texture doesn't fit anymore a new page is added, every page is a separate texture with its own bind group.
+/// Separate textures are used instead of a texture array because an array can't grow without copying all pages, and WebGL2 only supports a few array layers.
+/// Every page also has its own uniform array of texture rectangles, because WebGL2 limits the size of a single uniform to 1024 rectangles.
 pub struct Atlas {
-    /// GPU reference.
-    pub(crate) texture: wgpu::Texture,
-    /// GPU bind group.
-    pub(crate) bind_group: wgpu::BindGroup,
-    /// GPU bind group layout.
+    /// Separately packed textures, always contains at least one item.
+    pub(crate) pages: Vec<AtlasPage>,
+    /// GPU bind group layout, shared by all pages.
     pub(crate) bind_group_layout: wgpu::BindGroupLayout,
-    /// GPU uniform buffer holding all atlassed texture rectangles.
-    ///
-    /// Index of this array is used as the texture reference.
-    /// Because of that all static embedded sprites must be preallocated.
-    pub(crate) rects: UniformArrayState<[f32; 4]>,
+    /// GPU sampler, shared by all pages.
+    sampler: wgpu::Sampler,
     /// In-memory textures to receive the pixels from retroactively.
     #[cfg(feature = "read-texture")]
     pub(crate) textures: hashbrown::HashMap<TextureRef, Vec<u32>>,
-    /// Packer algorithm used.
-    packer: Packer,
+    /// Where each texture is packed, by texture reference.
+    ///
+    /// The references of static embedded sprites are reserved at the start.
+    texture_slots: Vec<TextureSlot>,
+    /// Amount of textures embedded in the binary, they can't be removed.
+    static_textures: usize,
+    /// References of removed textures that can be used again.
+    free_texture_refs: Vec<TextureRef>,
+    /// Textures of an asset that's being loaded again.
+    replacement: Option<Replacement>,
 }
 
 impl Atlas {
     /// Create and upload the atlas to the GPU.
     ///
-    /// Preallocate the embedded rectangles so the references can't be duplicated.
-    pub(crate) fn new(
-        preallocate_textures: usize,
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
-    ) -> Self {
-        // Create the texture on the GPU
-        let texture = device.create_texture(&wgpu::TextureDescriptor {
-            label: Some(&Cow::Borrowed("Static Texture Atlas")),
-            size: wgpu::Extent3d {
-                width: ATLAS_TEXTURE_SIZE,
-                height: ATLAS_TEXTURE_SIZE,
-                // TODO: support multiple layers
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            // Texture is 2D
-            dimension: wgpu::TextureDimension::D2,
-            // Use sRGB format
-            format: PREFERRED_TEXTURE_FORMAT,
-            // We want to use this texture in shaders and we want to copy data to it
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            // We only need a single format
-            view_formats: &[PREFERRED_TEXTURE_FORMAT],
-        });
-
-        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-
+    /// Preallocate the embedded texture references so they can't be duplicated.
+    pub(crate) fn new(preallocate_textures: usize, device: &wgpu::Device) -> Self {
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             label: Some("Static Texture Atlas Sampler"),
             address_mode_u: wgpu::AddressMode::ClampToEdge,
@@ -99,36 +101,30 @@ impl Atlas {
             ],
         });
 
-        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Static Texture Atlas Bind Group"),
-            layout: &bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&texture_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&sampler),
-                },
-            ],
-        });
-
-        // Setup a new atlas packer
-        let packer = Packer::new((
-            const { ATLAS_TEXTURE_SIZE as u16 },
-            const { ATLAS_TEXTURE_SIZE as u16 },
-        ));
+        // Start with a single page, all other pages share the layout of its rectangles
+        let rects = UniformArrayState::new(device);
+        let pages = vec![AtlasPage::new(
+            0,
+            rects,
+            device,
+            &bind_group_layout,
+            &sampler,
+        )];
 
-        // Create and upload the uniforms
-        let rects = UniformArrayState::new(preallocate_textures, device, queue);
+        // Only textures uploaded at runtime can be removed
+        let texture_slots = vec![TextureSlot::default(); preallocate_textures];
+        let static_textures = preallocate_textures;
+        let free_texture_refs = Vec::new();
+        let replacement = None;
 
         Self {
-            texture,
-            bind_group,
+            pages,
             bind_group_layout,
-            rects,
-            packer,
+            sampler,
+            texture_slots,
+            static_textures,
+            free_texture_refs,
+            replacement,
             #[cfg(feature = "read-texture")]
             textures: hashbrown::HashMap::new(),
         }
@@ -139,57 +135,123 @@ impl Atlas {
     /// # Returns
     ///
     /// - An unique identification number for the texture to be passed along with the vertices.
+    ///
+    /// # Panics
+    ///
+    /// - When the texture is bigger than an atlas page.
+    /// - When the maximum amount of textures is reached.
     pub(crate) fn add_texture(
         &mut self,
         width: u32,
         height: u32,
         pixels: &[u32],
+        device: &wgpu::Device,
         queue: &wgpu::Queue,
     ) -> TextureRef {
+        // Overwrite a texture of the asset being replaced when it has the same size
+        if let Some(texture_ref) = self.take_replaceable_texture(width, height) {
+            let [x, y, _, _] = self.rect(texture_ref);
+            self.update_pixels_raw_offset(
+                self.slot(texture_ref).page,
+                (x as u32, y as u32, width, height),
+                pixels,
+                queue,
+            );
+
+            // Keep the pixels in memory
+            #[cfg(feature = "read-texture")]
+            self.textures.insert(texture_ref, pixels.to_vec());
+
+            return texture_ref;
+        }
+
         // Pack the rectangle
-        let (x, y) = self
-            .packer
-            .insert((width as u16, height as u16))
-            .expect("New texture could not be packed, not enough space");
-        let x = x as u32;
-        let y = y as u32;
+        let (slot, x, y) = self.pack(width, height, device, queue);
 
         // Write the sub-texture to the atlas location
-        queue.write_texture(
-            // Where to copy the pixel data
-            wgpu::ImageCopyTexture {
-                texture: &self.texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d { x, y, z: 0 },
-                aspect: wgpu::TextureAspect::All,
-            },
-            // Actual pixel data
-            bytemuck::cast_slice(pixels),
-            // Layout of the texture
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * width),
-                rows_per_image: Some(height),
-            },
-            // Texture size
-            wgpu::Extent3d {
-                width,
-                height,
-                depth_or_array_layers: 1,
-            },
-        );
+        self.update_pixels_raw_offset(slot.page, (x, y, width, height), pixels, queue);
 
-        // Push the newly packed dimensions to the uniform buffer, returning the reference to it
-        let uniform_index = self
-            .rects
-            .push(&[x as f32, y as f32, width as f32, height as f32], queue);
+        // Reuse the reference of a removed texture if possible
+        let texture_ref = self.free_texture_refs.pop().unwrap_or_else(|| {
+            TextureRef::try_from(self.texture_slots.len()).unwrap_or_else(|_| {
+                panic!(
+                    "New texture could not be added, the atlas can't contain more than {} textures",
+                    TextureRef::MAX as usize + 1
+                )
+            })
+        });
+        self.set_slot(texture_ref, slot);
+
+        // Remember the new texture so it can be removed again when the replacement fails
+        if let Some(replacement) = &mut self.replacement {
+            replacement.new.push(texture_ref);
+        }
 
         // Keep the pixels in memory
         #[cfg(feature = "read-texture")]
-        self.textures
-            .insert(uniform_index as TextureRef, pixels.to_vec());
+        self.textures.insert(texture_ref, pixels.to_vec());
 
-        uniform_index as TextureRef
+        texture_ref
+    }
+
+    /// Remove a texture from the atlas so its space and reference can be used by new textures.
+    ///
+    /// Textures embedded in the binary are never removed.
+    pub(crate) fn remove_texture(&mut self, texture_ref: TextureRef) {
+        if (texture_ref as usize) < self.static_textures
+            || self.free_texture_refs.contains(&texture_ref)
+        {
+            return;
+        }
+
+        // Free the packed space and the rectangle
+        let TextureSlot { page, index } = self.slot(texture_ref);
+        self.pages[page as usize].remove(index);
+
+        self.free_texture_refs.push(texture_ref);
+
+        // Forget the pixels
+        #[cfg(feature = "read-texture")]
+        self.textures.remove(&texture_ref);
+    }
+
+    /// Start loading an asset again, uploaded textures with the same size as one of its old textures overwrite it in place.
+    ///
+    /// Must be followed by [`Self::finish_replacing`].
+    pub(crate) fn start_replacing(&mut self, old_textures: Vec<TextureRef>) {
+        self.replacement = Some(Replacement {
+            old: old_textures,
+            new: Vec::new(),
+        });
+    }
+
+    /// Finish loading an asset again.
+    ///
+    /// When the asset is replaced its old textures that are not overwritten are removed, otherwise the newly uploaded textures are removed.
+    pub(crate) fn finish_replacing(&mut self, replaced: bool) {
+        let Some(Replacement { old, new }) = self.replacement.take() else {
+            return;
+        };
+
+        for texture_ref in if replaced { old } else { new } {
+            self.remove_texture(texture_ref);
+        }
+    }
+
+    /// Take an old texture of the asset being replaced with the same size.
+    fn take_replaceable_texture(&mut self, width: u32, height: u32) -> Option<TextureRef> {
+        let index = self
+            .replacement
+            .as_ref()?
+            .old
+            .iter()
+            .position(|texture_ref| {
+                let [_, _, old_width, old_height] = self.rect(*texture_ref);
+
+                old_width as u32 == width && old_height as u32 == height
+            })?;
+
+        Some(self.replacement.as_mut()?.old.swap_remove(index))
     }
 
     /// Add an empty texture to the atlas.
@@ -203,22 +265,12 @@ impl Atlas {
         texture_ref: TextureRef,
         width: u32,
         height: u32,
+        device: &wgpu::Device,
         queue: &wgpu::Queue,
     ) {
         // Pack the rectangle
-        let (x, y) = self
-            .packer
-            .insert((width as u16, height as u16))
-            .expect("New texture could not be packed, not enough space");
-        let x = x as u32;
-        let y = y as u32;
-
-        // Push the newly packed dimensions to the uniform buffer, returning the reference to it
-        self.rects.set(
-            texture_ref as usize,
-            &[x as f32, y as f32, width as f32, height as f32],
-            queue,
-        );
+        let (slot, _, _) = self.pack(width, height, device, queue);
+        self.set_slot(texture_ref, slot);
 
         // Keep the pixels in memory
         #[cfg(feature = "read-texture")]
@@ -236,7 +288,7 @@ impl Atlas {
     ) {
         // Get the region in the atlas for the already pushed sprite
         let [sprite_region_x, sprite_region_y, sprite_region_width, sprite_region_height] =
-            self.rects[texture_ref as usize];
+            self.rect(texture_ref);
 
         let x = x.round() as u32;
         let y = y.round() as u32;
@@ -255,7 +307,12 @@ impl Atlas {
         let atlas_y = y + sprite_region_y;
 
         // Convert to u32 with proper rounding
-        self.update_pixels_raw_offset((atlas_x, atlas_y, width, height), pixels, queue);
+        self.update_pixels_raw_offset(
+            self.slot(texture_ref).page,
+            (atlas_x, atlas_y, width, height),
+            pixels,
+            queue,
+        );
 
         // Update the local texture
         #[cfg(feature = "read-texture")]
@@ -274,9 +331,88 @@ impl Atlas {
         }
     }
 
+    /// Where a texture is packed.
+    pub(crate) fn slot(&self, texture_ref: TextureRef) -> TextureSlot {
+        self.texture_slots
+            .get(texture_ref as usize)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Store where a texture is packed.
+    fn set_slot(&mut self, texture_ref: TextureRef, slot: TextureSlot) {
+        let index = texture_ref as usize;
+        if self.texture_slots.len() <= index {
+            self.texture_slots.resize(index + 1, TextureSlot::default());
+        }
+
+        self.texture_slots[index] = slot;
+    }
+
+    /// Packed `(x, y, width, height)` rectangle of a texture in its page.
+    fn rect(&self, texture_ref: TextureRef) -> [f32; 4] {
+        let TextureSlot { page, index } = self.slot(texture_ref);
+
+        self.pages[page as usize].rects[index as usize]
+    }
+
+    /// Pack a rectangle in the first page it fits in, adding a new page when it doesn't fit in any.
+    ///
+    /// # Returns
+    ///
+    /// - Tuple of the slot and the offset in the page.
+    ///
+    /// # Panics
+    ///
+    /// - When the rectangle is bigger than a page.
+    fn pack(
+        &mut self,
+        width: u32,
+        height: u32,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> (TextureSlot, u32, u32) {
+        // Try the existing pages first
+        if let Some((page, (index, x, y))) = self
+            .pages
+            .iter_mut()
+            .enumerate()
+            .find_map(|(page, atlas_page)| Some((page, atlas_page.insert(width, height, queue)?)))
+        {
+            let slot = TextureSlot {
+                page: page as u16,
+                index,
+            };
+
+            return (slot, x, y);
+        }
+
+        if let Err(err) = ensure_fits_page(width, height) {
+            panic!("New texture could not be packed, {err}");
+        }
+
+        // All pages are full, add a new one
+        let page = self.pages.len();
+        let rects = self.pages[0].rects.new_sharing_layout(device);
+        let mut atlas_page =
+            AtlasPage::new(page, rects, device, &self.bind_group_layout, &self.sampler);
+        let (index, x, y) = atlas_page
+            .insert(width, height, queue)
+            .expect("New texture could not be packed in an empty atlas page");
+        self.pages.push(atlas_page);
+
+        let slot = TextureSlot {
+            page: page as u16,
+            index,
+        };
+
+        (slot, x, y)
+    }
+
     /// Update a region of pixels of the texture in the atlas.
     fn update_pixels_raw_offset(
         &self,
+        page: u16,
         (x, y, width, height): (u32, u32, u32, u32),
         pixels: &[u32],
         queue: &wgpu::Queue,
@@ -285,7 +421,7 @@ impl Atlas {
         queue.write_texture(
             // Where to copy the pixel data
             wgpu::ImageCopyTexture {
-                texture: &self.texture,
+                texture: &self.pages[page as usize].texture,
                 mip_level: 0,
                 origin: wgpu::Origin3d { x, y, z: 0 },
                 aspect: wgpu::TextureAspect::All,
@@ -307,3 +443,149 @@ impl Atlas {
         );
     }
 }
+
+/// Single texture of the atlas with its own packed space.
+pub(crate) struct AtlasPage {
+    /// GPU reference.
+    texture: wgpu::Texture,
+    /// GPU bind group.
+    pub(crate) bind_group: wgpu::BindGroup,
+    /// GPU uniform buffer holding the rectangles of all textures in this page.
+    ///
+    /// Index of this array is passed to the shader with every instance.
+    pub(crate) rects: UniformArrayState<[f32; 4]>,
+    /// Indices of the rectangles of removed textures that can be used again.
+    free_rects: Vec<u16>,
+    /// Packer algorithm used.
+    packer: Packer,
+}
+
+impl AtlasPage {
+    /// Create the empty texture of a page on the GPU.
+    fn new(
+        index: usize,
+        rects: UniformArrayState<[f32; 4]>,
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+    ) -> Self {
+        // Create the texture on the GPU
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(&format!("Texture Atlas Page {index}")),
+            size: wgpu::Extent3d {
+                width: ATLAS_TEXTURE_SIZE,
+                height: ATLAS_TEXTURE_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            // Texture is 2D
+            dimension: wgpu::TextureDimension::D2,
+            // Use sRGB format
+            format: PREFERRED_TEXTURE_FORMAT,
+            // We want to use this texture in shaders and we want to copy data to it
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            // We only need a single format
+            view_formats: &[PREFERRED_TEXTURE_FORMAT],
+        });
+
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some(&format!("Texture Atlas Page {index} Bind Group")),
+            layout: bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        });
+
+        // Setup a new packer for the page
+        let packer = Packer::new((
+            const { ATLAS_TEXTURE_SIZE as u16 },
+            const { ATLAS_TEXTURE_SIZE as u16 },
+        ));
+
+        // No textures are removed yet
+        let free_rects = Vec::new();
+
+        Self {
+            texture,
+            bind_group,
+            rects,
+            free_rects,
+            packer,
+        }
+    }
+
+    /// Pack a rectangle and store it in the uniform.
+    ///
+    /// # Returns
+    ///
+    /// - Tuple of the index of the rectangle and the offset in the page, `None` when there's no space or no rectangle left.
+    fn insert(&mut self, width: u32, height: u32, queue: &wgpu::Queue) -> Option<(u16, u32, u32)> {
+        // Textures bigger than a page can't be packed
+        let packed_width = u16::try_from(width).ok()?;
+        let packed_height = u16::try_from(height).ok()?;
+        let (x, y) = self.packer.insert((packed_width, packed_height))?;
+        let rect = [f32::from(x), f32::from(y), width as f32, height as f32];
+
+        // Reuse the rectangle of a removed texture if possible
+        let index = if let Some(index) = self.free_rects.pop() {
+            self.rects.set(index as usize, &rect, queue);
+
+            index
+        } else {
+            let Ok(index) = self.rects.push(&rect, queue) else {
+                // All rectangles are used, free the space again so another page is tried
+                self.packer.remove((x, y, packed_width, packed_height));
+
+                return None;
+            };
+
+            index as u16
+        };
+
+        Some((index, u32::from(x), u32::from(y)))
+    }
+
+    /// Free the packed space and the rectangle of a texture.
+    fn remove(&mut self, index: u16) {
+        let [x, y, width, height] = self.rects[index as usize];
+        self.packer
+            .remove((x as u16, y as u16, width as u16, height as u16));
+
+        self.free_rects.push(index);
+    }
+}
+
+/// Textures of an asset that's being loaded again.
+struct Replacement {
+    /// Textures of the previous version that are not overwritten yet.
+    old: Vec<TextureRef>,
+    /// Textures uploaded for the new version that are not overwriting old ones.
+    new: Vec<TextureRef>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ensure_fits_page_size() {
+        // Textures up to the size of a page fit
+        ensure_fits_page(1, 1).unwrap();
+        ensure_fits_page(ATLAS_TEXTURE_SIZE, ATLAS_TEXTURE_SIZE).unwrap();
+
+        // A single pixel more in either direction doesn't
+        ensure_fits_page(ATLAS_TEXTURE_SIZE + 1, 1).unwrap_err();
+        ensure_fits_page(1, ATLAS_TEXTURE_SIZE + 1).unwrap_err();
+        ensure_fits_page(u32::from(u16::MAX) + 1, 1).unwrap_err();
+    }
+}
@@ -0,0 +1,80 @@
+//! 2D camera mapping world coordinates to buffer coordinates.
+
+use glam::{Affine2, Vec2};
+
+/// 2D camera state.
+///
+/// The default camera maps world coordinates directly to viewport coordinates.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Camera {
+    /// World coordinate shown at the left top of the viewport when not zoomed or rotated.
+    pub(crate) position: (f32, f32),
+    /// Integer zoom factor, zooming happens around the center of the viewport.
+    pub(crate) zoom: u32,
+    /// Rotation in radians around the center of the viewport.
+    pub(crate) rotation: f32,
+    /// Offset applied after clamping to the bounds, used for screen shake effects.
+    pub(crate) shake: (f32, f32),
+    /// World rectangle `(x, y, width, height)` the view is kept inside.
+    pub(crate) bounds: Option<(f32, f32, f32, f32)>,
+}
+
+impl Camera {
+    /// Camera without any transformation.
+    pub(crate) const fn new() -> Self {
+        Self {
+            position: (0.0, 0.0),
+            zoom: 1,
+            rotation: 0.0,
+            shake: (0.0, 0.0),
+            bounds: None,
+        }
+    }
+
+    /// Transformation from world coordinates to coordinates relative to the viewport.
+    ///
+    /// The translation is rounded so everything stays aligned to whole pixels.
+    pub(crate) fn transformation(&self, viewport_size: (f32, f32)) -> Affine2 {
+        let half_viewport_size = Vec2::from(viewport_size) / 2.0;
+
+        // Position the view inside the bounds and add the shake afterwards so it's still visible at the edges
+        let position = self.clamped_position(half_viewport_size) + Vec2::from(self.shake);
+
+        // Move the world to the left top, then zoom and rotate around the center of the viewport
+        let mut transformation = Affine2::from_translation(half_viewport_size)
+            * Affine2::from_scale_angle_translation(
+                Vec2::splat(self.zoom.max(1) as f32),
+                -self.rotation,
+                Vec2::ZERO,
+            )
+            * Affine2::from_translation(-position - half_viewport_size);
+        transformation.translation = transformation.translation.round();
+
+        transformation
+    }
+
+    /// Position of the left top of the view, moved so the view doesn't leave the bounds.
+    fn clamped_position(&self, half_viewport_size: Vec2) -> Vec2 {
+        let position = Vec2::from(self.position);
+
+        let Some((x, y, width, height)) = self.bounds else {
+            return position;
+        };
+
+        // Half of the size of the world that's visible, used to clamp the center of the view
+        let half_view_size = half_viewport_size / self.zoom.max(1) as f32;
+
+        let min = Vec2::new(x, y) + half_view_size;
+        let max = Vec2::new(x + width, y + height) - half_view_size;
+        let center = Vec2::new(x + width / 2.0, y + height / 2.0);
+
+        // Center the view when the bounds are smaller than the view
+        let clamped = Vec2::select(
+            min.cmpgt(max),
+            center,
+            (position + half_viewport_size).max(min).min(max),
+        );
+
+        clamped - half_viewport_size
+    }
+}
@@ -1,6 +1,7 @@
 //! Data types that can be send to the GPU.
 
 use bytemuck::{Pod, Zeroable};
+use glam::Affine2;
 use wgpu::{VertexAttribute, VertexBufferLayout, VertexFormat, VertexStepMode};
 
 /// WGPU attributes.
@@ -58,6 +59,20 @@ pub(crate) struct ScreenInfo {
     pub buffer_width: f32,
     /// Output buffer height.
     pub buffer_height: f32,
-    /// Unused data for padding.
-    pub _padding: u64,
+    /// Translation of the camera.
+    pub camera_translation: [f32; 2],
+    /// Rotation and zoom of the camera.
+    pub camera_matrix: [[f32; 2]; 2],
+}
+
+impl ScreenInfo {
+    /// Construct from the buffer size and the camera transformation.
+    pub(crate) fn new(buffer_width: f32, buffer_height: f32, camera: Affine2) -> Self {
+        Self {
+            buffer_width,
+            buffer_height,
+            camera_translation: camera.translation.into(),
+            camera_matrix: camera.matrix2.to_cols_array_2d(),
+        }
+    }
 }
@@ -1,9 +1,11 @@
 //! Type for exposing instancing functionality in the [`crate::graphics::Render`] trait.
 
+use std::ops::Range;
+
 use bytemuck::{Pod, Zeroable};
 use glam::Affine2;
 
-use super::atlas::TextureRef;
+use super::atlas::{TextureRef, TextureSlot};
 
 /// WGPU attributes.
 const ATTRIBUTES: &[wgpu::VertexAttribute] = &[
@@ -25,11 +27,151 @@ const ATTRIBUTES: &[wgpu::VertexAttribute] = &[
     },
     wgpu::VertexAttribute {
         format: wgpu::VertexFormat::Uint32,
-        offset: std::mem::offset_of!(Instance, texture_ref) as u64,
+        offset: std::mem::offset_of!(Instance, texture_index) as u64,
         shader_location: 5,
     },
+    wgpu::VertexAttribute {
+        format: wgpu::VertexFormat::Unorm8x4,
+        offset: std::mem::offset_of!(Instance, tint) as u64,
+        shader_location: 6,
+    },
+    wgpu::VertexAttribute {
+        format: wgpu::VertexFormat::Unorm8x4,
+        offset: std::mem::offset_of!(Instance, flash) as u64,
+        shader_location: 7,
+    },
+    wgpu::VertexAttribute {
+        format: wgpu::VertexFormat::Uint32,
+        offset: std::mem::offset_of!(Instance, screen_space) as u64,
+        shader_location: 8,
+    },
 ];
 
+/// How the pixels of a drawn sprite or text are combined with the pixels already drawn below it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum BlendMode {
+    /// Draw on top, transparent pixels show what's below.
+    #[default]
+    Alpha,
+    /// Add the colors to the colors below, useful for lights, glows and fire.
+    Additive,
+    /// Multiply the colors with the colors below, useful for shadows and darkening.
+    Multiply,
+    /// Inverse multiply of the colors with the colors below, brightens without blowing out like [`BlendMode::Additive`].
+    Screen,
+}
+
+impl BlendMode {
+    /// All blend modes, used for creating a render pipeline for each.
+    pub(crate) const ALL: [Self; 4] = [Self::Alpha, Self::Additive, Self::Multiply, Self::Screen];
+
+    /// Index into the render pipelines.
+    pub(crate) const fn index(self) -> usize {
+        match self {
+            Self::Alpha => 0,
+            Self::Additive => 1,
+            Self::Multiply => 2,
+            Self::Screen => 3,
+        }
+    }
+
+    /// GPU blend state.
+    ///
+    /// The fragment shader outputs colors with premultiplied alpha.
+    pub(crate) const fn blend_state(self) -> wgpu::BlendState {
+        let color = match self {
+            Self::Alpha => wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+            Self::Additive => wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            Self::Multiply => wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::Dst,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+            Self::Screen => wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::OneMinusSrc,
+                operation: wgpu::BlendOperation::Add,
+            },
+        };
+
+        wgpu::BlendState {
+            color,
+            alpha: wgpu::BlendComponent::OVER,
+        }
+    }
+}
+
+/// Color, blending and ordering properties of an instance.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Style {
+    /// Color multiplied with each pixel in `0xAARRGGBB` format.
+    pub(crate) tint: u32,
+    /// Solid color mixed with each pixel in `0xAARRGGBB` format, alpha is the amount.
+    pub(crate) flash: u32,
+    /// How the pixels are combined with the pixels below.
+    pub(crate) blend_mode: BlendMode,
+    /// Index of the named layer, lower layers are drawn first.
+    pub(crate) layer: u16,
+    /// Order within the layer, lower values are drawn first.
+    pub(crate) z: i32,
+    /// Whether to ignore the camera and draw directly in buffer coordinates.
+    pub(crate) screen_space: bool,
+}
+
+impl Style {
+    /// Default style without any color changes, alpha blending and drawn on the first layer.
+    pub(crate) const fn new() -> Self {
+        Self {
+            tint: 0xFFFF_FFFF,
+            flash: 0,
+            blend_mode: BlendMode::Alpha,
+            layer: 0,
+            z: 0,
+            screen_space: false,
+        }
+    }
+
+    /// Key to sort the instances on before drawing.
+    const fn order(&self) -> (u16, i32) {
+        (self.layer, self.z)
+    }
+
+    /// Multiply the tint with another color.
+    pub(crate) fn tint(&mut self, argb: u32) {
+        // Multiply each color component separately
+        self.tint = u32::from_le_bytes(std::array::from_fn(|index| {
+            let old = (self.tint >> (index * 8)) & 0xFF;
+            let new = (argb >> (index * 8)) & 0xFF;
+
+            (old * new / 0xFF) as u8
+        }));
+    }
+
+    /// Multiply the alpha component of the tint.
+    pub(crate) fn alpha(&mut self, alpha: f32) {
+        let old = (self.tint >> 24) as f32;
+        let new = (old * alpha.clamp(0.0, 1.0)).round() as u32;
+
+        self.tint = (self.tint & 0x00FF_FFFF) | (new << 24);
+    }
+
+    /// Set the color to flash.
+    pub(crate) fn flash(&mut self, rgb: u32, amount: f32) {
+        let amount = (amount.clamp(0.0, 1.0) * 255.0).round() as u32;
+
+        self.flash = (rgb & 0x00FF_FFFF) | (amount << 24);
+    }
+}
+
 /// Raw representation of the instance type send to the GPU.
 #[repr(C, align(16))]
 #[derive(Debug, Default, Copy, Clone, Pod, Zeroable)]
@@ -40,10 +182,20 @@ struct Instance {
     translation: [f32; 2],
     /// Rectangle within the texture to render.
     sub_rectangle: [f32; 4],
-    /// Texture to render.
+    /// Color multiplied with each pixel.
+    tint: u32,
+    /// Solid color mixed with each pixel.
+    flash: u32,
+    /// `1` when the camera must be ignored, `0` otherwise.
+    screen_space: u32,
+    /// Index of the rectangle of the texture in the uniform of its atlas page.
+    texture_index: u32,
+    /// Texture to render, not used by the shader.
     texture_ref: TextureRef,
+    /// Atlas page of the texture, not used by the shader.
+    page: u16,
     /// Empty padding.
-    _padding: [u8; 6],
+    _padding: [u8; 4],
 }
 
 impl Instance {
@@ -52,27 +204,56 @@ impl Instance {
         transformation: Affine2,
         sub_rectangle: (f32, f32, f32, f32),
         texture_ref: TextureRef,
+        style: Style,
     ) -> Self {
         let matrix = transformation.matrix2.to_cols_array_2d();
         let translation = transformation.translation.into();
         let sub_rectangle = sub_rectangle.into();
+        let Style {
+            tint,
+            flash,
+            screen_space,
+            ..
+        } = style;
 
         Self {
             matrix,
             translation,
             sub_rectangle,
+            tint,
+            flash,
+            screen_space: u32::from(screen_space),
             texture_ref,
             ..Default::default()
         }
     }
 }
 
+/// Consecutive range of instances sharing the same draw order, blend mode and space.
+#[derive(Debug, Clone)]
+struct Batch {
+    /// Layer and z-index, lower values are drawn first.
+    order: (u16, i32),
+    /// How the pixels are combined with the pixels below.
+    blend_mode: BlendMode,
+    /// Whether the instances ignore the camera, these are drawn once instead of for every viewport.
+    screen_space: bool,
+    /// Instances in this batch.
+    range: Range<u32>,
+}
+
 /// Raw instance data.
 ///
 /// Shouldn't be used directly outside of the internal rendering code.
-#[repr(C)]
 #[derive(Debug, Default, Clone)]
-pub(crate) struct Instances(Vec<Instance>);
+pub(crate) struct Instances {
+    /// All instances in the order they are drawn.
+    instances: Vec<Instance>,
+    /// Consecutive ranges of instances sharing the same draw order, blend mode and space.
+    ///
+    /// Each blend mode range is drawn with a separate draw call, in the common case of only using a single blend mode this will only contain a single item.
+    batches: Vec<Batch>,
+}
 
 impl Instances {
     /// Push an instance to draw this frame.
@@ -81,40 +262,175 @@ impl Instances {
         transformation: Affine2,
         sub_rectangle: (f32, f32, f32, f32),
         texture_ref: TextureRef,
+        style: Style,
     ) {
-        self.0
-            .push(Instance::new(transformation, sub_rectangle, texture_ref));
+        self.instances.push(Instance::new(
+            transformation,
+            sub_rectangle,
+            texture_ref,
+            style,
+        ));
+
+        self.extend_batch(style);
     }
 
     /// Push an iterator of instances to draw this frame.
     pub(crate) fn extend(
         &mut self,
         items: impl Iterator<Item = (Affine2, (f32, f32, f32, f32), TextureRef)>,
+        style: Style,
     ) {
-        self.0
+        let previous_len = self.instances.len();
+
+        self.instances
             .extend(items.map(|(transformation, sub_rectangle, texture_ref)| {
-                Instance::new(transformation, sub_rectangle, texture_ref)
+                Instance::new(transformation, sub_rectangle, texture_ref, style)
             }));
+
+        // Only add a batch when something is pushed
+        if self.instances.len() > previous_len {
+            self.extend_batch(style);
+        }
+    }
+
+    /// Sort the instances by layer and z-index.
+    ///
+    /// Instances on the same layer and z-index keep the order they were pushed in.
+    pub(crate) fn sort(&mut self) {
+        // Nothing needs to happen when everything is already pushed in order, which is the common case
+        if self
+            .batches
+            .iter()
+            .zip(self.batches.iter().skip(1))
+            .all(|(previous, next)| previous.order <= next.order)
+        {
+            return;
+        }
+
+        // Stable sort, so the push order within each layer is preserved
+        let mut batches = std::mem::take(&mut self.batches);
+        batches.sort_by_key(|batch| batch.order);
+
+        // Copy the instances of each batch in the sorted order
+        let mut sorted = Vec::with_capacity(self.instances.len());
+        for batch in batches {
+            let start = sorted.len() as u32;
+            sorted.extend_from_slice(
+                &self.instances[batch.range.start as usize..batch.range.end as usize],
+            );
+            let end = sorted.len() as u32;
+
+            // After sorting only the blend mode and space matter for merging batches
+            match self.batches.last_mut() {
+                Some(last)
+                    if last.blend_mode == batch.blend_mode
+                        && last.screen_space == batch.screen_space =>
+                {
+                    last.order = batch.order;
+                    last.range.end = end;
+                }
+                _ => self.batches.push(Batch {
+                    range: start..end,
+                    ..batch
+                }),
+            }
+        }
+
+        self.instances = sorted;
+    }
+
+    /// Point every instance to the rectangle of its texture in the uniform of the atlas page it's packed in.
+    ///
+    /// Must be called before [`Self::batches`] and [`Self::bytes`].
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - Get where a texture is packed in the atlas.
+    pub(crate) fn resolve_textures(&mut self, slot: impl Fn(TextureRef) -> TextureSlot) {
+        for instance in &mut self.instances {
+            let TextureSlot { page, index } = slot(instance.texture_ref);
+
+            instance.page = page;
+            instance.texture_index = u32::from(index);
+        }
+    }
+
+    /// Draw batches of instances with the same blend mode and atlas page, in the order they must be drawn.
+    ///
+    /// # Arguments
+    ///
+    /// * `screen_space` - Only get the instances ignoring the camera when `true`, or only the instances in the world when `false`.
+    pub(crate) fn batches(
+        &self,
+        screen_space: bool,
+    ) -> impl Iterator<Item = (BlendMode, usize, Range<u32>)> + '_ {
+        self.batches
+            .iter()
+            .filter(move |batch| batch.screen_space == screen_space)
+            .flat_map(move |batch| {
+                let mut start = batch.range.start;
+
+                // Split the batch every time the page changes
+                std::iter::from_fn(move || {
+                    if start >= batch.range.end {
+                        return None;
+                    }
+
+                    let batch_page = self.instances[start as usize].page;
+                    let end = (start + 1..batch.range.end)
+                        .find(|index| self.instances[*index as usize].page != batch_page)
+                        .unwrap_or(batch.range.end);
+
+                    let range = start..end;
+                    start = end;
+
+                    Some((batch.blend_mode, usize::from(batch_page), range))
+                })
+            })
+    }
+
+    /// Grow the last batch to include all instances, or start a new one when the blend mode, space or draw order changes.
+    fn extend_batch(&mut self, style: Style) {
+        let end = self.instances.len() as u32;
+        let order = style.order();
+        let blend_mode = style.blend_mode;
+        let screen_space = style.screen_space;
+
+        match self.batches.last_mut() {
+            Some(last)
+                if last.order == order
+                    && last.blend_mode == blend_mode
+                    && last.screen_space == screen_space =>
+            {
+                last.range.end = end;
+            }
+            last => {
+                let start = last.map_or(0, |last| last.range.end);
+
+                self.batches.push(Batch {
+                    order,
+                    blend_mode,
+                    screen_space,
+                    range: start..end,
+                });
+            }
+        }
     }
 
     /// Remove all items.
     pub(crate) fn clear(&mut self) {
-        self.0.clear();
+        self.instances.clear();
+        self.batches.clear();
     }
 
     /// Get as raw bytes.
     pub(crate) fn bytes(&self) -> &[u8] {
-        bytemuck::cast_slice(&self.0)
-    }
-
-    /// Amount of instances to draw this frame.
-    pub(crate) fn len(&self) -> usize {
-        self.0.len()
+        bytemuck::cast_slice(&self.instances)
     }
 
     /// Whether there are any.
     pub(crate) fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.instances.is_empty()
     }
 
     /// WGPU descriptor.
@@ -126,3 +442,157 @@ impl Instances {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Push an instance of a texture with a style.
+    fn push(instances: &mut Instances, texture_ref: TextureRef, style: Style) {
+        instances.push(Affine2::IDENTITY, (0.0, 0.0, 1.0, 1.0), texture_ref, style);
+    }
+
+    #[test]
+    fn style_colors() {
+        // Multiplying with white keeps the color, each component is multiplied separately
+        let mut style = Style::new();
+        style.tint(0x80FF_8040);
+        assert_eq!(style.tint, 0x80FF_8040);
+        style.tint(0xFF80_FFFF);
+        assert_eq!(style.tint, 0x8080_8040);
+
+        // Alpha only changes the highest byte
+        style.alpha(0.5);
+        assert_eq!(style.tint, 0x4080_8040);
+        style.alpha(2.0);
+        assert_eq!(style.tint, 0x4080_8040);
+
+        // Flash amount replaces the alpha of the color
+        style.flash(0xFF12_3456, 0.5);
+        assert_eq!(style.flash, 0x8012_3456);
+        style.flash(0x0012_3456, 2.0);
+        assert_eq!(style.flash, 0xFF12_3456);
+
+        // The packed colors are sent to the GPU as is
+        let instance = Instance::new(Affine2::IDENTITY, (0.0, 0.0, 1.0, 1.0), 0, style);
+        assert_eq!((instance.tint, instance.flash), (0x4080_8040, 0xFF12_3456));
+    }
+
+    #[test]
+    fn sort_layer_and_z() {
+        let mut instances = Instances::default();
+        let style = |layer, z| Style {
+            layer,
+            z,
+            ..Style::new()
+        };
+
+        // The texture reference is the submission order
+        push(&mut instances, 0, style(1, -5));
+        push(&mut instances, 1, style(0, 3));
+        push(&mut instances, 2, style(0, 0));
+        push(&mut instances, 3, style(1, -5));
+        push(&mut instances, 4, style(0, 3));
+        push(&mut instances, 5, style(0, 0));
+        instances.sort();
+
+        // Higher layers are drawn after lower layers regardless of z, equal keys keep their order
+        assert_eq!(
+            instances
+                .instances
+                .iter()
+                .map(|instance| instance.texture_ref)
+                .collect::<Vec<_>>(),
+            [2, 5, 1, 4, 0, 3]
+        );
+
+        // Sorted instances with the same blend mode are drawn together
+        instances.resolve_textures(|_| TextureSlot::default());
+        assert_eq!(
+            instances.batches(false).collect::<Vec<_>>(),
+            [(BlendMode::Alpha, 0, 0..6)]
+        );
+    }
+
+    #[test]
+    fn batches_screen_space() {
+        let mut instances = Instances::default();
+        let screen_space = Style {
+            screen_space: true,
+            ..Style::new()
+        };
+        push(&mut instances, 0, Style::new());
+        push(&mut instances, 1, screen_space);
+        push(&mut instances, 2, Style::new());
+        push(&mut instances, 3, screen_space);
+        instances.sort();
+        instances.resolve_textures(|_| TextureSlot::default());
+
+        // World and screen space instances are drawn separately
+        assert_eq!(
+            instances.batches(false).collect::<Vec<_>>(),
+            [(BlendMode::Alpha, 0, 0..1), (BlendMode::Alpha, 0, 2..3)]
+        );
+        assert_eq!(
+            instances.batches(true).collect::<Vec<_>>(),
+            [(BlendMode::Alpha, 0, 1..2), (BlendMode::Alpha, 0, 3..4)]
+        );
+    }
+
+    #[test]
+    fn batches_split_on_page() {
+        let mut instances = Instances::default();
+        let additive = Style {
+            blend_mode: BlendMode::Additive,
+            ..Style::new()
+        };
+
+        // Textures `0` and `1` are in page 0, texture `2` in page 1
+        for texture_ref in [0, 1, 2, 2, 0] {
+            push(&mut instances, texture_ref, Style::new());
+        }
+        for texture_ref in [2, 1] {
+            push(&mut instances, texture_ref, additive);
+        }
+        instances.resolve_textures(|texture_ref| TextureSlot {
+            page: u16::from(texture_ref == 2),
+            index: texture_ref * 10,
+        });
+
+        assert_eq!(
+            instances.batches(false).collect::<Vec<_>>(),
+            [
+                (BlendMode::Alpha, 0, 0..2),
+                (BlendMode::Alpha, 1, 2..4),
+                (BlendMode::Alpha, 0, 4..5),
+                (BlendMode::Additive, 1, 5..6),
+                (BlendMode::Additive, 0, 6..7),
+            ]
+        );
+
+        // The shader gets the index in the page
+        assert_eq!(
+            instances
+                .instances
+                .iter()
+                .map(|instance| instance.texture_index)
+                .collect::<Vec<_>>(),
+            [0, 10, 20, 20, 0, 20, 10]
+        );
+    }
+
+    #[test]
+    fn batches_single_page() {
+        let mut instances = Instances::default();
+        for texture_ref in 0..100 {
+            push(&mut instances, texture_ref, Style::new());
+        }
+        instances.resolve_textures(|_| TextureSlot::default());
+
+        // Everything in the same page is a single draw call
+        assert_eq!(
+            instances.batches(false).collect::<Vec<_>>(),
+            [(BlendMode::Alpha, 0, 0..100)]
+        );
+    }
+}
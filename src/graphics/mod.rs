@@ -1,13 +1,16 @@
 //! Graphics state handling drawing items.
 
 pub mod atlas;
+pub(crate) mod camera;
 mod data;
-mod instance;
+pub(crate) mod instance;
 mod post_processing;
 mod uniform;
+pub(crate) mod viewport;
 
 use std::{borrow::Cow, sync::Arc};
 
+use glam::Affine2;
 #[cfg(feature = "embed-assets")]
 use imgref::ImgVec;
 use wgpu::util::DeviceExt;
@@ -15,7 +18,10 @@ use winit::window::Window;
 
 #[cfg(feature = "embed-assets")]
 use crate::assets::{
-    loader::{png::PngLoader, Loader},
+    loader::{
+        png::{PngImage, PngLoader},
+        Loader,
+    },
     Id,
 };
 use crate::{
@@ -26,9 +32,10 @@ use crate::{
 use self::{
     atlas::{Atlas, TextureRef},
     data::{ScreenInfo, TexturedVertex},
-    instance::Instances,
+    instance::{BlendMode, Instances},
     post_processing::PostProcessingState,
     uniform::UniformState,
+    viewport::Viewport,
 };
 
 /// Texture format we prefer to use for everything.
@@ -47,8 +54,8 @@ pub(crate) struct Graphics {
     pub(crate) queue: wgpu::Queue,
     /// GPU surface configuration.
     pub(crate) surface_config: wgpu::SurfaceConfiguration,
-    /// Pipeline of the rendering itself.
-    pub(crate) render_pipeline: wgpu::RenderPipeline,
+    /// Pipelines of the rendering itself, one for each blend mode.
+    pub(crate) render_pipelines: [wgpu::RenderPipeline; BlendMode::ALL.len()],
     /// GPU buffer reference to the vertices of the texture squares.
     pub(crate) vertex_buffer: wgpu::Buffer,
     /// GPU buffer reference to the indices of the texture squares.
@@ -71,6 +78,10 @@ pub(crate) struct Graphics {
     pub(crate) downscale: PostProcessingState,
     /// All instances to render.
     pub(crate) instances: Instances,
+    /// Views into the world, each drawn with its own camera inside a rectangle of the buffer.
+    ///
+    /// Always contains at least one item.
+    pub(crate) viewports: Vec<Viewport>,
     /// Letterbox output `(x, y, width, height)` for the final render pass viewport.
     pub(crate) letterbox: (f32, f32, f32, f32),
     /// Background color.
@@ -82,7 +93,8 @@ pub(crate) struct Graphics {
 impl Graphics {
     /// Upload a texture to the GPU.
     pub fn upload_texture(&mut self, width: u32, height: u32, pixels: &[u32]) -> TextureRef {
-        self.atlas.add_texture(width, height, pixels, &self.queue)
+        self.atlas
+            .add_texture(width, height, pixels, &self.device, &self.queue)
     }
 
     /// Setup the GPU buffers and data structures.
@@ -160,25 +172,20 @@ impl Graphics {
         // Setup the texture atlas
         let embedded_atlas = asset_source.embedded_atlas();
         #[allow(unused_mut)]
-        let mut atlas = Atlas::new(embedded_atlas.textures.len(), &device, &queue);
+        let mut atlas = Atlas::new(embedded_atlas.textures.len(), &device);
 
         // Upload embedded assets to atlas
         #[cfg(feature = "embed-assets")]
         if !embedded_atlas.textures.is_empty() {
-            // Get the PNG reader for the atlas, using the loader
-            let mut png = PngLoader::load(
+            // Read the PNG of the atlas, using the loader
+            let png = <PngLoader as Loader<PngImage>>::load(
                 embedded_atlas.diced_atlas_png_bytes,
                 &Id::new_inline("_embedded_atlas"),
-            );
-
-            // Read the PNG
-            let mut pixels = vec![0_u32; png.output_buffer_size()];
-            let info = png
-                .next_frame(bytemuck::cast_slice_mut(&mut pixels))
-                .unwrap();
+            )
+            .unwrap_or_else(|err| panic!("{err}"));
 
             // Treat the 4 color components as a single numeric value
-            let img = ImgVec::new(pixels, info.width as usize, info.height as usize);
+            let img = ImgVec::new(png.pixels, png.width as usize, png.height as usize);
 
             // Upload all textures
             for texture in embedded_atlas.textures.values() {
@@ -187,6 +194,7 @@ impl Graphics {
                     texture.reference,
                     texture.width as u32,
                     texture.height as u32,
+                    &device,
                     &queue,
                 );
 
@@ -219,11 +227,7 @@ impl Graphics {
         // Create the uniforms
         let screen_info = UniformState::new(
             &device,
-            &ScreenInfo {
-                buffer_width,
-                buffer_height,
-                ..Default::default()
-            },
+            &ScreenInfo::new(buffer_width, buffer_height, Affine2::IDENTITY),
         );
 
         // Create a new render pipeline first
@@ -232,7 +236,8 @@ impl Graphics {
                 label: Some("Component Render Pipeline Layout"),
                 bind_group_layouts: &[
                     &atlas.bind_group_layout,
-                    &atlas.rects.bind_group_layout,
+                    // Every page has its own rectangles with the same layout
+                    &atlas.pages[0].rects.bind_group_layout,
                     &screen_info.bind_group_layout,
                 ],
                 push_constant_ranges: &[],
@@ -247,52 +252,54 @@ impl Graphics {
             )))),
         });
 
-        // Create the pipeline for rendering textures
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                buffers: &[TexturedVertex::descriptor(), Instances::descriptor()],
-                module: &shader,
-                entry_point: "vs_main",
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: match rotation_algorithm {
-                    RotationAlgorithm::CleanEdge => "fs_main_clean_edge",
-                    RotationAlgorithm::Scale3x => "fs_main_scale3x",
-                    RotationAlgorithm::Scale2x => "fs_main_scale2x",
-                    RotationAlgorithm::Diag2x => "fs_main_diag2x",
-                    RotationAlgorithm::NearestNeighbor => "fs_main_nearest_neighbor",
+        // Create the pipelines for rendering textures, one for each blend mode
+        let render_pipelines = BlendMode::ALL.map(|blend_mode| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("Render Pipeline"),
+                layout: Some(&render_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    buffers: &[TexturedVertex::descriptor(), Instances::descriptor()],
+                    module: &shader,
+                    entry_point: "vs_main",
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
                 },
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: PREFERRED_TEXTURE_FORMAT,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Cw,
-                // Irrelevant since we disable culling
-                cull_mode: None,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                // How many samples the pipeline will use
-                count: 1,
-                // Use all masks
-                mask: !0,
-                // Disable anti-aliasing
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: match rotation_algorithm {
+                        RotationAlgorithm::CleanEdge => "fs_main_clean_edge",
+                        RotationAlgorithm::Scale3x => "fs_main_scale3x",
+                        RotationAlgorithm::Scale2x => "fs_main_scale2x",
+                        RotationAlgorithm::Diag2x => "fs_main_diag2x",
+                        RotationAlgorithm::NearestNeighbor => "fs_main_nearest_neighbor",
+                    },
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: PREFERRED_TEXTURE_FORMAT,
+                        blend: Some(blend_mode.blend_state()),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Cw,
+                    // Irrelevant since we disable culling
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState {
+                    // How many samples the pipeline will use
+                    count: 1,
+                    // Use all masks
+                    mask: !0,
+                    // Disable anti-aliasing
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+            })
         });
 
         // Create the initial empty instance buffer, will be resized by the render call
@@ -343,6 +350,14 @@ impl Graphics {
         // Create the instances list
         let instances = Instances::default();
 
+        // Start with a single viewport covering the whole buffer without any camera transformation
+        let viewports = vec![Viewport::new(
+            (0.0, 0.0, buffer_width, buffer_height),
+            (buffer_width, buffer_height),
+            &screen_info,
+            &device,
+        )];
+
         // Full size letterbox, will be rescaled
         let letterbox = (0.0, 0.0, buffer_width * scaling, buffer_height * scaling);
 
@@ -355,7 +370,7 @@ impl Graphics {
             surface,
             queue,
             surface_config,
-            render_pipeline,
+            render_pipelines,
             vertex_buffer,
             index_buffer,
             instance_buffer,
@@ -365,6 +380,7 @@ impl Graphics {
             screen_info,
             downscale,
             instances,
+            viewports,
             letterbox,
             background_color,
             viewport_color,
@@ -486,6 +502,18 @@ impl Graphics {
         Some(((x - letterbox_x) / scale, (y - letterbox_y) / scale))
     }
 
+    /// Map a coordinate on the buffer to the viewport below it and the coordinate in the world of that viewport.
+    ///
+    /// When viewports overlap the viewport drawn last is used.
+    pub(crate) fn map_buffer_coordinate(&self, x: f32, y: f32) -> Option<(usize, (f32, f32))> {
+        self.viewports
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, viewport)| viewport.contains(x, y))
+            .map(|(index, viewport)| (index, viewport.buffer_to_world(x, y)))
+    }
+
     /// Render the instances if applicable.
     fn render_instances(
         &mut self,
@@ -497,12 +525,33 @@ impl Graphics {
             return;
         }
 
-        // Get the instances
-        let (instances_bytes, instances_len) = {
-            // Construct the bytes of the instances to upload
-            (self.instances.bytes(), self.instances.len())
+        // Order the instances by layer and z-index
+        self.instances.sort();
+
+        // Point the instances to the rectangles of their textures in the atlas pages
+        let atlas = &self.atlas;
+        self.instances
+            .resolve_textures(|texture_ref| atlas.slot(texture_ref));
+
+        // Upload the camera transformation of each viewport
+        let buffer_size = (self.buffer_width, self.buffer_height);
+        for viewport in &self.viewports {
+            viewport.upload(buffer_size, &self.queue);
+        }
+
+        // Scissor rectangles must be calculated for the actual size of the render target
+        let target_size = if custom_view.is_some() {
+            (self.surface_config.width, self.surface_config.height)
+        } else {
+            (
+                self.buffer_width.round() as u32,
+                self.buffer_height.round() as u32,
+            )
         };
 
+        // Construct the bytes of the instances to upload
+        let instances_bytes = self.instances.bytes();
+
         // Resize the buffer if needed
         let instance_buffer_already_pushed =
             if instances_bytes.len() as u64 > self.instance_buffer.size() {
@@ -538,17 +587,6 @@ impl Graphics {
                 occlusion_query_set: None,
             });
 
-            // Set our pipeline
-            render_pass.set_pipeline(&self.render_pipeline);
-
-            // Bind the atlas texture
-            render_pass.set_bind_group(0, &self.atlas.bind_group, &[]);
-            // Bind the atlas texture info
-            render_pass.set_bind_group(1, &self.atlas.rects.bind_group, &[]);
-
-            // Bind the screen size
-            render_pass.set_bind_group(2, &self.screen_info.bind_group, &[]);
-
             // Set the target vertices
             render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
             // Set the instances
@@ -556,8 +594,33 @@ impl Graphics {
             // Set the target indices
             render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
 
-            // Draw the instances
-            render_pass.draw_indexed(0..6, 0, 0..instances_len as u32);
+            // Draw all instances in the world once for each viewport
+            for viewport in &self.viewports {
+                // Only draw inside the rectangle of the viewport
+                let Some((x, y, width, height)) =
+                    viewport.scissor_rectangle(buffer_size, target_size)
+                else {
+                    continue;
+                };
+                render_pass.set_scissor_rect(x, y, width, height);
+
+                // Bind the screen size and camera
+                render_pass.set_bind_group(2, &viewport.screen_info.bind_group, &[]);
+
+                self.draw_batches(&mut render_pass, false);
+            }
+
+            // Draw the instances ignoring the camera once on top of all viewports
+            if let Some((x, y, width, height)) = viewport::scissor_rectangle(
+                (0.0, 0.0, self.buffer_width, self.buffer_height),
+                buffer_size,
+                target_size,
+            ) {
+                render_pass.set_scissor_rect(x, y, width, height);
+                render_pass.set_bind_group(2, &self.screen_info.bind_group, &[]);
+
+                self.draw_batches(&mut render_pass, true);
+            }
         }
 
         // Upload the instance buffer
@@ -569,6 +632,17 @@ impl Graphics {
         // Clear the instances to write a new frame
         self.instances.clear();
     }
+
+    /// Draw the instances, switching the pipeline and the atlas page for each batch with a different blend mode or page.
+    fn draw_batches<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, screen_space: bool) {
+        for (blend_mode, page, instances_range) in self.instances.batches(screen_space) {
+            render_pass.set_pipeline(&self.render_pipelines[blend_mode.index()]);
+            render_pass.set_bind_group(0, &self.atlas.pages[page].bind_group, &[]);
+            render_pass.set_bind_group(1, &self.atlas.pages[page].rects.bind_group, &[]);
+
+            render_pass.draw_indexed(0..6, 0, instances_range);
+        }
+    }
 }
 
 /// Convert an `u32` color to a WGPU [`wgpu::Color`] taking in account sRGB.
@@ -1,6 +1,6 @@
 //! State for uniform bindings.
 
-use std::{marker::PhantomData, ops::Index};
+use std::{marker::PhantomData, ops::Index, rc::Rc};
 
 use bytemuck::NoUninit;
 use wgpu::util::DeviceExt;
@@ -9,8 +9,11 @@ use wgpu::util::DeviceExt;
 ///
 /// Type must be aligned to 16 bytes.
 pub(crate) struct UniformState<T: NoUninit> {
-    pub(crate) bind_group_layout: wgpu::BindGroupLayout,
+    /// Layout, shared between all uniforms of the same type so they can be swapped in the same pipeline.
+    pub(crate) bind_group_layout: Rc<wgpu::BindGroupLayout>,
     pub(crate) bind_group: wgpu::BindGroup,
+    /// Buffer on GPU.
+    buffer: wgpu::Buffer,
     /// Store the type information.
     _phantom: PhantomData<T>,
 }
@@ -18,6 +21,40 @@ pub(crate) struct UniformState<T: NoUninit> {
 impl<T: NoUninit> UniformState<T> {
     /// Upload a new uniform.
     pub(crate) fn new(device: &wgpu::Device, initial_value: &T) -> Self {
+        // Create the bind group layout for passing the screen size
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Uniform Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        Self::with_layout(device, initial_value, Rc::new(bind_group_layout))
+    }
+
+    /// Upload a new uniform of the same type, using the same layout so it can be bound in the same pipelines.
+    pub(crate) fn new_sharing_layout(&self, device: &wgpu::Device, initial_value: &T) -> Self {
+        Self::with_layout(device, initial_value, Rc::clone(&self.bind_group_layout))
+    }
+
+    /// Upload a new value for the uniform.
+    pub(crate) fn update(&self, value: &T, queue: &wgpu::Queue) {
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(value));
+    }
+
+    /// Upload a new uniform with an existing layout.
+    fn with_layout(
+        device: &wgpu::Device,
+        initial_value: &T,
+        bind_group_layout: Rc<wgpu::BindGroupLayout>,
+    ) -> Self {
         // Convert initial value to bytes
         let contents = bytemuck::bytes_of(initial_value);
 
@@ -35,21 +72,6 @@ impl<T: NoUninit> UniformState<T> {
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
         });
 
-        // Create the bind group layout for passing the screen size
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("Uniform Bind Group Layout"),
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
-        });
-
         // Also already create the bind group, since it will be used without changing the size
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Uniform Bind Group"),
@@ -61,8 +83,9 @@ impl<T: NoUninit> UniformState<T> {
         });
 
         Self {
-            bind_group,
             bind_group_layout,
+            bind_group,
+            buffer,
             _phantom: PhantomData,
         }
     }
@@ -72,7 +95,8 @@ impl<T: NoUninit> UniformState<T> {
 ///
 /// Types must be aligned to 16 bytes.
 pub(crate) struct UniformArrayState<T: NoUninit + Default> {
-    pub(crate) bind_group_layout: wgpu::BindGroupLayout,
+    /// Layout, shared between all uniform arrays of the same type so they can be swapped in the same pipeline.
+    pub(crate) bind_group_layout: Rc<wgpu::BindGroupLayout>,
     pub(crate) bind_group: wgpu::BindGroup,
     /// Buffer on GPU.
     buffer: wgpu::Buffer,
@@ -87,22 +111,42 @@ impl<T: NoUninit + Default> UniformArrayState<T> {
     /// Maximum items in array based on the maximum amount of bytes.
     const MAX_ITEMS: u64 = Self::MAX_BYTES / std::mem::size_of::<T>() as u64;
 
-    /// Upload a new empty uniform array.
-    pub(crate) fn new(
-        preallocated_items: usize,
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
-    ) -> Self {
+    /// Create a new empty uniform array.
+    pub(crate) fn new(device: &wgpu::Device) -> Self {
+        // Create the bind group layout for passing the array
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Uniform Array Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        Self::with_layout(device, Rc::new(bind_group_layout))
+    }
+
+    /// Create a new empty uniform array of the same type, using the same layout so it can be bound in the same pipelines.
+    pub(crate) fn new_sharing_layout(&self, device: &wgpu::Device) -> Self {
+        Self::with_layout(device, Rc::clone(&self.bind_group_layout))
+    }
+
+    /// Create a new empty uniform array with an existing layout.
+    fn with_layout(device: &wgpu::Device, bind_group_layout: Rc<wgpu::BindGroupLayout>) -> Self {
         // Ensure that the data has an alignment of 16 bytes, which is needed by WASM
         assert!(
             std::mem::size_of::<T>() % 16 == 0,
             "Uniform of type '{}' is not aligned to 16 bytes",
             std::any::type_name::<T>(),
         );
-        assert!(preallocated_items < Self::MAX_ITEMS as usize);
 
         // Create the local CPU buffer
-        let local_buffer = vec![T::default(); preallocated_items];
+        let local_buffer = Vec::new();
 
         // Create the GPU buffer
         let buffer = device.create_buffer(&wgpu::BufferDescriptor {
@@ -113,30 +157,9 @@ impl<T: NoUninit + Default> UniformArrayState<T> {
             size: Self::MAX_BYTES,
         });
 
-        // Convert preallocated array to bytes
-        let data = bytemuck::cast_slice(&local_buffer);
-
-        // Push the preallocated items to the GPU
-        queue.write_buffer(&buffer, data.len() as u64, data);
-
-        // Create the bind group layout for passing the screen size
-        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("Uniform Bind Group Layout"),
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
-        });
-
         // Also already create the bind group, since it will be used without changing the size
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Uniform Bind Group"),
+            label: Some("Uniform Array Bind Group"),
             layout: &bind_group_layout,
             entries: &[wgpu::BindGroupEntry {
                 binding: 0,
@@ -153,11 +176,23 @@ impl<T: NoUninit + Default> UniformArrayState<T> {
     }
 
     /// Push and upload a value to the array of the uniform.
-    pub(crate) fn push(&mut self, value: &T, queue: &wgpu::Queue) -> u64 {
+    ///
+    /// # Returns
+    ///
+    /// - Index of the pushed value.
+    ///
+    /// # Errors
+    ///
+    /// - When the array already contains the maximum amount of items allowed by WebGL2.
+    pub(crate) fn push(&mut self, value: &T, queue: &wgpu::Queue) -> Result<u64, String> {
         // Get the old index where the item will be pushed
         let index = self.local_buffer.len() as u64;
-
-        assert!(index <= Self::MAX_ITEMS, "Uniform value out ouf range");
+        if index >= Self::MAX_ITEMS {
+            return Err(format!(
+                "uniform array is full, it can't contain more than {} items",
+                Self::MAX_ITEMS
+            ));
+        }
 
         // Push the new value
         self.local_buffer.push(*value);
@@ -168,11 +203,10 @@ impl<T: NoUninit + Default> UniformArrayState<T> {
         // Push the new value to the GPU
         queue.write_buffer(&self.buffer, index * std::mem::size_of::<T>() as u64, data);
 
-        index
+        Ok(index)
     }
 
     /// Set and upload a value to the array of the uniform.
-    #[cfg(feature = "embed-assets")]
     pub(crate) fn set(&mut self, index: usize, value: &T, queue: &wgpu::Queue) {
         // Set the new value
         self.local_buffer[index] = *value;
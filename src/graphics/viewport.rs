@@ -0,0 +1,148 @@
+//! Independent views into the world, used for split-screen rendering.
+
+use glam::{Affine2, Vec2};
+
+use super::{camera::Camera, data::ScreenInfo, uniform::UniformState};
+
+/// Rectangle inside the buffer showing the world through its own camera.
+pub(crate) struct Viewport {
+    /// Rectangle `(x, y, width, height)` inside the buffer in pixels.
+    pub(crate) rectangle: (f32, f32, f32, f32),
+    /// Camera transforming the world coordinates of the instances inside this viewport.
+    pub(crate) camera: Camera,
+    /// Uniform with the buffer size and the camera transformation for this viewport.
+    pub(crate) screen_info: UniformState<ScreenInfo>,
+}
+
+impl Viewport {
+    /// Create a new viewport without any camera transformation.
+    pub(crate) fn new(
+        rectangle: (f32, f32, f32, f32),
+        buffer_size: (f32, f32),
+        screen_info: &UniformState<ScreenInfo>,
+        device: &wgpu::Device,
+    ) -> Self {
+        let (buffer_width, buffer_height) = buffer_size;
+
+        let camera = Camera::new();
+        let screen_info = screen_info.new_sharing_layout(
+            device,
+            &ScreenInfo::new(buffer_width, buffer_height, Affine2::IDENTITY),
+        );
+
+        Self {
+            rectangle,
+            camera,
+            screen_info,
+        }
+    }
+
+    /// Size of the rectangle.
+    pub(crate) const fn size(&self) -> (f32, f32) {
+        (self.rectangle.2, self.rectangle.3)
+    }
+
+    /// Whether a coordinate on the buffer is inside the rectangle.
+    pub(crate) fn contains(&self, x: f32, y: f32) -> bool {
+        let (viewport_x, viewport_y, width, height) = self.rectangle;
+
+        x >= viewport_x && y >= viewport_y && x < viewport_x + width && y < viewport_y + height
+    }
+
+    /// Transformation from world coordinates to buffer coordinates.
+    pub(crate) fn transformation(&self) -> Affine2 {
+        let (x, y, _, _) = self.rectangle;
+
+        // Move the view of the camera to the position of the viewport
+        Affine2::from_translation(Vec2::new(x, y)) * self.camera.transformation(self.size())
+    }
+
+    /// Map a coordinate on the buffer to a coordinate in the world.
+    pub(crate) fn buffer_to_world(&self, x: f32, y: f32) -> (f32, f32) {
+        self.transformation()
+            .inverse()
+            .transform_point2(Vec2::new(x, y))
+            .into()
+    }
+
+    /// Upload the camera transformation.
+    pub(crate) fn upload(&self, buffer_size: (f32, f32), queue: &wgpu::Queue) {
+        let (buffer_width, buffer_height) = buffer_size;
+
+        self.screen_info.update(
+            &ScreenInfo::new(buffer_width, buffer_height, self.transformation()),
+            queue,
+        );
+    }
+
+    /// Scissor rectangle `(x, y, width, height)` on a render target with a different size than the buffer.
+    ///
+    /// Returns `None` when nothing of the viewport is visible.
+    pub(crate) fn scissor_rectangle(
+        &self,
+        buffer_size: (f32, f32),
+        target_size: (u32, u32),
+    ) -> Option<(u32, u32, u32, u32)> {
+        scissor_rectangle(self.rectangle, buffer_size, target_size)
+    }
+}
+
+/// Scale a rectangle `(x, y, width, height)` in the buffer to a scissor rectangle on a render target with a different size.
+///
+/// Returns `None` when nothing of the rectangle is visible.
+pub(crate) fn scissor_rectangle(
+    rectangle: (f32, f32, f32, f32),
+    buffer_size: (f32, f32),
+    target_size: (u32, u32),
+) -> Option<(u32, u32, u32, u32)> {
+    let (x, y, width, height) = rectangle;
+    let (target_width, target_height) = target_size;
+
+    // Scale the rectangle to the render target, which can be stretched differently in both directions
+    let scale_x = target_width as f32 / buffer_size.0;
+    let scale_y = target_height as f32 / buffer_size.1;
+
+    // Clamp to the render target, otherwise WGPU will panic
+    let left = ((x * scale_x).round() as u32).min(target_width);
+    let top = ((y * scale_y).round() as u32).min(target_height);
+    let right = (((x + width) * scale_x).round() as u32).min(target_width);
+    let bottom = (((y + height) * scale_y).round() as u32).min(target_height);
+
+    (right > left && bottom > top).then(|| (left, top, right - left, bottom - top))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scissor_rectangle_scaled() {
+        // Same size as the buffer
+        assert_eq!(
+            scissor_rectangle((160.0, 0.0, 160.0, 240.0), (320.0, 240.0), (320, 240)),
+            Some((160, 0, 160, 240))
+        );
+
+        // Stretched with a different aspect ratio, both halves of a vertical split must cover the whole target
+        assert_eq!(
+            scissor_rectangle((0.0, 0.0, 320.0, 120.0), (320.0, 240.0), (640, 960)),
+            Some((0, 0, 640, 480))
+        );
+        assert_eq!(
+            scissor_rectangle((0.0, 120.0, 320.0, 120.0), (320.0, 240.0), (640, 960)),
+            Some((0, 480, 640, 480))
+        );
+
+        // Clamped to the render target
+        assert_eq!(
+            scissor_rectangle((-10.0, 200.0, 400.0, 100.0), (320.0, 240.0), (640, 480)),
+            Some((0, 400, 640, 80))
+        );
+
+        // Outside of the render target
+        assert_eq!(
+            scissor_rectangle((320.0, 0.0, 10.0, 10.0), (320.0, 240.0), (640, 480)),
+            None
+        );
+    }
+}
@@ -9,7 +9,9 @@
 //! - Hot-reloadable assets, seeing your assets update live in the game when you save them is a great boost in productivity for quickly iterating on ideas.
 //! - Single-binary, all non-texture assets will be embedded directly, and textures will be diced into a single atlas map embedded in the binary when deploying.
 //! - Simple bitmap font drawing.
-//! - OGG audio playback.
+//! - OGG, WAV and FLAC audio playback, with streaming and crossfading for music tracks.
+//! - Procedurally generated retro sound effects and randomized sound groups with voice limits.
+//! - Background asset decoding with progress reporting for loading screens.
 //! - First-class gamepad support.
 //!
 //! # Goals
@@ -133,11 +135,13 @@
 //! [^escape-key]: [`Context::key_pressed`]
 
 pub mod assets;
+mod audio;
 pub mod config;
 pub mod context;
 mod graphics;
 mod input;
 mod random;
+pub mod sfxr;
 
 pub use assets::source::AssetSource;
 pub use config::Config;
@@ -478,13 +482,23 @@ impl<G: Game> ApplicationHandler<Context> for State<G> {
                         // Update the input so pressed and released events can be handled
                         ctx.input.update();
 
+                        // Duck the audio buses based on the sounds playing
+                        ctx.audio_buses.update();
+
+                        // Attenuate and pan the positional sounds
+                        ctx.spatial_audio.update();
+
                         // Handle hot reloaded assets
+                        ctx.reloaded_assets.clear();
                         #[cfg(not(target_arch = "wasm32"))]
                         assets::hot_reload::handle_changed_asset_files(ctx);
                     });
                 }
 
                 ctx.write(|ctx| {
+                    // Store the assets decoded in the background
+                    ctx.preloader.update(&ctx.asset_source);
+
                     // Set the blending factor
                     ctx.blending_factor = self.accumulator / self.config.update_delta_time;
 
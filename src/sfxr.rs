@@ -0,0 +1,915 @@
+//! Procedurally generate retro sound effects, based on [sfxr](https://www.drpetter.se/project_sfxr.html).
+//!
+//! A [`Sfxr`] describes a sound with a small amount of parameters, which is synthesized into samples at runtime.
+//! The parameters can be generated from presets, tweaked by hand, or loaded from a RON file with [`crate::Context::audio`].
+
+use std::{f32::consts::TAU, sync::Arc};
+
+use fastrand::Rng;
+use kira::{
+    sound::static_sound::{StaticSoundData, StaticSoundSettings},
+    Frame,
+};
+use nanoserde::DeRon;
+
+use crate::{
+    assets::{loadable::Loadable, loader::ron::RonLoader, AssetError, Id},
+    context::ContextInner,
+};
+
+/// Amount of samples per second of the synthesized sound.
+pub const SAMPLE_RATE: u32 = 44_100;
+
+/// Volume applied to all sounds, leaves room for the punch and the phaser before clipping.
+const MASTER_VOLUME: f32 = 0.5;
+/// Amount of sub-samples calculated for every sample to reduce aliasing.
+const SUPERSAMPLING: usize = 8;
+/// Maximum length of an envelope stage in samples.
+const MAX_ENVELOPE_STAGE_LENGTH: f32 = 100_000.0;
+/// Size of the delay buffer of the phaser.
+const PHASER_BUFFER_SIZE: usize = 1024;
+/// Amount of random values in a single period of the noise wave.
+const NOISE_BUFFER_SIZE: usize = 32;
+
+/// Waveform of the oscillator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, DeRon)]
+#[non_exhaustive]
+pub enum SfxrWave {
+    /// Square wave with a configurable duty cycle, sounds hollow and retro.
+    #[default]
+    Square,
+    /// Sawtooth wave, sounds bright and buzzy.
+    Sawtooth,
+    /// Sine wave, sounds soft and pure.
+    Sine,
+    /// White noise, for explosions and hits.
+    Noise,
+}
+
+/// Parameters of a synthesized sound effect.
+///
+/// Most values are in the range `0.0..=1.0`, values that can also go in the other direction are in the range `-1.0..=1.0`.
+///
+/// Can be loaded as audio from a RON file, any field that's not set uses its default value:
+///
+/// ```ron
+/// (wave: Square, start_frequency: 0.6, sustain: 0.05, decay: 0.3, sustain_punch: 0.4, change_amount: 0.3, change_speed: 0.6)
+/// ```
+///
+/// # Example
+///
+/// ```no_run
+/// use chuot::{sfxr::Sfxr, Context};
+///
+/// # struct Empty; impl Empty {
+/// // In `Game::update` trait implementation
+/// // ..
+/// fn update(&mut self, ctx: Context) {
+/// # let picked_up_coin = false;
+///   if picked_up_coin {
+///     // Generate a different coin sound every time
+///     let sfxr = Sfxr::pickup(chuot::random(0.0, 1_000_000.0) as u64);
+///     ctx.audio("coin").create(&sfxr);
+///     ctx.audio("coin").play();
+///   }
+/// }
+/// # }
+/// ```
+#[allow(clippy::exhaustive_structs)]
+#[derive(Debug, Clone, PartialEq, DeRon)]
+pub struct Sfxr {
+    /// Waveform of the oscillator.
+    #[nserde(default)]
+    pub wave: SfxrWave,
+    /// Time it takes for the volume to go from zero to full.
+    #[nserde(default)]
+    pub attack: f32,
+    /// Time the volume stays at full.
+    #[nserde(default = "0.3")]
+    pub sustain: f32,
+    /// Extra volume at the start of the sustain that fades out, making the sound "pop".
+    #[nserde(default)]
+    pub sustain_punch: f32,
+    /// Time it takes for the volume to go from full to zero.
+    #[nserde(default = "0.4")]
+    pub decay: f32,
+    /// Base pitch of the sound.
+    #[nserde(default = "0.3")]
+    pub start_frequency: f32,
+    /// Pitch at which the sound stops when sliding down, `0.0` never stops it.
+    #[nserde(default)]
+    pub min_frequency: f32,
+    /// Slide of the pitch, negative slides down and positive slides up.
+    #[nserde(default)]
+    pub slide: f32,
+    /// Acceleration of the slide.
+    #[nserde(default)]
+    pub delta_slide: f32,
+    /// Strength of the vibrato.
+    #[nserde(default)]
+    pub vibrato_depth: f32,
+    /// Speed of the vibrato.
+    #[nserde(default)]
+    pub vibrato_speed: f32,
+    /// Arpeggio pitch jump, negative jumps down and positive jumps up.
+    #[nserde(default)]
+    pub change_amount: f32,
+    /// How soon the arpeggio pitch jump happens.
+    #[nserde(default)]
+    pub change_speed: f32,
+    /// Width of the square wave pulse, only used by [`SfxrWave::Square`].
+    #[nserde(default)]
+    pub square_duty: f32,
+    /// Change of the square wave pulse width over time.
+    #[nserde(default)]
+    pub duty_sweep: f32,
+    /// Speed at which the pitch and arpeggio restart, `0.0` never restarts.
+    #[nserde(default)]
+    pub repeat_speed: f32,
+    /// Offset of the phaser, which mixes the sound with a delayed copy of itself.
+    #[nserde(default)]
+    pub phaser_offset: f32,
+    /// Change of the phaser offset over time.
+    #[nserde(default)]
+    pub phaser_sweep: f32,
+    /// Cutoff of the low-pass filter, `1.0` disables it.
+    #[nserde(default = "1.0")]
+    pub lp_filter_cutoff: f32,
+    /// Change of the low-pass filter cutoff over time.
+    #[nserde(default)]
+    pub lp_filter_cutoff_sweep: f32,
+    /// Resonance of the low-pass filter.
+    #[nserde(default)]
+    pub lp_filter_resonance: f32,
+    /// Cutoff of the high-pass filter, `0.0` disables it.
+    #[nserde(default)]
+    pub hp_filter_cutoff: f32,
+    /// Change of the high-pass filter cutoff over time.
+    #[nserde(default)]
+    pub hp_filter_cutoff_sweep: f32,
+    /// Volume of the sound.
+    #[nserde(default = "0.5")]
+    pub volume: f32,
+    /// Seed for the random values of the noise wave.
+    #[nserde(default)]
+    pub seed: u64,
+}
+
+impl Sfxr {
+    /// Generate a coin or item pickup sound.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - Seed for the random parameters, the same seed always generates the same sound.
+    #[inline]
+    #[must_use]
+    pub fn pickup(seed: u64) -> Self {
+        let mut rng = Rng::with_seed(seed);
+        let mut sfxr = Self::with_seed(seed);
+
+        sfxr.start_frequency = 0.4 + random(&mut rng, 0.5);
+        sfxr.sustain = random(&mut rng, 0.1);
+        sfxr.decay = 0.1 + random(&mut rng, 0.4);
+        sfxr.sustain_punch = 0.3 + random(&mut rng, 0.3);
+        if rng.bool() {
+            sfxr.change_speed = 0.5 + random(&mut rng, 0.2);
+            sfxr.change_amount = 0.2 + random(&mut rng, 0.4);
+        }
+
+        sfxr
+    }
+
+    /// Generate a laser or shooting sound.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - Seed for the random parameters, the same seed always generates the same sound.
+    #[inline]
+    #[must_use]
+    pub fn laser(seed: u64) -> Self {
+        let mut rng = Rng::with_seed(seed);
+        let mut sfxr = Self::with_seed(seed);
+
+        // Prefer square and sawtooth waves
+        sfxr.wave = match rng.u8(0..3) {
+            0 => SfxrWave::Square,
+            1 => SfxrWave::Sawtooth,
+            _ if rng.bool() => SfxrWave::Square,
+            _ => SfxrWave::Sine,
+        };
+
+        sfxr.start_frequency = 0.5 + random(&mut rng, 0.5);
+        sfxr.min_frequency = (sfxr.start_frequency - 0.2 - random(&mut rng, 0.6)).max(0.2);
+        sfxr.slide = -0.15 - random(&mut rng, 0.2);
+        if rng.u8(0..3) == 0 {
+            sfxr.start_frequency = 0.3 + random(&mut rng, 0.6);
+            sfxr.min_frequency = random(&mut rng, 0.1);
+            sfxr.slide = -0.35 - random(&mut rng, 0.3);
+        }
+
+        if rng.bool() {
+            sfxr.square_duty = random(&mut rng, 0.5);
+            sfxr.duty_sweep = random(&mut rng, 0.2);
+        } else {
+            sfxr.square_duty = 0.4 + random(&mut rng, 0.5);
+            sfxr.duty_sweep = -random(&mut rng, 0.7);
+        }
+
+        sfxr.sustain = 0.1 + random(&mut rng, 0.2);
+        sfxr.decay = random(&mut rng, 0.4);
+        if rng.bool() {
+            sfxr.sustain_punch = random(&mut rng, 0.3);
+        }
+
+        if rng.u8(0..3) == 0 {
+            sfxr.phaser_offset = random(&mut rng, 0.2);
+            sfxr.phaser_sweep = -random(&mut rng, 0.2);
+        }
+
+        if rng.bool() {
+            sfxr.hp_filter_cutoff = random(&mut rng, 0.3);
+        }
+
+        sfxr
+    }
+
+    /// Generate an explosion sound.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - Seed for the random parameters, the same seed always generates the same sound.
+    #[inline]
+    #[must_use]
+    pub fn explosion(seed: u64) -> Self {
+        let mut rng = Rng::with_seed(seed);
+        let mut sfxr = Self::with_seed(seed);
+
+        sfxr.wave = SfxrWave::Noise;
+        if rng.bool() {
+            sfxr.start_frequency = 0.1 + random(&mut rng, 0.4);
+            sfxr.slide = -0.1 + random(&mut rng, 0.4);
+        } else {
+            sfxr.start_frequency = 0.2 + random(&mut rng, 0.7);
+            sfxr.slide = -0.2 - random(&mut rng, 0.2);
+        }
+        sfxr.start_frequency *= sfxr.start_frequency;
+        if rng.u8(0..5) == 0 {
+            sfxr.slide = 0.0;
+        }
+
+        if rng.u8(0..3) == 0 {
+            sfxr.repeat_speed = 0.3 + random(&mut rng, 0.5);
+        }
+
+        sfxr.sustain = 0.1 + random(&mut rng, 0.3);
+        sfxr.decay = random(&mut rng, 0.5);
+        if rng.bool() {
+            sfxr.phaser_offset = -0.3 + random(&mut rng, 0.9);
+            sfxr.phaser_sweep = -random(&mut rng, 0.3);
+        }
+        sfxr.sustain_punch = 0.2 + random(&mut rng, 0.6);
+
+        if rng.bool() {
+            sfxr.vibrato_depth = random(&mut rng, 0.7);
+            sfxr.vibrato_speed = random(&mut rng, 0.6);
+        }
+
+        if rng.u8(0..3) == 0 {
+            sfxr.change_speed = 0.6 + random(&mut rng, 0.3);
+            sfxr.change_amount = 0.8 - random(&mut rng, 1.6);
+        }
+
+        sfxr
+    }
+
+    /// Generate a power-up sound.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - Seed for the random parameters, the same seed always generates the same sound.
+    #[inline]
+    #[must_use]
+    pub fn powerup(seed: u64) -> Self {
+        let mut rng = Rng::with_seed(seed);
+        let mut sfxr = Self::with_seed(seed);
+
+        if rng.bool() {
+            sfxr.wave = SfxrWave::Sawtooth;
+        } else {
+            sfxr.square_duty = random(&mut rng, 0.6);
+        }
+
+        sfxr.start_frequency = 0.2 + random(&mut rng, 0.3);
+        if rng.bool() {
+            sfxr.slide = 0.1 + random(&mut rng, 0.4);
+            sfxr.repeat_speed = 0.4 + random(&mut rng, 0.4);
+        } else {
+            sfxr.slide = 0.05 + random(&mut rng, 0.2);
+            if rng.bool() {
+                sfxr.vibrato_depth = random(&mut rng, 0.7);
+                sfxr.vibrato_speed = random(&mut rng, 0.6);
+            }
+        }
+
+        sfxr.sustain = random(&mut rng, 0.4);
+        sfxr.decay = 0.1 + random(&mut rng, 0.4);
+
+        sfxr
+    }
+
+    /// Generate a hit or hurt sound.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - Seed for the random parameters, the same seed always generates the same sound.
+    #[inline]
+    #[must_use]
+    pub fn hit(seed: u64) -> Self {
+        let mut rng = Rng::with_seed(seed);
+        let mut sfxr = Self::with_seed(seed);
+
+        sfxr.wave = match rng.u8(0..3) {
+            0 => SfxrWave::Square,
+            1 => SfxrWave::Sawtooth,
+            _ => SfxrWave::Noise,
+        };
+        if sfxr.wave == SfxrWave::Square {
+            sfxr.square_duty = random(&mut rng, 0.6);
+        }
+
+        sfxr.start_frequency = 0.2 + random(&mut rng, 0.6);
+        sfxr.slide = -0.3 - random(&mut rng, 0.4);
+        sfxr.sustain = random(&mut rng, 0.1);
+        sfxr.decay = 0.1 + random(&mut rng, 0.2);
+        if rng.bool() {
+            sfxr.hp_filter_cutoff = random(&mut rng, 0.3);
+        }
+
+        sfxr
+    }
+
+    /// Generate a jump sound.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - Seed for the random parameters, the same seed always generates the same sound.
+    #[inline]
+    #[must_use]
+    pub fn jump(seed: u64) -> Self {
+        let mut rng = Rng::with_seed(seed);
+        let mut sfxr = Self::with_seed(seed);
+
+        sfxr.square_duty = random(&mut rng, 0.6);
+        sfxr.start_frequency = 0.3 + random(&mut rng, 0.3);
+        sfxr.slide = 0.1 + random(&mut rng, 0.2);
+        sfxr.sustain = 0.1 + random(&mut rng, 0.3);
+        sfxr.decay = 0.1 + random(&mut rng, 0.2);
+        if rng.bool() {
+            sfxr.hp_filter_cutoff = random(&mut rng, 0.3);
+        }
+        if rng.bool() {
+            sfxr.lp_filter_cutoff = 1.0 - random(&mut rng, 0.6);
+        }
+
+        sfxr
+    }
+
+    /// Generate a short blip for menus and dialogs.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - Seed for the random parameters, the same seed always generates the same sound.
+    #[inline]
+    #[must_use]
+    pub fn blip(seed: u64) -> Self {
+        let mut rng = Rng::with_seed(seed);
+        let mut sfxr = Self::with_seed(seed);
+
+        if rng.bool() {
+            sfxr.wave = SfxrWave::Sawtooth;
+        } else {
+            sfxr.square_duty = random(&mut rng, 0.6);
+        }
+
+        sfxr.start_frequency = 0.2 + random(&mut rng, 0.4);
+        sfxr.sustain = 0.1 + random(&mut rng, 0.1);
+        sfxr.decay = random(&mut rng, 0.2);
+        sfxr.hp_filter_cutoff = 0.1;
+
+        sfxr
+    }
+
+    /// Synthesize the sound into mono samples.
+    ///
+    /// The output is always the same for the same parameters.
+    ///
+    /// # Returns
+    ///
+    /// - Samples in the range `-1.0..=1.0` at [`SAMPLE_RATE`] samples per second.
+    #[inline]
+    #[must_use]
+    pub fn samples(&self) -> Vec<f32> {
+        Synthesizer::new(self).collect()
+    }
+
+    /// Synthesize the sound into kira sound data.
+    pub(crate) fn sound_data(&self) -> StaticSoundData {
+        let frames = self
+            .samples()
+            .into_iter()
+            .map(Frame::from_mono)
+            .collect::<Arc<[_]>>();
+
+        StaticSoundData {
+            sample_rate: SAMPLE_RATE,
+            frames,
+            settings: StaticSoundSettings::default(),
+            slice: None,
+        }
+    }
+
+    /// Default parameters with a seed for the noise.
+    fn with_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for Sfxr {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            wave: SfxrWave::Square,
+            attack: 0.0,
+            sustain: 0.3,
+            sustain_punch: 0.0,
+            decay: 0.4,
+            start_frequency: 0.3,
+            min_frequency: 0.0,
+            slide: 0.0,
+            delta_slide: 0.0,
+            vibrato_depth: 0.0,
+            vibrato_speed: 0.0,
+            change_amount: 0.0,
+            change_speed: 0.0,
+            square_duty: 0.0,
+            duty_sweep: 0.0,
+            repeat_speed: 0.0,
+            phaser_offset: 0.0,
+            phaser_sweep: 0.0,
+            lp_filter_cutoff: 1.0,
+            lp_filter_cutoff_sweep: 0.0,
+            lp_filter_resonance: 0.0,
+            hp_filter_cutoff: 0.0,
+            hp_filter_cutoff_sweep: 0.0,
+            volume: 0.5,
+            seed: 0,
+        }
+    }
+}
+
+impl Loadable for Sfxr {
+    #[inline]
+    fn load_if_exists(id: &Id, ctx: &mut ContextInner) -> Result<Option<Self>, AssetError>
+    where
+        Self: Sized,
+    {
+        ctx.asset_source.load_if_exists::<RonLoader, _>(id)
+    }
+}
+
+/// State of the synthesizer, iterating over the samples.
+struct Synthesizer<'sfxr> {
+    /// Parameters of the sound.
+    sfxr: &'sfxr Sfxr,
+    /// Random number generator for the noise.
+    rng: Rng,
+    /// Whether the sound finished.
+    finished: bool,
+    /// Position in the period of the oscillator.
+    phase: usize,
+    /// Length of the period of the oscillator in sub-samples.
+    period: f64,
+    /// Longest period before the sound stops.
+    max_period: f64,
+    /// Multiplication of the period every sample.
+    slide: f64,
+    /// Change of the slide every sample.
+    delta_slide: f64,
+    /// Current width of the square wave pulse.
+    square_duty: f32,
+    /// Change of the square wave pulse width every sample.
+    square_slide: f32,
+    /// Current stage of the envelope, attack, sustain or decay.
+    envelope_stage: usize,
+    /// Samples since the start of the current envelope stage.
+    envelope_time: usize,
+    /// Length of each envelope stage in samples.
+    envelope_length: [usize; 3],
+    /// Current offset of the phaser.
+    phaser_phase: f32,
+    /// Change of the phaser offset every sample.
+    phaser_delta: f32,
+    /// Position in the phaser buffer.
+    phaser_position: usize,
+    /// Delayed samples for the phaser.
+    phaser_buffer: [f32; PHASER_BUFFER_SIZE],
+    /// Random values of a single period of noise.
+    noise_buffer: [f32; NOISE_BUFFER_SIZE],
+    /// Output of the low-pass filter.
+    low_pass: f32,
+    /// Change of the output of the low-pass filter.
+    low_pass_delta: f32,
+    /// Strength of the low-pass filter.
+    low_pass_width: f32,
+    /// Change of the low-pass filter strength every sample.
+    low_pass_width_delta: f32,
+    /// Damping of the low-pass filter.
+    low_pass_damping: f32,
+    /// Output of the high-pass filter.
+    high_pass: f32,
+    /// Strength of the high-pass filter.
+    high_pass_cutoff: f32,
+    /// Change of the high-pass filter strength every sample.
+    high_pass_cutoff_delta: f32,
+    /// Position in the vibrato wave.
+    vibrato_phase: f64,
+    /// Change of the vibrato position every sample.
+    vibrato_speed: f64,
+    /// Strength of the vibrato.
+    vibrato_amplitude: f64,
+    /// Samples since the start or the last repeat.
+    repeat_time: usize,
+    /// Samples after which the sound repeats, `0` never repeats.
+    repeat_limit: usize,
+    /// Samples since the start or the last repeat for the arpeggio.
+    arpeggio_time: usize,
+    /// Samples after which the arpeggio pitch jump happens, `0` when already happened.
+    arpeggio_limit: usize,
+    /// Multiplication of the period when the arpeggio pitch jump happens.
+    arpeggio_modulation: f64,
+}
+
+impl<'sfxr> Synthesizer<'sfxr> {
+    /// Setup the synthesizer for the parameters.
+    fn new(sfxr: &'sfxr Sfxr) -> Self {
+        let mut rng = Rng::with_seed(sfxr.seed);
+
+        // Envelope
+        let stage_length = |length: f32| (length * length * MAX_ENVELOPE_STAGE_LENGTH) as usize;
+        let envelope_length = [
+            stage_length(sfxr.attack),
+            stage_length(sfxr.sustain),
+            stage_length(sfxr.decay),
+        ];
+
+        // Phaser
+        let phaser_phase = sfxr.phaser_offset.powi(2).copysign(sfxr.phaser_offset) * 1020.0;
+        let phaser_delta = sfxr.phaser_sweep.powi(2).copysign(sfxr.phaser_sweep);
+
+        // Noise
+        let noise_buffer = std::array::from_fn(|_| rng.f32().mul_add(2.0, -1.0));
+
+        // Filters
+        let low_pass_width = sfxr.lp_filter_cutoff.powi(3) * 0.1;
+        let low_pass_width_delta = sfxr.lp_filter_cutoff_sweep.mul_add(0.0001, 1.0);
+        let low_pass_damping = (5.0 / sfxr.lp_filter_resonance.powi(2).mul_add(20.0, 1.0)
+            * (0.01 + low_pass_width))
+            .min(0.8);
+        let high_pass_cutoff = sfxr.hp_filter_cutoff.powi(2) * 0.1;
+        let high_pass_cutoff_delta = sfxr.hp_filter_cutoff_sweep.mul_add(0.0003, 1.0);
+
+        // Vibrato
+        let vibrato_speed = f64::from(sfxr.vibrato_speed).powi(2) * 0.01;
+        let vibrato_amplitude = f64::from(sfxr.vibrato_depth) * 0.5;
+
+        // Repeat
+        let repeat_limit = if sfxr.repeat_speed == 0.0 {
+            0
+        } else {
+            (1.0 - sfxr.repeat_speed).powi(2).mul_add(20_000.0, 32.0) as usize
+        };
+
+        let mut synthesizer = Self {
+            sfxr,
+            rng,
+            finished: false,
+            phase: 0,
+            period: 0.0,
+            max_period: 0.0,
+            slide: 0.0,
+            delta_slide: 0.0,
+            square_duty: 0.0,
+            square_slide: 0.0,
+            envelope_stage: 0,
+            envelope_time: 0,
+            envelope_length,
+            phaser_phase,
+            phaser_delta,
+            phaser_position: 0,
+            phaser_buffer: [0.0; PHASER_BUFFER_SIZE],
+            noise_buffer,
+            low_pass: 0.0,
+            low_pass_delta: 0.0,
+            low_pass_width,
+            low_pass_width_delta,
+            low_pass_damping,
+            high_pass: 0.0,
+            high_pass_cutoff,
+            high_pass_cutoff_delta,
+            vibrato_phase: 0.0,
+            vibrato_speed,
+            vibrato_amplitude,
+            repeat_time: 0,
+            repeat_limit,
+            arpeggio_time: 0,
+            arpeggio_limit: 0,
+            arpeggio_modulation: 0.0,
+        };
+        synthesizer.restart();
+
+        synthesizer
+    }
+
+    /// Reset the pitch and arpeggio, used at the start and when repeating.
+    fn restart(&mut self) {
+        let sfxr = self.sfxr;
+
+        // Pitch
+        let start_frequency = f64::from(sfxr.start_frequency);
+        let min_frequency = f64::from(sfxr.min_frequency);
+        self.period = 100.0 / start_frequency.mul_add(start_frequency, 0.001);
+        self.max_period = 100.0 / min_frequency.mul_add(min_frequency, 0.001);
+        self.slide = f64::from(sfxr.slide).powi(3).mul_add(-0.01, 1.0);
+        self.delta_slide = -f64::from(sfxr.delta_slide).powi(3) * 0.000_001;
+
+        // Square wave duty
+        self.square_duty = sfxr.square_duty.mul_add(-0.5, 0.5);
+        self.square_slide = -sfxr.duty_sweep * 0.000_05;
+
+        // Arpeggio
+        let change_amount = f64::from(sfxr.change_amount);
+        self.arpeggio_modulation = if change_amount >= 0.0 {
+            change_amount.powi(2).mul_add(-0.9, 1.0)
+        } else {
+            change_amount.powi(2).mul_add(10.0, 1.0)
+        };
+        self.arpeggio_time = 0;
+        self.arpeggio_limit = if sfxr.change_speed >= 1.0 {
+            0
+        } else {
+            (1.0 - sfxr.change_speed).powi(2).mul_add(20_000.0, 32.0) as usize
+        };
+    }
+
+    /// Calculate a single sub-sample of the oscillator.
+    fn oscillate(&mut self, period: usize) -> f32 {
+        self.phase += 1;
+        if self.phase >= period {
+            self.phase %= period;
+
+            // Generate new noise every period
+            if self.sfxr.wave == SfxrWave::Noise {
+                for noise in &mut self.noise_buffer {
+                    *noise = self.rng.f32().mul_add(2.0, -1.0);
+                }
+            }
+        }
+
+        let position = self.phase as f32 / period as f32;
+        match self.sfxr.wave {
+            SfxrWave::Square => {
+                if position < self.square_duty {
+                    0.5
+                } else {
+                    -0.5
+                }
+            }
+            SfxrWave::Sawtooth => position.mul_add(-2.0, 1.0),
+            SfxrWave::Sine => (position * TAU).sin(),
+            SfxrWave::Noise => self
+                .noise_buffer
+                .get(self.phase * NOISE_BUFFER_SIZE / period)
+                .copied()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Apply the filters and the phaser to a sub-sample.
+    fn apply_filters(&mut self, sample: f32, phaser_offset: usize) -> f32 {
+        // Low-pass filter
+        let previous_low_pass = self.low_pass;
+        self.low_pass_width = (self.low_pass_width * self.low_pass_width_delta).clamp(0.0, 0.1);
+        if self.sfxr.lp_filter_cutoff < 1.0 {
+            self.low_pass_delta += (sample - self.low_pass) * self.low_pass_width;
+            self.low_pass_delta -= self.low_pass_delta * self.low_pass_damping;
+        } else {
+            self.low_pass = sample;
+            self.low_pass_delta = 0.0;
+        }
+        self.low_pass += self.low_pass_delta;
+
+        // High-pass filter
+        self.high_pass += self.low_pass - previous_low_pass;
+        self.high_pass -= self.high_pass * self.high_pass_cutoff;
+        let sample = self.high_pass;
+
+        // Phaser, mix with a delayed copy
+        let mask = PHASER_BUFFER_SIZE - 1;
+        self.phaser_buffer[self.phaser_position & mask] = sample;
+        let delayed =
+            self.phaser_buffer[(self.phaser_position + PHASER_BUFFER_SIZE - phaser_offset) & mask];
+        self.phaser_position = (self.phaser_position + 1) & mask;
+
+        sample + delayed
+    }
+}
+
+impl Iterator for Synthesizer<'_> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        // Restart the pitch
+        self.repeat_time += 1;
+        if self.repeat_limit != 0 && self.repeat_time >= self.repeat_limit {
+            self.repeat_time = 0;
+            self.restart();
+        }
+
+        // Jump the pitch once
+        self.arpeggio_time += 1;
+        if self.arpeggio_limit != 0 && self.arpeggio_time >= self.arpeggio_limit {
+            self.arpeggio_limit = 0;
+            self.period *= self.arpeggio_modulation;
+        }
+
+        // Slide the pitch
+        self.slide += self.delta_slide;
+        self.period *= self.slide;
+        if self.period > self.max_period {
+            self.period = self.max_period;
+
+            // Stop when the pitch slid below the minimum frequency
+            if self.sfxr.min_frequency > 0.0 {
+                self.finished = true;
+            }
+        }
+
+        // Apply the vibrato
+        let mut period = self.period;
+        if self.vibrato_amplitude > 0.0 {
+            self.vibrato_phase += self.vibrato_speed;
+            period *= self
+                .vibrato_phase
+                .sin()
+                .mul_add(self.vibrato_amplitude, 1.0);
+        }
+        let period = (period as usize).max(8);
+
+        // Sweep the square wave duty
+        self.square_duty = (self.square_duty + self.square_slide).clamp(0.0, 0.5);
+
+        // Advance the envelope
+        self.envelope_time += 1;
+        if self
+            .envelope_length
+            .get(self.envelope_stage)
+            .is_some_and(|length| self.envelope_time > *length)
+        {
+            self.envelope_time = 0;
+            self.envelope_stage += 1;
+        }
+        let stage_progress = |length: usize| self.envelope_time as f32 / length.max(1) as f32;
+        let envelope_volume = match self.envelope_length {
+            [attack, _, _] if self.envelope_stage == 0 => stage_progress(attack),
+            [_, sustain, _] if self.envelope_stage == 1 => {
+                (1.0 - stage_progress(sustain)).mul_add(2.0 * self.sfxr.sustain_punch, 1.0)
+            }
+            [_, _, decay] if self.envelope_stage == 2 => 1.0 - stage_progress(decay),
+            // All stages are finished
+            _ => {
+                self.finished = true;
+
+                return None;
+            }
+        };
+
+        // Sweep the phaser
+        self.phaser_phase += self.phaser_delta;
+        let phaser_offset = (self.phaser_phase.abs() as usize).min(PHASER_BUFFER_SIZE - 1);
+
+        // Sweep the high-pass filter
+        if self.sfxr.hp_filter_cutoff_sweep != 0.0 {
+            self.high_pass_cutoff =
+                (self.high_pass_cutoff * self.high_pass_cutoff_delta).clamp(0.000_01, 0.1);
+        }
+
+        // Average multiple sub-samples to reduce aliasing
+        let mut sample = 0.0;
+        for _ in 0..SUPERSAMPLING {
+            let sub_sample = self.oscillate(period);
+            sample += self.apply_filters(sub_sample, phaser_offset) * envelope_volume;
+        }
+        sample /= SUPERSAMPLING as f32;
+
+        Some((sample * self.sfxr.volume * MASTER_VOLUME).clamp(-1.0, 1.0))
+    }
+}
+
+/// Random number between `0.0` and `max`.
+fn random(rng: &mut Rng, max: f32) -> f32 {
+    rng.f32() * max
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_are_deterministic() {
+        // The same seed should always generate the same parameters and samples
+        for preset in [
+            Sfxr::pickup,
+            Sfxr::laser,
+            Sfxr::explosion,
+            Sfxr::powerup,
+            Sfxr::hit,
+            Sfxr::jump,
+            Sfxr::blip,
+        ] {
+            for seed in 0..4 {
+                assert_eq!(preset(seed), preset(seed));
+                assert_eq!(preset(seed).samples(), preset(seed).samples());
+            }
+        }
+
+        // Different seeds should generate different sounds
+        assert_ne!(Sfxr::explosion(1).samples(), Sfxr::explosion(2).samples());
+    }
+
+    #[test]
+    fn envelope_length() {
+        // Without a frequency limit the sound lasts exactly as long as the envelope
+        let sfxr = Sfxr {
+            attack: 0.1,
+            sustain: 0.2,
+            decay: 0.3,
+            ..Sfxr::default()
+        };
+        // The sustain and decay stages both last one sample longer
+        let expected = [0.1_f32, 0.2, 0.3]
+            .iter()
+            .map(|length| (length * length * MAX_ENVELOPE_STAGE_LENGTH) as usize)
+            .sum::<usize>()
+            + 2;
+        assert_eq!(sfxr.samples().len(), expected);
+    }
+
+    #[test]
+    fn samples_in_range() {
+        // Extreme parameters should never produce samples out of the range or NaN
+        let sfxr = Sfxr {
+            wave: SfxrWave::Sawtooth,
+            sustain_punch: 1.0,
+            lp_filter_cutoff: 0.3,
+            lp_filter_resonance: 1.0,
+            volume: 1.0,
+            ..Sfxr::default()
+        };
+        assert!(sfxr
+            .samples()
+            .iter()
+            .all(|sample| (-1.0..=1.0).contains(sample)));
+    }
+
+    #[test]
+    fn min_frequency_stops_sound() {
+        // Sliding down below the minimum frequency should stop the sound before the envelope finishes
+        let sliding = Sfxr {
+            slide: -0.5,
+            min_frequency: 0.2,
+            ..Sfxr::default()
+        };
+        assert!(sliding.samples().len() < Sfxr::default().samples().len());
+    }
+
+    #[test]
+    fn load_from_ron() {
+        // Missing fields should use the defaults
+        let sfxr: Sfxr = DeRon::deserialize_ron("(wave: Noise, decay: 0.1)").unwrap();
+        assert_eq!(
+            sfxr,
+            Sfxr {
+                wave: SfxrWave::Noise,
+                decay: 0.1,
+                ..Sfxr::default()
+            }
+        );
+    }
+}